@@ -1,12 +1,17 @@
 use tokio::time::Instant;
 
-use crate::error::ValidationErrorKind;
+use crate::{error::ValidationErrorKind, filter::BlobFilterStats};
+
+use futures::stream::{self, Stream};
+use memmap2::Advice;
 
 use super::prelude::*;
 
 use super::{header::Header, index::IndexTrait};
 
 pub(crate) const BLOB_INDEX_FILE_EXTENSION: &str = "index";
+// number of record headers read at once by `RecordsTail`
+const TAIL_PAGE_SIZE: usize = 1_000;
 
 /// A [`Blob`] struct representing file with records,
 /// provides methods for read/write access by key
@@ -19,6 +24,7 @@ pub struct Blob<K: Key> {
     name: FileName,
     file: File,
     current_offset: Arc<Mutex<u64>>,
+    meta_index: Option<MetaIndex>,
     key_type_marker: PhantomData<K>,
 }
 
@@ -36,6 +42,7 @@ impl<K: Key + 'static> Blob<K> {
         index_config: IndexConfig,
     ) -> Result<Self> {
         let file = File::create(name.to_path(), ioring.clone()).await?;
+        let meta_index = MetaIndex::new(&index_config.meta_indexes);
         let index = Self::create_index(name.clone(), ioring, index_config);
         let current_offset = Arc::new(Mutex::new(0));
         let header = Header::new();
@@ -45,6 +52,7 @@ impl<K: Key + 'static> Blob<K> {
             name,
             file,
             current_offset,
+            meta_index,
             key_type_marker: PhantomData,
         };
         blob.write_header().await?;
@@ -80,19 +88,50 @@ impl<K: Key + 'static> Blob<K> {
             self.fsyncdata()
                 .await
                 .with_context(|| "Blob file dump failed!")?;
-            self.index
+            let dumped = self
+                .index
                 .dump()
                 .await
-                .with_context(|| "Blob index file dump failed!")
+                .with_context(|| "Blob index file dump failed!")?;
+            // meta index of closed blob is read from file on lookup
+            if let Some(meta_index) = self.meta_index.take() {
+                let index_hash = self.index.records_hash().unwrap_or_default();
+                meta_index
+                    .dump(&self.meta_index_name(), self.file.ioring(), index_hash)
+                    .await
+                    .with_context(|| "Blob meta index file dump failed!")?;
+            }
+            if self.index.config().mmap {
+                // records are read by key, so there is no point in readahead
+                self.file.enable_mmap(Advice::Random);
+            }
+            Ok(dumped)
         }
     }
 
     pub(crate) async fn load_index(&mut self) -> Result<()> {
+        let names = self.index.config().meta_indexes.clone();
+        // saved meta index is checked against index file, so it's read before index load
+        let saved_meta_index = if names.is_empty() || self.meta_index.is_some() {
+            None
+        } else {
+            self.read_meta_index(&names).await.unwrap_or_else(|e| {
+                warn!("error loading meta index of {}: {}", self.name, e);
+                None
+            })
+        };
         if let Err(e) = self.index.load().await {
             warn!("error loading index: {}, regenerating", e);
             self.index.clear();
             self.try_regenerate_index().await?;
         }
+        if !names.is_empty() && self.meta_index.is_none() {
+            let meta_index = match saved_meta_index {
+                Some(meta_index) => meta_index,
+                None => self.build_meta_index(&names).await?,
+            };
+            self.meta_index = Some(meta_index);
+        }
         Ok(())
     }
 
@@ -100,10 +139,13 @@ impl<K: Key + 'static> Blob<K> {
         Box::new(self)
     }
 
+    /// Opens existing blob. If `load_filter` is false, bloom filter of the dumped index
+    /// isn't read from disk and stays offloaded.
     pub(crate) async fn from_file(
         path: PathBuf,
         ioring: Option<Rio>,
         index_config: IndexConfig,
+        load_filter: bool,
     ) -> Result<Self> {
         let now = Instant::now();
         let file = File::open(&path, ioring.clone()).await?;
@@ -121,33 +163,40 @@ impl<K: Key + 'static> Blob<K> {
         let mut is_index_corrupted = false;
         let index = if index_name.exists() {
             trace!("file exists");
-            Index::from_file(index_name.clone(), index_config.clone(), ioring.clone())
-                .await
-                .or_else(|error| {
-                    if let Some(io_error) = error.downcast_ref::<IOError>() {
-                        match io_error.kind() {
-                            IOErrorKind::PermissionDenied | IOErrorKind::Other => {
-                                warn!("index cannot be regenerated due to error: {}", io_error);
-                                return Err(error);
-                            }
-                            _ => {}
+            Index::from_file(
+                index_name.clone(),
+                index_config.clone(),
+                ioring.clone(),
+                load_filter,
+            )
+            .await
+            .or_else(|error| {
+                if let Some(io_error) = error.downcast_ref::<IOError>() {
+                    match io_error.kind() {
+                        IOErrorKind::PermissionDenied | IOErrorKind::Other => {
+                            warn!("index cannot be regenerated due to error: {}", io_error);
+                            return Err(error);
                         }
+                        _ => {}
                     }
-                    is_index_corrupted = true;
-                    Ok(Index::new(index_name, ioring, index_config))
-                })?
+                }
+                is_index_corrupted = true;
+                Ok(Index::new(index_name, ioring, index_config))
+            })?
         } else {
             trace!("file not found, create new");
             Index::new(index_name, ioring, index_config)
         };
         trace!("index initialized");
         let header_size = bincode::serialized_size(&header)?;
+        let meta_names = index.config().meta_indexes.clone();
         let mut blob = Self {
             header,
             file,
             name,
             index,
             current_offset: Arc::new(Mutex::new(size)),
+            meta_index: None,
             key_type_marker: PhantomData,
         };
         trace!("call update index");
@@ -158,6 +207,12 @@ impl<K: Key + 'static> Blob<K> {
         } else {
             warn!("empty or corrupted blob: {:?}", path);
         }
+        blob.load_meta_index(&meta_names)
+            .await
+            .context("failed to load meta index")?;
+        if blob.index.on_disk() && blob.index.config().mmap {
+            blob.file.enable_mmap(Advice::Random);
+        }
         trace!("check data consistency");
         Self::check_data_consistency();
         info!(
@@ -204,11 +259,72 @@ impl<K: Key + 'static> Blob<K> {
         Ok(())
     }
 
+    fn meta_index_name(&self) -> FileName {
+        let mut name = self.name.clone();
+        name.extension = BLOB_META_INDEX_FILE_EXTENSION.to_owned();
+        name
+    }
+
+    // Meta index of blob with dumped index is kept only in file, otherwise it's kept in memory
+    // and saved together with the index.
+    async fn load_meta_index(&mut self, names: &[String]) -> Result<()> {
+        if names.is_empty() {
+            return Ok(());
+        }
+        let name = self.meta_index_name();
+        if self.index.on_disk() {
+            match self.read_meta_index(names).await {
+                Ok(Some(_)) => return Ok(()),
+                Ok(None) => warn!("meta index {} is outdated, regenerating", name),
+                Err(e) => warn!("error loading meta index {}: {}, regenerating", name, e),
+            }
+        }
+        let meta_index = self.build_meta_index(names).await?;
+        debug!("meta index successfully generated: {}", name);
+        if let Some(index_hash) = self.index.records_hash() {
+            meta_index
+                .dump(&name, self.file.ioring(), index_hash)
+                .await?;
+        } else {
+            self.meta_index = Some(meta_index);
+        }
+        Ok(())
+    }
+
+    // Returns `None` if index isn't dumped or saved meta index doesn't match it.
+    async fn read_meta_index(&self, names: &[String]) -> Result<Option<MetaIndex>> {
+        let name = self.meta_index_name();
+        match self.index.records_hash() {
+            Some(index_hash) if name.exists() => {
+                MetaIndex::from_file(&name, self.file.ioring(), names, index_hash).await
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn build_meta_index(&self, names: &[String]) -> Result<MetaIndex> {
+        let mut meta_index = MetaIndex::new(names).expect("meta attributes are not empty");
+        let raw_r = self
+            .raw_records()
+            .await
+            .context("failed to read raw records")?;
+        if let Some(headers) = raw_r.load().await? {
+            for header in headers {
+                let mut entry = Entry::new(header.clone(), self.file.clone(), self.id());
+                if let Some(meta) = entry.load_meta().await? {
+                    meta_index.push(&header, meta);
+                }
+            }
+        }
+        Ok(meta_index)
+    }
+
     pub(crate) fn check_data_consistency() {
         // @TODO implement
     }
 
-    pub(crate) async fn write(&mut self, mut record: Record) -> Result<()> {
+    /// Appends record to the blob and returns its header with the offset set.
+    pub(crate) async fn write(&mut self, mut record: Record) -> Result<RecordHeader> {
         debug!("blob write");
         let mut offset = self.current_offset.lock().await;
         debug!("blob write record offset: {}", *offset);
@@ -226,9 +342,26 @@ impl<K: Key + 'static> Blob<K> {
                     _ => e.into(),
                 }
             })? as u64;
-        self.index.push(record.header().clone())?;
+        let header = record.header().clone();
+        self.index.push(header.clone())?;
+        if let Some(meta_index) = &mut self.meta_index {
+            meta_index.push(&header, record.meta());
+        }
         *offset += bytes_written;
-        Ok(())
+        Ok(header)
+    }
+
+    /// Returns records written at `offset` and after it. Records written to the blob later
+    /// are not included.
+    pub(crate) fn records_from(&self, offset: u64) -> Result<RecordsTail> {
+        Ok(RecordsTail {
+            file: self.file.clone(),
+            blob_id: self.id(),
+            blob_header_size: bincode::serialized_size(&self.header)?,
+            key_size: K::LEN as usize,
+            offset,
+            end: self.file.size(),
+        })
     }
 
     pub(crate) async fn read_any(
@@ -236,7 +369,7 @@ impl<K: Key + 'static> Blob<K> {
         key: &K,
         meta: Option<&Meta>,
         check_filters: bool,
-    ) -> Result<Vec<u8>> {
+    ) -> Result<Bytes> {
         debug!("blob read any");
         let entry = self
             .get_entry(key, meta, check_filters)
@@ -252,22 +385,67 @@ impl<K: Key + 'static> Blob<K> {
         Ok(buf)
     }
 
+    pub(crate) async fn latest_entry(&self, key: &K) -> Result<Option<Entry>> {
+        self.get_entry(key, None, true).await
+    }
+
+    /// Returns entries of records with `value` of indexed meta attribute `name`, in order
+    /// of writing. Meta index of closed blob is read from its file.
+    pub(crate) async fn find_by_meta(&self, name: &str, value: &[u8]) -> Result<Vec<Entry>> {
+        let saved;
+        let meta_index = match &self.meta_index {
+            Some(meta_index) => meta_index,
+            None => {
+                let names = &self.index.config().meta_indexes;
+                saved = self.read_meta_index(names).await?.ok_or_else(|| {
+                    let msg = format!("meta index of blob {} is outdated", self.name);
+                    Error::from(ErrorKind::Index(msg))
+                })?;
+                &saved
+            }
+        };
+        let headers = meta_index.get(name, value).unwrap_or_default();
+        Ok(self.headers_to_entries(headers.to_vec()))
+    }
+
+    /// Returns sorted keys, which start with `prefix`. Index isn't read, if range
+    /// and prefix filters reject the prefix.
+    pub(crate) async fn keys_with_prefix(&self, prefix: &[u8]) -> Result<Vec<K>> {
+        if self.index.check_prefix_filters(prefix) == FilterResult::NotContains {
+            Ok(Vec::new())
+        } else {
+            self.index.keys_with_prefix(prefix).await
+        }
+    }
+
     #[inline]
     pub(crate) async fn read_all_entries(&self, key: &K) -> Result<Option<Vec<Entry>>> {
         let headers = self.index.get_all(key).await?;
         Ok(headers.map(|h| {
             debug!("blob core read all {} headers", h.len());
-            Self::headers_to_entries(h, &self.file)
+            self.headers_to_entries(h)
         }))
     }
 
-    fn headers_to_entries(headers: Vec<RecordHeader>, file: &File) -> Vec<Entry> {
+    fn headers_to_entries(&self, headers: Vec<RecordHeader>) -> Vec<Entry> {
         headers
             .into_iter()
-            .map(|header| Entry::new(header, file.clone()))
+            .map(|header| self.header_to_entry(header))
             .collect()
     }
 
+    pub(crate) fn detached_index(&self) -> Option<DetachedIndex<K>> {
+        self.index.detach()
+    }
+
+    pub(crate) async fn for_each_header(&self, f: impl FnMut(&RecordHeader) + Send) -> Result<()> {
+        self.index.for_each_header(f).await
+    }
+
+    pub(crate) fn header_to_entry(&self, header: RecordHeader) -> Entry {
+        Entry::new(header, self.file.clone(), self.id())
+    }
+
     async fn get_entry(
         &self,
         key: &K,
@@ -280,7 +458,7 @@ impl<K: Key + 'static> Blob<K> {
             Ok(None)
         } else if let Some(meta) = meta {
             debug!("blob get any entry meta: {:?}", meta);
-            self.get_entry_with_meta(key, meta).await
+            self.get_entry_with_meta(key, meta, check_filters).await
         } else {
             debug!("blob get any entry bloom true no meta");
             if let Some(header) = self
@@ -289,19 +467,30 @@ impl<K: Key + 'static> Blob<K> {
                 .await
                 .with_context(|| "blob index get any failed")?
             {
-                let entry = Entry::new(header, self.file.clone());
+                let entry = Entry::new(header, self.file.clone(), self.id());
                 debug!("blob, get any entry, bloom true no meta, entry found");
                 Ok(Some(entry))
             } else {
+                if check_filters {
+                    self.index.record_filter_false_positive();
+                }
                 Ok(None)
             }
         }
     }
 
-    async fn get_entry_with_meta(&self, key: &K, meta: &Meta) -> Result<Option<Entry>> {
+    async fn get_entry_with_meta(
+        &self,
+        key: &K,
+        meta: &Meta,
+        filters_checked: bool,
+    ) -> Result<Option<Entry>> {
         let headers = self.index.get_all(key).await?;
+        if headers.is_none() && filters_checked {
+            self.index.record_filter_false_positive();
+        }
         if let Some(headers) = headers {
-            let entries = Self::headers_to_entries(headers, &self.file);
+            let entries = self.headers_to_entries(headers);
             self.filter_entries(entries, meta).await
         } else {
             Ok(None)
@@ -371,6 +560,39 @@ impl<K: Key + 'static> Blob<K> {
     pub(crate) fn filter_memory_allocated(&self) -> usize {
         self.index.bloom_memory_allocated()
     }
+
+    pub(crate) fn filter_memory_required(&self) -> usize {
+        self.index.bloom_memory_required()
+    }
+
+    /// Filter can be offloaded only when index is dumped to disk.
+    pub(crate) fn is_filter_offloadable(&self) -> bool {
+        self.index.is_filter_offloadable()
+    }
+
+    pub(crate) fn update_filter_heat(&self) -> u64 {
+        self.index.update_filter_heat()
+    }
+
+    pub(crate) fn offload_filter(&mut self) -> usize {
+        self.index.offload_filter()
+    }
+
+    pub(crate) async fn reload_filter(&mut self) -> Result<usize> {
+        self.index.reload_filter().await
+    }
+
+    pub(crate) fn restore_filter(&mut self, bloom: Bloom) -> usize {
+        self.index.restore_filter(bloom)
+    }
+
+    pub(crate) fn filter_stats(&self) -> BlobFilterStats {
+        self.index.filter_stats(self.id())
+    }
+
+    pub(crate) fn index_records_hash(&self) -> Option<&[u8]> {
+        self.index.records_hash()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -395,6 +617,10 @@ impl FileName {
         Self::try_from_path(path).ok_or_else(|| Error::file_pattern(path.to_owned()).into())
     }
 
+    pub(crate) fn id(&self) -> usize {
+        self.id
+    }
+
     pub fn to_path(&self) -> PathBuf {
         self.dir.join(self.to_string())
     }
@@ -429,6 +655,62 @@ impl Display for FileName {
     }
 }
 
+/// Records of the blob in range of offsets, which can be loaded without holding locks
+/// on the blob.
+#[derive(Debug)]
+pub(crate) struct RecordsTail {
+    file: File,
+    blob_id: usize,
+    blob_header_size: u64,
+    key_size: usize,
+    offset: u64,
+    end: u64,
+}
+
+impl RecordsTail {
+    pub(crate) fn blob_id(&self) -> usize {
+        self.blob_id
+    }
+
+    /// Returns entries of the records in order of writing. Record headers are read by
+    /// pages, so the whole tail isn't kept in memory.
+    pub(crate) fn entries(self) -> impl Stream<Item = Result<Entry>> {
+        stream::try_unfold(self, |mut tail| async move {
+            let page = tail.next_page().await?;
+            let next = (!page.is_empty()).then(|| (stream::iter(page.into_iter().map(Ok)), tail));
+            Ok::<_, anyhow::Error>(next)
+        })
+        .try_flatten()
+    }
+
+    // reads headers of the next `TAIL_PAGE_SIZE` records and moves offset after them
+    async fn next_page(&mut self) -> Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        if self.end <= self.offset.max(self.blob_header_size) {
+            return Ok(entries);
+        }
+        let mut raw_r = RawRecords::start(self.file.clone(), self.blob_header_size, self.key_size)
+            .await
+            .context("failed to create iterator for raw records")?;
+        raw_r.current_offset = raw_r.current_offset.max(self.offset);
+        while raw_r.current_offset < self.end && entries.len() < TAIL_PAGE_SIZE {
+            let offset = raw_r.current_offset;
+            let header = raw_r
+                .read_current_record_header()
+                .await
+                .with_context(|| format!("read record header failed, at {}", offset))?;
+            // offset may point into the middle of a record
+            header.check_magic_byte()?;
+            header
+                .check_header_checksum()
+                .with_context(|| format!("no valid record header at {}", offset))?;
+            entries.push(Entry::new(header, self.file.clone(), self.blob_id));
+        }
+        self.offset = raw_r.current_offset;
+        Ok(entries)
+    }
+}
+
 struct RawRecords {
     current_offset: u64,
     record_header_size: u64,
@@ -522,13 +804,13 @@ impl<K> BloomProvider<K> for Blob<K>
 where
     K: Key + 'static,
 {
-    type Filter = Bloom;
+    type Filter = CombinedFilter<K>;
     async fn check_filter(&self, item: &K) -> FilterResult {
-        self.index.check_bloom_key(item).await.unwrap_or_default()
+        self.index.check_filters_key(item).await.unwrap_or_default()
     }
 
     fn check_filter_fast(&self, item: &K) -> FilterResult {
-        self.index.check_bloom_key_in_memory(item)
+        self.index.check_filters_in_memory(item)
     }
 
     async fn offload_buffer(&mut self, _: usize, _: usize) -> usize {
@@ -536,14 +818,14 @@ where
     }
 
     async fn get_filter(&self) -> Option<Self::Filter> {
-        Some(self.index.get_bloom_filter().clone())
+        Some(self.index.combined_filter().clone())
     }
 
     fn get_filter_fast(&self) -> Option<&Self::Filter> {
-        Some(self.index.get_bloom_filter())
+        Some(self.index.combined_filter())
     }
 
     async fn filter_memory_allocated(&self) -> usize {
-        self.index.get_bloom_filter().memory_allocated()
+        self.index.bloom_memory_allocated()
     }
 }
@@ -9,11 +9,12 @@ use super::prelude::*;
 ///
 /// [`Entry`]: struct.Entry.html
 /// [`load`]: struct.Entry.html#method.load
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Entry {
     header: RecordHeader,
     meta: Option<Meta>,
     blob_file: File,
+    blob_id: usize,
 }
 
 impl Entry {
@@ -23,10 +24,10 @@ impl Entry {
     pub async fn load(self) -> Result<Record> {
         let meta_size = self.header.meta_size().try_into()?;
         let data_size: usize = self.header.data_size().try_into()?;
-        let mut buf = vec![0; data_size + meta_size];
         // The number of bytes read is checked by File internally.
-        self.blob_file
-            .read_at(&mut buf, self.header.meta_offset())
+        let mut buf = self
+            .blob_file
+            .read_bytes(self.header.meta_offset(), data_size + meta_size)
             .await
             .with_context(|| "blob load failed")?;
         let data_buf = buf.split_off(meta_size);
@@ -35,14 +36,22 @@ impl Entry {
         record.validate()
     }
 
-    /// Returns only data.
+    /// Returns only data. If mmap is enabled and blob is closed, data is not copied.
     /// # Errors
     /// Fails after any disk IO errors.
-    pub async fn load_data(&self) -> Result<Vec<u8>> {
+    pub async fn load_data(&self) -> Result<Bytes> {
         let data_offset = self.header.data_offset();
-        let mut buf = vec![0; self.header.data_size().try_into()?];
-        self.blob_file.read_at(&mut buf, data_offset).await?;
-        Ok(buf)
+        let data_size = self.header.data_size().try_into()?;
+        self.blob_file.read_bytes(data_offset, data_size).await
+    }
+
+    /// Returns the whole record as it is stored in the blob.
+    pub(crate) async fn load_raw(&self) -> Result<Bytes> {
+        let size =
+            self.header.serialized_size() + self.header.meta_size() + self.header.data_size();
+        self.blob_file
+            .read_bytes(self.header.blob_offset(), size.try_into()?)
+            .await
     }
 
     /// Loads meta data from fisk, and returns reference to it.
@@ -56,11 +65,58 @@ impl Entry {
         Ok(self.meta.as_ref())
     }
 
-    pub(crate) fn new(header: RecordHeader, blob_file: File) -> Self {
+    /// Returns time of the record creation in seconds since the unix epoch.
+    #[must_use]
+    pub fn timestamp(&self) -> u64 {
+        self.header.created()
+    }
+
+    /// Returns size of the record data in bytes.
+    #[must_use]
+    pub fn data_size(&self) -> u64 {
+        self.header.data_size()
+    }
+
+    /// Returns size of the serialized record metadata in bytes.
+    #[must_use]
+    pub fn meta_size(&self) -> u64 {
+        self.header.meta_size()
+    }
+
+    /// Returns offset of the record in the blob file.
+    #[must_use]
+    pub fn blob_offset(&self) -> u64 {
+        self.header.blob_offset()
+    }
+
+    /// Returns id of the blob, which contains the record.
+    #[must_use]
+    pub fn blob_id(&self) -> usize {
+        self.blob_id
+    }
+
+    /// Returns CRC32C checksum of the record data.
+    #[must_use]
+    pub fn data_checksum(&self) -> u32 {
+        self.header.data_checksum()
+    }
+
+    /// Returns CRC32C checksum of the record header.
+    #[must_use]
+    pub fn header_checksum(&self) -> u32 {
+        self.header.header_checksum()
+    }
+
+    pub(crate) fn header(&self) -> &RecordHeader {
+        &self.header
+    }
+
+    pub(crate) fn new(header: RecordHeader, blob_file: File, blob_id: usize) -> Self {
         Self {
             meta: None,
             header,
             blob_file,
+            blob_id,
         }
     }
 }
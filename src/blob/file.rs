@@ -1,5 +1,6 @@
 use std::os::unix::prelude::{AsRawFd, FileExt};
 
+use memmap2::{Advice, Mmap, MmapOptions};
 use nix::{errno::Errno, fcntl::FcntlArg};
 
 use super::prelude::*;
@@ -9,6 +10,17 @@ pub struct File {
     ioring: Option<Rio>,
     no_lock_fd: Arc<StdFile>, // requires only for read_at/write_at methods
     size: Arc<AtomicU64>,
+    // covers file content at the moment of mapping, reads beyond it go through `read_at`
+    mmap: Option<Arc<Mmap>>,
+}
+
+// allows to hand out parts of mapped file as `Bytes` without copying
+struct MmapOwner(Arc<Mmap>);
+
+impl AsRef<[u8]> for MmapOwner {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
 }
 
 #[derive(PartialEq, Eq)]
@@ -43,10 +55,41 @@ impl File {
         Self::from_tokio_file(file, ioring).await
     }
 
+    pub(crate) fn ioring(&self) -> Option<Rio> {
+        self.ioring.clone()
+    }
+
     pub fn size(&self) -> u64 {
         self.size.load(ORD)
     }
 
+    /// Maps file into memory, so reads of already written data don't need syscalls.
+    /// Must be called only for files, which are not modified anymore (appending is allowed).
+    /// If mapping fails, reads continue to go through `read_at`.
+    pub(crate) fn enable_mmap(&mut self, advice: Advice) {
+        if self.mmap.is_some() || self.size() == 0 {
+            return;
+        }
+        // Safety: files are locked for the whole time storage is running and never
+        // truncated or overwritten after mapping.
+        match unsafe { MmapOptions::new().map(&*self.no_lock_fd) } {
+            Ok(mmap) => {
+                if let Err(e) = mmap.advise(advice) {
+                    warn!("madvise {:?} failed: {}", advice, e);
+                }
+                self.mmap = Some(Arc::new(mmap));
+            }
+            Err(e) => warn!("mmap failed, fallback to read_at: {}", e),
+        }
+    }
+
+    fn mapped(&self, offset: u64, len: usize) -> Option<&[u8]> {
+        let mmap = self.mmap.as_ref()?;
+        let start: usize = offset.try_into().ok()?;
+        let end = start.checked_add(len)?;
+        mmap.get(start..end)
+    }
+
     pub(crate) async fn write_append(&self, buf: &[u8]) -> IOResult<usize> {
         if let Some(ref ioring) = self.ioring {
             self.write_append_aio(buf, ioring).await
@@ -99,7 +142,33 @@ impl File {
         Ok(buf)
     }
 
+    /// Reads `len` bytes at `offset`, data of mapped files is returned without copying.
+    pub(crate) async fn read_bytes(&self, offset: u64, len: usize) -> Result<Bytes> {
+        if let (Some(mmap), Some(_)) = (&self.mmap, self.mapped(offset, len)) {
+            let start = offset as usize;
+            let bytes = Bytes::from_owner(MmapOwner(mmap.clone()));
+            return Ok(bytes.slice(start..start + len));
+        }
+        let buf = if let Some(ref ioring) = self.ioring {
+            let mut buf = vec![0; len];
+            self.read_at_aio(&mut buf, offset, ioring).await?;
+            buf
+        } else {
+            let buf = self.read_vec_sync(len, offset).await?;
+            if buf.len() < len {
+                let msg = format!("blob file read {}/{} bytes at {}", buf.len(), len, offset);
+                return Err(Error::io(msg).into());
+            }
+            buf
+        };
+        Ok(buf.into())
+    }
+
     pub(crate) async fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize> {
+        if let Some(data) = self.mapped(offset, buf.len()) {
+            buf.copy_from_slice(data);
+            return Ok(buf.len());
+        }
         if let Some(ref ioring) = self.ioring {
             self.read_at_aio(buf, offset, ioring).await
         } else {
@@ -107,17 +176,71 @@ impl File {
         }
     }
 
+    /// Reads buffers of given lengths at given offsets. All reads are submitted at once:
+    /// to io_uring before awaiting any of them, or to a single blocking task.
+    pub(crate) async fn read_at_batch(&self, reads: &[(u64, usize)]) -> Result<Vec<Vec<u8>>> {
+        let mapped: Option<Vec<_>> = reads
+            .iter()
+            .map(|&(offset, len)| self.mapped(offset, len).map(<[u8]>::to_vec))
+            .collect();
+        if let Some(bufs) = mapped {
+            return Ok(bufs);
+        }
+        if let Some(ref ioring) = self.ioring {
+            let mut bufs: Vec<_> = reads.iter().map(|&(_, len)| vec![0; len]).collect();
+            let sizes = {
+                let completions: Vec<_> = bufs
+                    .iter()
+                    .zip(reads)
+                    .map(|(buf, &(offset, _))| ioring.read_at(&*self.no_lock_fd, buf, offset))
+                    .collect();
+                future::try_join_all(completions)
+                    .await
+                    .with_context(|| "batched read at failed")?
+            };
+            for ((buf, &(offset, _)), size) in bufs.iter_mut().zip(reads).zip(sizes) {
+                if size < buf.len() {
+                    self.read_at_aio(&mut buf[size..], offset + size as u64, ioring)
+                        .await?;
+                }
+            }
+            Ok(bufs)
+        } else {
+            let file = self.no_lock_fd.clone();
+            let reads = reads.to_vec();
+            Self::blocking_call(move || {
+                reads
+                    .into_iter()
+                    .map(|(offset, len)| {
+                        let mut buf = vec![0; len];
+                        file.read_exact_at(&mut buf, offset).map(|_| buf)
+                    })
+                    .collect::<IOResult<Vec<_>>>()
+            })
+            .await
+            .map_err(Into::into)
+        }
+    }
+
     pub(crate) async fn read_at_sync(&self, buf: &mut [u8], offset: u64) -> Result<usize> {
-        let file = self.no_lock_fd.clone();
-        let mut new_buf = buf.to_vec();
+        let new_buf = self.read_vec_sync(buf.len(), offset).await?;
+        buf[..new_buf.len()].copy_from_slice(&new_buf);
+        Ok(new_buf.len())
+    }
 
-        let (count, new_buf) = Self::blocking_call(move || {
-            file.read_at(&mut new_buf, offset)
-                .map(|count| (count, new_buf))
+    // blocking task can't borrow caller's buffer, so it reads into its own one, which is
+    // returned truncated to the number of bytes read
+    async fn read_vec_sync(&self, len: usize, offset: u64) -> Result<Vec<u8>> {
+        let file = self.no_lock_fd.clone();
+        let buf = Self::blocking_call(move || {
+            let mut buf = vec![0; len];
+            file.read_at(&mut buf, offset).map(|count| {
+                buf.truncate(count);
+                buf
+            })
         })
         .await?;
-        buf.clone_from_slice(new_buf.as_slice());
-        Ok(count)
+        Ok(buf)
     }
 
     async fn read_at_aio(&self, buf: &mut [u8], offset: u64, ioring: &Rio) -> Result<usize> {
@@ -167,10 +290,36 @@ impl File {
             ioring,
             no_lock_fd: Arc::new(std_file),
             size,
+            mmap: None,
         };
         Ok(file)
     }
 
+    /// Replaces content of file at `path` with `buf`: data is written to temporary file,
+    /// which is synced and renamed, so after crash file has either the old or the new
+    /// content. Directory is synced after rename, so rename itself isn't lost.
+    pub(crate) async fn write_atomically(
+        path: &Path,
+        buf: &[u8],
+        ioring: Option<Rio>,
+    ) -> IOResult<()> {
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+        // file is not truncated on creation
+        if tmp_path.exists() {
+            tokio::fs::remove_file(&tmp_path).await?;
+        }
+        let file = Self::create(&tmp_path, ioring).await?;
+        file.write_append(buf).await?;
+        file.fsyncdata().await?;
+        drop(file);
+        tokio::fs::rename(&tmp_path, path).await?;
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+        let dir = TokioFile::open(dir.unwrap_or_else(|| Path::new("."))).await?;
+        dir.sync_all().await
+    }
+
     pub(crate) async fn fsyncdata(&self) -> IOResult<()> {
         if let Some(ref ioring) = self.ioring {
             let compl = ioring.fsync(&*self.no_lock_fd);
@@ -80,7 +80,7 @@ async fn benchmark_from_records() {
             ioring.clone(),
             &headers,
             meta.clone(),
-            true,
+            &IndexConfig::default(),
         )
         .await
         .unwrap();
@@ -120,7 +120,7 @@ async fn benchmark_from_file() {
             ioring.clone(),
             &headers,
             meta,
-            true,
+            &IndexConfig::default(),
         )
         .await
         .unwrap();
@@ -136,6 +136,7 @@ async fn benchmark_from_file() {
                 PathBuf::from(DIR),
             ),
             ioring.clone(),
+            &IndexConfig::default(),
         )
         .await
         .unwrap();
@@ -172,7 +173,7 @@ async fn benchmark_get_any() {
         ioring.clone(),
         &headers,
         meta.clone(),
-        true,
+        &IndexConfig::default(),
     )
     .await
     .unwrap();
@@ -225,7 +226,7 @@ async fn benchmark_get_all() {
         ioring.clone(),
         &headers,
         meta.clone(),
-        true,
+        &IndexConfig::default(),
     )
     .await
     .unwrap();
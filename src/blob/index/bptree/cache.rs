@@ -0,0 +1,177 @@
+use super::prelude::*;
+use std::sync::{atomic::AtomicU64, Mutex};
+
+type NodeKey = (usize, u64);
+
+/// Storage-wide cache of b+-tree index nodes with CLOCK eviction.
+/// Nodes are identified by blob id and offset of the node in the index file.
+#[derive(Clone)]
+pub(crate) struct NodeCache {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    capacity: usize,
+    state: Mutex<State>,
+    memory: AtomicUsize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Default)]
+struct State {
+    slots: Vec<Slot>,
+    positions: HashMap<NodeKey, usize>,
+    hand: usize,
+}
+
+struct Slot {
+    key: NodeKey,
+    data: Box<[u8]>,
+    referenced: bool,
+}
+
+/// Statistics of b+-tree node cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NodeCacheStats {
+    /// Number of node reads served from the cache.
+    pub hits: u64,
+    /// Number of node reads which went to disk.
+    pub misses: u64,
+    /// Number of nodes currently in the cache.
+    pub nodes: usize,
+    /// Memory occupied by cached nodes in bytes.
+    pub memory: usize,
+}
+
+impl NodeCacheStats {
+    /// Share of node reads served from the cache, `0.0` if there were no reads.
+    #[must_use]
+    pub fn hit_ratio(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+impl NodeCache {
+    /// Creates cache, which holds at most `memory_limit` bytes of nodes.
+    pub(crate) fn new(memory_limit: usize) -> Self {
+        let inner = Inner {
+            capacity: memory_limit / BLOCK_SIZE,
+            state: Mutex::new(State::default()),
+            memory: AtomicUsize::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        };
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Copies cached node into `buf`, returns size of the node.
+    pub(crate) fn get(&self, blob_id: usize, offset: u64, buf: &mut [u8]) -> Option<usize> {
+        let mut state = self.inner.state.lock().expect("node cache lock poisoned");
+        let res = state.positions.get(&(blob_id, offset)).copied().map(|pos| {
+            let slot = &mut state.slots[pos];
+            slot.referenced = true;
+            let len = slot.data.len().min(buf.len());
+            buf[..len].copy_from_slice(&slot.data[..len]);
+            len
+        });
+        drop(state);
+        if res.is_some() {
+            self.inner.hits.fetch_add(1, ORD);
+        } else {
+            self.inner.misses.fetch_add(1, ORD);
+        }
+        res
+    }
+
+    pub(crate) fn insert(&self, blob_id: usize, offset: u64, data: &[u8]) {
+        if self.inner.capacity == 0 {
+            return;
+        }
+        let key = (blob_id, offset);
+        let mut state = self.inner.state.lock().expect("node cache lock poisoned");
+        if state.positions.contains_key(&key) {
+            return;
+        }
+        let slot = Slot {
+            key,
+            data: data.into(),
+            referenced: false,
+        };
+        self.inner.memory.fetch_add(data.len(), ORD);
+        if state.slots.len() < self.inner.capacity {
+            let pos = state.slots.len();
+            state.slots.push(slot);
+            state.positions.insert(key, pos);
+            return;
+        }
+        // second chance: skip referenced slots, resetting their bit, and replace the first
+        // one, which wasn't accessed since the previous pass of the hand
+        loop {
+            let hand = state.hand;
+            state.hand = (hand + 1) % state.slots.len();
+            let current = &mut state.slots[hand];
+            if current.referenced {
+                current.referenced = false;
+                continue;
+            }
+            let evicted = std::mem::replace(current, slot);
+            self.inner.memory.fetch_sub(evicted.data.len(), ORD);
+            state.positions.remove(&evicted.key);
+            state.positions.insert(key, hand);
+            break;
+        }
+    }
+
+    /// Drops all nodes of the blob, must be called when index file of the blob is rewritten.
+    pub(crate) fn remove_blob(&self, blob_id: usize) {
+        let mut state = self.inner.state.lock().expect("node cache lock poisoned");
+        if state.positions.keys().all(|(id, _)| *id != blob_id) {
+            return;
+        }
+        let slots = std::mem::take(&mut state.slots);
+        let (removed, slots): (Vec<_>, Vec<_>) =
+            slots.into_iter().partition(|slot| slot.key.0 == blob_id);
+        let removed_memory = removed.iter().map(|slot| slot.data.len()).sum();
+        self.inner.memory.fetch_sub(removed_memory, ORD);
+        state.positions = slots
+            .iter()
+            .enumerate()
+            .map(|(pos, slot)| (slot.key, pos))
+            .collect();
+        state.slots = slots;
+        state.hand = 0;
+    }
+
+    pub(crate) fn stats(&self) -> NodeCacheStats {
+        let nodes = self
+            .inner
+            .state
+            .lock()
+            .expect("node cache lock poisoned")
+            .slots
+            .len();
+        NodeCacheStats {
+            hits: self.inner.hits.load(ORD),
+            misses: self.inner.misses.load(ORD),
+            nodes,
+            memory: self.inner.memory.load(ORD),
+        }
+    }
+}
+
+impl Debug for NodeCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("NodeCache")
+            .field("capacity", &self.inner.capacity)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
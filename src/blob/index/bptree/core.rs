@@ -19,25 +19,32 @@ pub(crate) struct BPTreeFileIndex<K> {
     header: IndexHeader,
     metadata: TreeMeta,
     root_node: [u8; BLOCK_SIZE],
+    // shared cache and id of the blob, which nodes of this index are cached with
+    node_cache: Option<(NodeCache, usize)>,
     key_type_marker: PhantomData<K>,
 }
 
 #[async_trait::async_trait]
 impl<K: Key + 'static> FileIndexTrait<K> for BPTreeFileIndex<K> {
-    async fn from_file(name: FileName, ioring: Option<Rio>) -> Result<Self> {
+    async fn from_file(name: FileName, ioring: Option<Rio>, config: &IndexConfig) -> Result<Self> {
         trace!("open index file");
-        let file = File::open(name.to_path(), ioring)
+        let mut file = File::open(name.to_path(), ioring)
             .await
             .context(format!("failed to open index file: {}", name))?;
-        let header = Self::read_index_header(&file).await?;
+        if config.mmap {
+            file.enable_mmap(Advice::WillNeed);
+        }
+        let header = IndexHeader::from_file(&file).await?;
         let metadata = Self::read_tree_meta(&file, &header).await?;
         let root_node = Self::read_root(&file, metadata.tree_offset).await?;
+        let node_cache = config.node_cache.clone().map(|cache| (cache, name.id()));
 
         Ok(Self {
             file,
             header,
             metadata,
             root_node,
+            node_cache,
             key_type_marker: PhantomData,
         })
     }
@@ -47,12 +54,12 @@ impl<K: Key + 'static> FileIndexTrait<K> for BPTreeFileIndex<K> {
         ioring: Option<Rio>,
         headers: &InMemoryIndex<K>,
         meta: Vec<u8>,
-        recreate_index_file: bool,
+        config: &IndexConfig,
     ) -> Result<Self> {
-        clean_file(path, recreate_index_file)?;
+        clean_file(path, config.recreate_index_file)?;
         let res = Self::serialize(headers, meta)?;
         let (mut header, metadata, buf) = res;
-        let file = File::create(path, ioring)
+        let mut file = File::create(path, ioring)
             .await
             .with_context(|| format!("file open failed {:?}", path))?;
         file.write_append(&buf).await?;
@@ -60,12 +67,26 @@ impl<K: Key + 'static> FileIndexTrait<K> for BPTreeFileIndex<K> {
         let serialized_header = serialize(&header)?;
         file.write_at(0, &serialized_header).await?;
         file.fsyncdata().await?;
+        if config.mmap {
+            file.enable_mmap(Advice::WillNeed);
+        }
         let root_node = Self::read_root(&file, metadata.tree_offset).await?;
+        let node_cache = config
+            .node_cache
+            .clone()
+            .map(|cache| -> Result<_> {
+                let blob_id = FileName::from_path(path)?.id();
+                // nodes of the previous index file of this blob are outdated
+                cache.remove_blob(blob_id);
+                Ok((cache, blob_id))
+            })
+            .transpose()?;
         Ok(Self {
             file,
             metadata,
             header,
             root_node,
+            node_cache,
             key_type_marker: PhantomData,
         })
     }
@@ -78,6 +99,10 @@ impl<K: Key + 'static> FileIndexTrait<K> for BPTreeFileIndex<K> {
         self.header.records_count
     }
 
+    fn records_hash(&self) -> &[u8] {
+        &self.header.hash
+    }
+
     async fn read_meta(&self) -> Result<Vec<u8>> {
         trace!("load meta");
         let mut buf = vec![0; self.header.meta_size];
@@ -100,6 +125,10 @@ impl<K: Key + 'static> FileIndexTrait<K> for BPTreeFileIndex<K> {
         Ok(buf[0])
     }
 
+    async fn read_meta_ranges(&self, ranges: &[(u64, usize)]) -> Result<Vec<Vec<u8>>> {
+        read_meta_ranges(&self.file, &self.header, ranges).await
+    }
+
     async fn find_by_key(&self, key: &K) -> Result<Option<Vec<RecordHeader>>> {
         let root_offset = self.metadata.tree_offset;
         let mut buf = [0u8; BLOCK_SIZE];
@@ -130,6 +159,23 @@ impl<K: Key + 'static> FileIndexTrait<K> for BPTreeFileIndex<K> {
             .map(|headers| (headers, self.header.records_count))
     }
 
+    async fn for_each_header(
+        &self,
+        f: &mut (dyn for<'h> FnMut(&'h RecordHeader) + Send),
+    ) -> Result<()> {
+        let start = self.metadata.leaves_offset;
+        let size = self.header.record_header_size * self.header.records_count;
+        let range = (start, start + size as u64);
+        for_each_header_in(
+            &self.file,
+            range,
+            self.header.record_header_size,
+            |_| true,
+            f,
+        )
+        .await
+    }
+
     async fn get_any(&self, key: &K) -> Result<Option<RecordHeader>> {
         let root_offset = self.metadata.tree_offset;
         let mut buf = [0u8; BLOCK_SIZE];
@@ -137,6 +183,20 @@ impl<K: Key + 'static> FileIndexTrait<K> for BPTreeFileIndex<K> {
         self.read_header(leaf_offset, key, &mut buf).await
     }
 
+    // keys with the prefix are adjacent in leaves only if keys are ordered by bytes,
+    // otherwise all leaves are read
+    async fn keys_with_prefix(&self, prefix: &[u8]) -> Result<Vec<K>> {
+        let leaf_offset = if K::BYTE_ORDERED {
+            let lower_bound = prefix_lower_bound::<K>(prefix);
+            let mut buf = [0u8; BLOCK_SIZE];
+            self.find_leaf_node(&lower_bound, self.metadata.tree_offset, &mut buf)
+                .await?
+        } else {
+            self.metadata.leaves_offset
+        };
+        self.read_keys_with_prefix(leaf_offset, prefix).await
+    }
+
     fn validate(&self) -> Result<()> {
         // FIXME: check hash here?
         if !self.header.is_written() {
@@ -157,13 +217,26 @@ impl<K: Key + 'static> BPTreeFileIndex<K> {
             offset = if offset == self.metadata.tree_offset {
                 Node::key_offset_serialized(&self.root_node, key)?
             } else {
-                self.file.read_at(buf, offset).await?;
+                self.read_node(offset, buf).await?;
                 Node::key_offset_serialized(buf, key)?
             };
         }
         Ok(offset)
     }
 
+    async fn read_node(&self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        if let Some((cache, blob_id)) = &self.node_cache {
+            if let Some(size) = cache.get(*blob_id, offset, buf) {
+                return Ok(size);
+            }
+            let size = self.file.read_at(buf, offset).await?;
+            cache.insert(*blob_id, offset, &buf[..size]);
+            Ok(size)
+        } else {
+            self.file.read_at(buf, offset).await
+        }
+    }
+
     async fn read_header(
         &self,
         leaf_offset: u64,
@@ -171,7 +244,7 @@ impl<K: Key + 'static> BPTreeFileIndex<K> {
         buf: &mut [u8],
     ) -> Result<Option<RecordHeader>> {
         let buf_size = self.leaf_node_buf_size(leaf_offset);
-        let read_buf_size = self.file.read_at(&mut buf[..buf_size], leaf_offset).await?;
+        let read_buf_size = self.read_node(leaf_offset, &mut buf[..buf_size]).await?;
         if read_buf_size != buf_size {
             Err(anyhow!("Can't read entire leaf node"))
         } else {
@@ -247,7 +320,7 @@ impl<K: Key + 'static> BPTreeFileIndex<K> {
         buf: &mut [u8],
     ) -> Result<Option<Vec<RecordHeader>>> {
         let buf_size = self.leaf_node_buf_size(leaf_offset);
-        let read_buf_size = self.file.read_at(&mut buf[..buf_size], leaf_offset).await?;
+        let read_buf_size = self.read_node(leaf_offset, &mut buf[..buf_size]).await?;
         let rh_size = self.header.record_header_size;
         if read_buf_size != buf_size {
             return Err(anyhow!("Can't read entire leaf node"));
@@ -255,6 +328,8 @@ impl<K: Key + 'static> BPTreeFileIndex<K> {
         if let Some((header, offset)) = self.read_header_buf(&buf[..buf_size], key, rh_size)? {
             let mut headers = vec![header];
             self.go_left(&mut headers, &buf[..buf_size], offset).await?;
+            // left headers are newer, they were pushed from the found one to the leftmost
+            headers.reverse();
             self.go_right(&mut headers, &buf[..buf_size], offset, leaf_offset)
                 .await?;
             Ok(Some(headers))
@@ -290,6 +365,36 @@ impl<K: Key + 'static> BPTreeFileIndex<K> {
         Ok(())
     }
 
+    // leaves are sorted by key, so they are read from the leaf with the lower bound of the
+    // prefix, until the first key, which is greater than keys with the prefix
+    async fn read_keys_with_prefix(&self, mut offset: u64, prefix: &[u8]) -> Result<Vec<K>> {
+        let record_header_size = self.header.record_header_size as u64;
+        let records_size = self.header.record_header_size * self.header.records_count;
+        let leaves_end = self.metadata.leaves_offset + records_size as u64;
+        let headers_per_read = (BLOCK_SIZE as u64 / record_header_size).max(1);
+        let mut keys: Vec<K> = Vec::new();
+        while offset < leaves_end {
+            let size = (leaves_end - offset).min(headers_per_read * record_header_size);
+            let mut buf = vec![0; size as usize];
+            if self.file.read_at(&mut buf, offset).await? != buf.len() {
+                return Err(anyhow!("Can't read leaf headers"));
+            }
+            for raw in buf.chunks(record_header_size as usize) {
+                let header: RecordHeader = deserialize(raw)?;
+                let key = header.key();
+                if key.starts_with(prefix) {
+                    if keys.last().is_none_or(|last| last.as_ref() != key) {
+                        keys.push(key.to_vec().into());
+                    }
+                } else if K::BYTE_ORDERED && key > prefix {
+                    return Ok(keys);
+                }
+            }
+            offset += size;
+        }
+        Ok(keys)
+    }
+
     async fn go_right_file(&self, headers: &mut Vec<RecordHeader>, mut offset: u64) -> Result<()> {
         // TODO: read headers one by one from file may be inefficient
         let record_header_size = self.header.record_header_size as u64;
@@ -353,13 +458,6 @@ impl<K: Key + 'static> BPTreeFileIndex<K> {
         Ok(hash == new_hash)
     }
 
-    async fn read_index_header(file: &File) -> Result<IndexHeader> {
-        let header_size = IndexHeader::serialized_size_default()? as usize;
-        let mut buf = vec![0; header_size];
-        file.read_at(&mut buf, 0).await?;
-        IndexHeader::from_raw(&buf).map_err(Into::into)
-    }
-
     async fn read_root(file: &File, root_offset: u64) -> Result<[u8; BLOCK_SIZE]> {
         let mut buf = [0; BLOCK_SIZE];
         let buf_size = std::cmp::min((file.size() - root_offset) as usize, BLOCK_SIZE);
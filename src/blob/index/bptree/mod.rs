@@ -1,5 +1,6 @@
 use super::prelude::*;
 
+mod cache;
 mod core;
 mod meta;
 mod node;
@@ -7,7 +8,8 @@ mod serializer;
 #[cfg(test)]
 mod tests;
 
-pub(crate) use self::core::BPTreeFileIndex;
+pub use self::cache::NodeCacheStats;
+pub(crate) use self::{cache::NodeCache, core::BPTreeFileIndex};
 
 mod prelude {
     pub(super) use super::core::BLOCK_SIZE;
@@ -34,7 +34,12 @@ impl<'a, K: Key> Serializer<'a, K> {
                 .headers_btree
                 .iter()
                 .fold(0, |acc, (_k, v)| acc + v.len());
-            let header = IndexHeader::new(record_header_size, headers_len, meta.len());
+            let header = IndexHeader::new(
+                IndexKind::BPTree,
+                record_header_size,
+                headers_len,
+                meta.len(),
+            );
             Ok(HeaderStage {
                 headers_btree: self.headers_btree,
                 header,
@@ -206,6 +211,7 @@ impl<'a, K: Key> TreeStage<'a, K> {
         Self::append_headers(self.headers_btree, &mut buf)?;
         let hash = get_hash(&buf);
         let header = IndexHeader::with_hash(
+            self.header.kind,
             self.header.record_header_size,
             self.header.records_count,
             self.meta.len(),
@@ -56,7 +56,7 @@ async fn serialize_deserialize_file() {
         None,
         &inmem,
         meta,
-        true,
+        &IndexConfig::default(),
     )
     .await
     .expect("Can't create file index");
@@ -85,7 +85,7 @@ async fn check_get_any() {
         None,
         &inmem,
         meta,
-        true,
+        &IndexConfig::default(),
     )
     .await
     .expect("Can't create file index");
@@ -134,7 +134,7 @@ async fn check_get() {
         None,
         &inmem,
         meta,
-        true,
+        &IndexConfig::default(),
     )
     .await
     .expect("Can't create file index");
@@ -162,3 +162,102 @@ async fn check_get() {
         }
     }
 }
+
+#[tokio::test]
+async fn check_get_any_latest() {
+    const MAX_AMOUNT: usize = 50;
+    const RANGE_FROM: usize = 100;
+    const RANGE_TO: usize = 400;
+
+    // a lot of versions of the same key, so some of them are split between leaves
+    let mut inmem = InMemoryIndex::<KeyType>::new();
+    (RANGE_FROM..RANGE_TO)
+        .map(|i| (i % MAX_AMOUNT + 1, i.into()))
+        .for_each(|(times, key): (_, KeyType)| {
+            let recs = (0..times)
+                .map(|version| RecordHeader::new(key.to_vec(), 1, version as u64, 1))
+                .collect();
+            inmem.insert(key, recs);
+        });
+    let meta = vec![META_VALUE; META_SIZE];
+    let findex = BPTreeFileIndex::<KeyType>::from_records(
+        &Path::new("/tmp/latest_bptree_index.b"),
+        None,
+        &inmem,
+        meta,
+        &IndexConfig::default(),
+    )
+    .await
+    .expect("Can't create file index");
+    for key in (RANGE_FROM..RANGE_TO).map(|k| k.into()) {
+        let latest = inmem[&key].last();
+        let actual = findex.get_any(&key).await.unwrap();
+        assert_eq!(latest, actual.as_ref());
+        let all = findex.find_by_key(&key).await.unwrap().unwrap();
+        assert_eq!(latest, all.first());
+        assert_eq!(inmem[&key].first(), all.last());
+    }
+}
+
+#[test]
+fn node_cache_eviction() {
+    let cache = NodeCache::new(2 * BLOCK_SIZE);
+    let node = vec![1_u8; BLOCK_SIZE];
+    let mut buf = vec![0_u8; BLOCK_SIZE];
+    cache.insert(0, 0, &node);
+    cache.insert(0, 4096, &node);
+    assert_eq!(Some(BLOCK_SIZE), cache.get(0, 0, &mut buf));
+    assert_eq!(node, buf);
+    // the first node was accessed, so the second one is evicted
+    cache.insert(1, 0, &node[..100]);
+    assert_eq!(None, cache.get(0, 4096, &mut buf));
+    assert_eq!(Some(100), cache.get(1, 0, &mut buf));
+    let stats = cache.stats();
+    assert_eq!(2, stats.hits);
+    assert_eq!(1, stats.misses);
+    assert_eq!(2, stats.nodes);
+    assert_eq!(BLOCK_SIZE + 100, stats.memory);
+
+    cache.remove_blob(0);
+    assert_eq!(None, cache.get(0, 0, &mut buf));
+    assert_eq!(Some(100), cache.get(1, 0, &mut buf));
+    assert_eq!(100, cache.stats().memory);
+}
+
+#[tokio::test]
+async fn check_get_with_node_cache() {
+    const RANGE_FROM: usize = 100;
+    const RANGE_TO: usize = 9000;
+
+    let mut inmem = InMemoryIndex::<KeyType>::new();
+    (RANGE_FROM..RANGE_TO)
+        .map(|i| i.into())
+        .for_each(|key: KeyType| {
+            let rh = RecordHeader::new(key.to_vec(), 1, 1, 1);
+            inmem.insert(key, vec![rh]);
+        });
+    let meta = vec![META_VALUE; META_SIZE];
+    let cache = NodeCache::new(1024 * BLOCK_SIZE);
+    let config = IndexConfig {
+        node_cache: Some(cache.clone()),
+        ..Default::default()
+    };
+    let findex = BPTreeFileIndex::<KeyType>::from_records(
+        &Path::new("/tmp/test.0.index"),
+        None,
+        &inmem,
+        meta,
+        &config,
+    )
+    .await
+    .expect("Can't create file index");
+    for _ in 0..2 {
+        for key in (RANGE_FROM..RANGE_TO).map(|k| k.into()) {
+            let header = findex.get_any(&key).await.unwrap();
+            assert_eq!(Some(&inmem[&key][0]), header.as_ref());
+        }
+    }
+    let stats = cache.stats();
+    assert!(stats.hits > stats.misses);
+    assert_eq!(stats.misses as usize, stats.nodes);
+}
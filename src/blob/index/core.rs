@@ -1,23 +1,91 @@
 use super::prelude::*;
-use crate::filter::BloomDataProvider;
-use std::mem::size_of;
+use crate::filter::{
+    BlobFilterStats, BlockedBloom, BloomDataProvider, FilterCounters, PrefixFilter, ScalableBloom,
+    StaticFilter, XorFilter,
+};
+use std::{io::Cursor, mem::size_of};
 
-pub(crate) type Index<K> = IndexStruct<BPTreeFileIndex<K>, K>;
+pub(crate) type Index<K> = IndexStruct<FileIndex<K>, K>;
 
-pub(crate) const HEADER_VERSION: u8 = 4;
+/// Index file, which headers can be read after the blob is unlocked.
+#[derive(Debug)]
+pub(crate) struct DetachedIndex<K>(FileIndex<K>);
+
+impl<K: Key + 'static> DetachedIndex<K> {
+    /// Passes all headers to `f`, headers are read from index file by chunks.
+    pub(crate) async fn for_each_header(
+        &self,
+        mut f: impl FnMut(&RecordHeader) + Send,
+    ) -> Result<()> {
+        self.0.for_each_header(&mut f).await
+    }
+
+    /// Reads bloom filter saved in index meta.
+    pub(crate) async fn read_bloom(&self) -> Result<Bloom> {
+        let meta_buf = self.0.read_meta().await?;
+        Ok(Index::<K>::deserialize_filters(&meta_buf)?.bloom)
+    }
+}
+
+impl<K: Key> Index<K> {
+    /// Returns index file, if index is on disk.
+    pub(crate) fn detach(&self) -> Option<DetachedIndex<K>> {
+        match &self.inner {
+            State::OnDisk(findex) => Some(DetachedIndex(findex.clone())),
+            State::InMemory(_) => None,
+        }
+    }
+}
+
+// filters read from index meta
+struct MetaFilters<K: Key> {
+    bloom: Bloom,
+    range: RangeFilter<K>,
+    bloom_offset: usize,
+    static_filter: Option<StaticFilter>,
+    prefix_filter: Option<PrefixFilter>,
+}
+
+// filters saved in index meta after bloom filter, static filter is saved as `StaticFilter`,
+// which variants have the same tags, so meta without prefix filter is read as before;
+// hash algorithm of static filter is saved before it, static filters saved without it
+// are hashed with ahash
+#[derive(Serialize, Deserialize)]
+enum MetaFilter {
+    BlockedBloom(BlockedBloom),
+    Xor(XorFilter),
+    Prefix(Vec<u8>),
+    StaticHashAlgorithm(u64),
+}
+
+fn deserialize_meta_tail(buf: &[u8]) -> Result<(Option<StaticFilter>, Option<PrefixFilter>)> {
+    let mut cursor = Cursor::new(buf);
+    let (mut static_filter, mut prefix_filter) = (None, None);
+    let mut hash_algorithm = HashAlgorithm::AHash;
+    while (cursor.position() as usize) < buf.len() {
+        match bincode::deserialize_from(&mut cursor)? {
+            MetaFilter::BlockedBloom(filter) => {
+                static_filter = Some(StaticFilter::BlockedBloom(filter))
+            }
+            MetaFilter::Xor(filter) => static_filter = Some(StaticFilter::Xor(filter)),
+            MetaFilter::Prefix(buf) => prefix_filter = Some(PrefixFilter::from_raw(&buf)?),
+            MetaFilter::StaticHashAlgorithm(tag) => hash_algorithm = HashAlgorithm::from_tag(tag)?,
+        }
+    }
+    let static_filter = static_filter.map(|filter| filter.with_hash_algorithm(hash_algorithm));
+    Ok((static_filter, prefix_filter))
+}
+
+pub(crate) const HEADER_VERSION: u8 = 5;
 
 #[derive(Debug)]
 struct IndexParams {
     bloom_is_on: bool,
-    recreate_file: bool,
 }
 
 impl IndexParams {
-    fn new(bloom_is_on: bool, recreate_file: bool) -> Self {
-        Self {
-            bloom_is_on,
-            recreate_file,
-        }
+    fn new(bloom_is_on: bool) -> Self {
+        Self { bloom_is_on }
     }
 }
 
@@ -25,6 +93,14 @@ impl IndexParams {
 pub struct IndexConfig {
     pub bloom_config: Option<BloomConfig>,
     pub recreate_index_file: bool,
+    pub index_kind: IndexKind,
+    pub mmap: bool,
+    pub meta_indexes: Vec<String>,
+    pub prefix_len: Option<usize>,
+    #[serde(skip)]
+    pub node_cache: Option<NodeCache>,
+    #[serde(skip)]
+    pub filter_page_cache: FilterPageCache,
 }
 
 impl Default for IndexConfig {
@@ -32,6 +108,12 @@ impl Default for IndexConfig {
         Self {
             bloom_config: None,
             recreate_index_file: true,
+            index_kind: IndexKind::default(),
+            mmap: false,
+            meta_indexes: Vec::new(),
+            prefix_len: None,
+            node_cache: None,
+            filter_page_cache: FilterPageCache::new(),
         }
     }
 }
@@ -39,10 +121,19 @@ impl Default for IndexConfig {
 #[derive(Debug)]
 pub(crate) struct IndexStruct<FileIndex, K: Key> {
     mem: Option<MemoryAttrs>,
-    range_filter: RangeFilter<K>,
-    bloom_filter: Bloom,
+    // range, bloom and prefix filters, which are merged into filters of blob groups;
+    // bloom filter is omitted if it's replaced by static or scalable filter,
+    // prefix filter is built for dumped index, if prefix length is set
+    filter: CombinedFilter<K>,
+    // replaces bloom filter of in-memory index, if filters are auto-sized
+    scalable_filter: Option<ScalableBloom>,
+    static_filter: Option<StaticFilter>,
     bloom_offset: Option<u64>,
+    filter_lookups: AtomicU64,
+    filter_heat: AtomicU64,
+    filter_counters: FilterCounters,
     params: IndexParams,
+    config: IndexConfig,
     inner: State<FileIndex, K>,
     name: FileName,
     ioring: Option<Rio>,
@@ -69,14 +160,20 @@ pub(crate) enum State<FileIndex, K> {
 
 impl<FileIndex: FileIndexTrait<K>, K: Key> IndexStruct<FileIndex, K> {
     pub(crate) fn new(name: FileName, ioring: Option<Rio>, config: IndexConfig) -> Self {
-        let params = IndexParams::new(config.bloom_config.is_some(), config.recreate_index_file);
-        let filter = config.bloom_config.map(Bloom::new).unwrap_or_default();
+        let params = IndexParams::new(config.bloom_config.is_some());
+        let scalable_filter = Self::new_scalable_filter(&config);
+        let bloom = Self::new_bloom_filter(&config, &scalable_filter);
         let mem = Some(Default::default());
         Self {
             params,
-            bloom_filter: filter,
+            config,
+            filter: CombinedFilter::new(RangeFilter::new(), bloom, None),
+            scalable_filter,
+            static_filter: None,
             bloom_offset: None,
-            range_filter: RangeFilter::new(),
+            filter_lookups: AtomicU64::new(0),
+            filter_heat: AtomicU64::new(0),
+            filter_counters: FilterCounters::default(),
             inner: State::InMemory(BTreeMap::new()),
             mem,
             name,
@@ -87,59 +184,149 @@ impl<FileIndex: FileIndexTrait<K>, K: Key> IndexStruct<FileIndex, K> {
     pub(crate) fn clear(&mut self) {
         self.inner = State::InMemory(BTreeMap::new());
         self.mem = Some(Default::default());
-        self.bloom_filter.clear();
-        self.range_filter.clear();
+        self.scalable_filter = Self::new_scalable_filter(&self.config);
+        let bloom = Self::new_bloom_filter(&self.config, &self.scalable_filter);
+        self.filter = CombinedFilter::new(RangeFilter::new(), bloom, None);
+        self.static_filter = None;
+    }
+
+    fn new_bloom_filter(
+        config: &IndexConfig,
+        scalable_filter: &Option<ScalableBloom>,
+    ) -> Option<Bloom> {
+        if scalable_filter.is_some() {
+            return None;
+        }
+        Some(
+            config
+                .bloom_config
+                .clone()
+                .map(Bloom::new)
+                .unwrap_or_default(),
+        )
+    }
+
+    fn new_scalable_filter(config: &IndexConfig) -> Option<ScalableBloom> {
+        config
+            .bloom_config
+            .clone()
+            .filter(|config| config.auto_size)
+            .map(ScalableBloom::new)
     }
 
     pub fn offload_filter(&mut self) -> usize {
-        if self.on_disk() {
-            self.bloom_filter.offload_from_memory()
+        if self.is_filter_offloadable() {
+            self.filter
+                .bloom
+                .as_mut()
+                .map_or(0, Bloom::offload_from_memory)
         } else {
             0
         }
     }
 
     pub(crate) async fn check_filters_key(&self, key: &K) -> Result<FilterResult> {
-        if !self.range_filter.contains(key)
-            || (self.params.bloom_is_on
-                && matches!(self.check_bloom_key(key).await?, FilterResult::NotContains))
-        {
-            Ok(FilterResult::NotContains)
-        } else {
-            Ok(FilterResult::NeedAdditionalCheck)
+        if !self.filter.range.contains(key) {
+            self.filter_counters.record_range_rejected();
+            return Ok(FilterResult::NotContains);
         }
+        let res = if self.params.bloom_is_on {
+            self.check_bloom_key(key).await?
+        } else {
+            FilterResult::NeedAdditionalCheck
+        };
+        self.filter_counters.record(&res);
+        Ok(res)
     }
 
     pub(crate) fn check_filters_in_memory(&self, key: &K) -> FilterResult {
-        if !self.range_filter.contains(key)
-            || self.check_bloom_key_in_memory(key) == FilterResult::NotContains
-        {
-            FilterResult::NotContains
-        } else {
-            FilterResult::NeedAdditionalCheck
+        if !self.filter.range.contains(key) {
+            self.filter_counters.record_range_rejected();
+            return FilterResult::NotContains;
+        }
+        let res = self.check_bloom_key_in_memory(key);
+        self.filter_counters.record(&res);
+        res
+    }
+
+    /// Key passed filters, but wasn't found in index.
+    pub(crate) fn record_filter_false_positive(&self) {
+        self.filter_counters.record_false_positive();
+    }
+
+    pub(crate) fn filter_stats(&self, blob_id: usize) -> BlobFilterStats {
+        let bloom = self.filter.bloom.as_ref();
+        BlobFilterStats {
+            blob_id,
+            checks: self.filter_counters.snapshot(),
+            fill_ratio: bloom.and_then(Bloom::fill_ratio),
+            estimated_false_positive_rate: bloom.and_then(Bloom::estimated_false_positive_rate),
+            hash_algorithm: self.hash_algorithm(),
         }
     }
 
     pub fn check_bloom_key_in_memory(&self, key: &K) -> FilterResult {
         if self.params.bloom_is_on {
-            self.bloom_filter
-                .contains_in_memory(key)
+            self.filter_lookups.fetch_add(1, ORD);
+            if let Some(filter) = &self.static_filter {
+                return filter.contains(key);
+            }
+            if let Some(filter) = &self.scalable_filter {
+                return filter.contains(key);
+            }
+            self.filter
+                .bloom
+                .as_ref()
+                .and_then(|bloom| bloom.contains_in_memory(key))
                 .unwrap_or_default()
         } else {
             FilterResult::NeedAdditionalCheck
         }
     }
 
-    pub fn get_bloom_filter(&self) -> &Bloom {
-        &self.bloom_filter
+    /// Range, bloom and prefix filters of the index, bloom filter is omitted if it can't be merged
+    /// with filters of other blobs.
+    pub(crate) fn combined_filter(&self) -> &CombinedFilter<K> {
+        &self.filter
+    }
+
+    /// Checks if index may contain keys starting with `prefix` by range and prefix filters.
+    pub(crate) fn check_prefix_filters(&self, prefix: &[u8]) -> FilterResult {
+        if !self.filter.range.contains_prefix(prefix) {
+            FilterResult::NotContains
+        } else {
+            self.filter
+                .prefix
+                .as_ref()
+                .map_or(FilterResult::NeedAdditionalCheck, |filter| {
+                    filter.contains_prefix(prefix)
+                })
+        }
+    }
+
+    /// Returns sorted keys, which start with `prefix`.
+    pub(crate) async fn keys_with_prefix(&self, prefix: &[u8]) -> Result<Vec<K>> {
+        match &self.inner {
+            State::InMemory(headers) => Ok(keys_with_prefix(headers, prefix)),
+            State::OnDisk(findex) => findex.keys_with_prefix(prefix).await,
+        }
     }
 
     pub async fn check_bloom_key(&self, key: &K) -> Result<FilterResult> {
         if self.params.bloom_is_on {
-            if let Some(result) = self.bloom_filter.contains_in_memory(key) {
-                Ok(result)
-            } else {
-                Ok(self.bloom_filter.contains_in_file(self, key).await?)
+            self.filter_lookups.fetch_add(1, ORD);
+            if let Some(filter) = &self.static_filter {
+                return Ok(filter.contains(key));
+            }
+            if let Some(filter) = &self.scalable_filter {
+                return Ok(filter.contains(key));
+            }
+            match &self.filter.bloom {
+                Some(bloom) => match bloom.contains_in_memory(key) {
+                    Some(result) => Ok(result),
+                    None => Ok(bloom.contains_in_file(self, key).await?),
+                },
+                None => Ok(FilterResult::NeedAdditionalCheck),
             }
         } else {
             Ok(FilterResult::NeedAdditionalCheck)
@@ -147,51 +334,203 @@ impl<FileIndex: FileIndexTrait<K>, K: Key> IndexStruct<FileIndex, K> {
     }
 
     pub fn is_filter_offloaded(&self) -> bool {
-        self.bloom_filter.is_offloaded()
+        matches!(&self.filter.bloom, Some(bloom) if bloom.is_offloaded())
     }
 
     pub fn bloom_memory_allocated(&self) -> usize {
-        self.bloom_filter.memory_allocated()
+        self.filter
+            .bloom
+            .as_ref()
+            .map_or(0, Bloom::memory_allocated)
+            + self.static_filter_memory()
+            + self.prefix_filter_memory()
+            + self
+                .scalable_filter
+                .as_ref()
+                .map_or(0, ScalableBloom::memory_allocated)
+    }
+
+    /// Memory, which bloom filter occupies, when it's loaded.
+    pub(crate) fn bloom_memory_required(&self) -> usize {
+        self.filter.bloom.as_ref().map_or(0, Bloom::memory_required)
+            + self.static_filter_memory()
+            + self.prefix_filter_memory()
+    }
+
+    fn prefix_filter_memory(&self) -> usize {
+        self.filter
+            .prefix
+            .as_ref()
+            .map_or(0, PrefixFilter::memory_allocated)
+    }
+
+    fn static_filter_memory(&self) -> usize {
+        self.static_filter
+            .as_ref()
+            .map_or(0, StaticFilter::memory_allocated)
+    }
+
+    /// Only bloom filter of index, dumped to disk, can be offloaded.
+    pub(crate) fn is_filter_offloadable(&self) -> bool {
+        self.on_disk() && self.static_filter.is_none()
+    }
+
+    // bloom filter lookups since the previous call are added to the heat,
+    // which is halved on each call, so rarely used filters cool down over time
+    pub(crate) fn update_filter_heat(&self) -> u64 {
+        let lookups = self.filter_lookups.swap(0, ORD);
+        let heat = self.filter_heat.load(ORD) / 2 + lookups;
+        self.filter_heat.store(heat, ORD);
+        heat
+    }
+
+    /// Loads offloaded bloom filter back from the index file.
+    /// Returns memory allocated by the filter.
+    pub(crate) async fn reload_filter(&mut self) -> Result<usize> {
+        if let State::OnDisk(findex) = &self.inner {
+            if self.is_filter_offloaded() {
+                let meta_buf = findex.read_meta().await?;
+                self.filter.bloom = Some(Self::deserialize_filters(&meta_buf)?.bloom);
+            }
+        }
+        Ok(self
+            .filter
+            .bloom
+            .as_ref()
+            .map_or(0, Bloom::memory_allocated))
+    }
+
+    /// Sets filter read from index file, if filter is still offloaded.
+    pub(crate) fn restore_filter(&mut self, bloom: Bloom) -> usize {
+        if self.on_disk() && self.is_filter_offloaded() {
+            self.filter.bloom = Some(bloom);
+        }
+        self.filter
+            .bloom
+            .as_ref()
+            .map_or(0, Bloom::memory_allocated)
+    }
+
+    // hash algorithm of the filters of the index, filters of dumped index keep the algorithm
+    // they were saved with
+    fn hash_algorithm(&self) -> Option<HashAlgorithm> {
+        if !self.params.bloom_is_on {
+            return None;
+        }
+        match (&self.static_filter, &self.filter.bloom) {
+            (Some(filter), _) => Some(filter.hash_algorithm()),
+            (None, Some(bloom)) => Some(bloom.hash_algorithm()),
+            (None, None) => self
+                .config
+                .bloom_config
+                .as_ref()
+                .map(|config| config.hash_algorithm),
+        }
+    }
+
+    /// Hash of the records of dumped index, `None` for in-memory index.
+    pub(crate) fn records_hash(&self) -> Option<&[u8]> {
+        match &self.inner {
+            State::OnDisk(findex) => Some(findex.records_hash()),
+            State::InMemory(_) => None,
+        }
     }
 
     pub(crate) fn name(&self) -> &FileName {
         &self.name
     }
 
+    /// Opens dumped index. If `load_bloom` is false, bloom filter data isn't read,
+    /// so filter stays offloaded until it's reloaded.
     pub(crate) async fn from_file(
         name: FileName,
         config: IndexConfig,
         ioring: Option<Rio>,
+        load_bloom: bool,
     ) -> Result<Self> {
-        let findex = FileIndex::from_file(name.clone(), ioring.clone()).await?;
+        let findex = FileIndex::from_file(name.clone(), ioring.clone(), &config).await?;
         findex.validate().with_context(|| "Header is corrupt")?;
-        let meta_buf = findex.read_meta().await?;
-        let (bloom_filter, range_filter, bloom_offset) = Self::deserialize_filters(&meta_buf)?;
-        let params = IndexParams::new(config.bloom_config.is_some(), config.recreate_index_file);
+        let filters = if load_bloom {
+            Self::deserialize_filters(&findex.read_meta().await?)?
+        } else {
+            Self::read_filters_offloaded(&findex).await?
+        };
+        let params = IndexParams::new(config.bloom_config.is_some());
+        let has_static_filter = filters.static_filter.is_some();
+        let bloom = Some(filters.bloom).filter(|_| !has_static_filter);
         trace!("index restored successfuly");
         let index = Self {
             inner: State::OnDisk(findex),
+            config,
             mem: None,
             name,
-            bloom_filter,
-            bloom_offset: Some(bloom_offset as u64),
-            range_filter,
+            filter: CombinedFilter::new(filters.range, bloom, filters.prefix_filter),
+            static_filter: filters.static_filter,
+            bloom_offset: Some(filters.bloom_offset as u64),
+            filter_lookups: AtomicU64::new(0),
+            filter_heat: AtomicU64::new(0),
+            filter_counters: FilterCounters::default(),
+            scalable_filter: None,
             params,
             ioring,
         };
         Ok(index)
     }
 
+    pub(crate) fn config(&self) -> &IndexConfig {
+        &self.config
+    }
+
     pub(crate) fn on_disk(&self) -> bool {
         matches!(&self.inner, State::OnDisk(_))
     }
 
+    /// Passes all headers to `f`, index file is read, if index is on disk.
+    pub(crate) async fn for_each_header(
+        &self,
+        mut f: impl FnMut(&RecordHeader) + Send,
+    ) -> Result<()> {
+        match &self.inner {
+            State::InMemory(headers) => headers.values().flatten().for_each(f),
+            State::OnDisk(findex) => findex.for_each_header(&mut f).await?,
+        }
+        Ok(())
+    }
+
     async fn dump_in_memory(&mut self) -> Result<usize> {
         if let State::InMemory(headers) = &self.inner {
             if headers.len() == 0 {
                 return Ok(0);
             }
             debug!("blob index simple in memory headers {}", headers.len());
+            // scalable filter is replaced by static or sized bloom filter of the dumped index
+            let scalable_filter = self.scalable_filter.take();
+            if let Some(filter) = self
+                .config
+                .bloom_config
+                .as_ref()
+                .and_then(|config| StaticFilter::build(config, headers.keys()))
+            {
+                self.static_filter = Some(filter);
+                self.filter.bloom = None;
+            } else if let (Some(_), Some(config)) = (scalable_filter, &self.config.bloom_config) {
+                // bloom filter is sized for the keys of the blob
+                let mut bloom_filter = Bloom::new(config.sized_for(headers.len()));
+                for key in headers.keys() {
+                    bloom_filter.add(key)?;
+                }
+                self.filter.bloom = Some(bloom_filter);
+            }
+            self.filter.range = RangeFilter::from_sorted_keys(headers.keys());
+            if let (Some(prefix_len), Some(config)) =
+                (self.config.prefix_len, &self.config.bloom_config)
+            {
+                self.filter.prefix = Some(PrefixFilter::build(
+                    prefix_len,
+                    config.clone(),
+                    headers.keys(),
+                )?);
+            }
             let (meta_buf, bloom_offset) = self.serialize_filters()?;
             self.bloom_offset = Some(bloom_offset as u64);
             let findex = FileIndex::from_records(
@@ -199,10 +538,12 @@ impl<FileIndex: FileIndexTrait<K>, K: Key> IndexStruct<FileIndex, K> {
                 self.ioring.clone(),
                 headers,
                 meta_buf,
-                self.params.recreate_file,
+                &self.config,
             )
             .await?;
             let size = findex.file_size() as usize;
+            // pages of the previous index file of this blob are outdated
+            self.config.filter_page_cache.remove_blob(self.name.id());
             self.inner = State::OnDisk(findex);
             self.mem = None;
             Ok(size)
@@ -212,24 +553,72 @@ impl<FileIndex: FileIndexTrait<K>, K: Key> IndexStruct<FileIndex, K> {
     }
 
     fn serialize_filters(&self) -> Result<(Vec<u8>, usize)> {
-        let range_buf = self.range_filter.to_raw()?;
+        let range_buf = self.filter.range.to_raw()?;
         let range_buf_size = range_buf.len() as u64;
-        let bloom_buf = self.bloom_filter.to_raw()?;
+        // omitted bloom filter is saved empty to keep meta layout
+        let bloom_buf = match &self.filter.bloom {
+            Some(bloom) => bloom.to_raw()?,
+            None => Bloom::default().to_raw()?,
+        };
         let mut buf = Vec::with_capacity(size_of::<u64>() + range_buf.len() + bloom_buf.len());
         let bloom_offset = size_of::<u64>() + range_buf.len();
         buf.extend_from_slice(&serialize(&range_buf_size)?);
         buf.extend_from_slice(&range_buf);
         buf.extend_from_slice(&bloom_buf);
+        if let Some(filter) = &self.static_filter {
+            let hash_algorithm = MetaFilter::StaticHashAlgorithm(filter.hash_algorithm().tag());
+            serialize_into(&mut buf, &hash_algorithm)?;
+            serialize_into(&mut buf, filter)?;
+        }
+        if let Some(filter) = &self.filter.prefix {
+            serialize_into(&mut buf, &MetaFilter::Prefix(filter.to_raw()?))?;
+        }
         Ok((buf, bloom_offset))
     }
 
-    fn deserialize_filters(buf: &[u8]) -> Result<(Bloom, RangeFilter<K>, usize)> {
+    // meta layout: range filter size, range filter, bloom filter, optional hash algorithm and
+    // static filter, optional prefix filter
+    fn deserialize_filters(buf: &[u8]) -> Result<MetaFilters<K>> {
         let (range_size_buf, rest_buf) = buf.split_at(size_of::<u64>());
         let range_size = deserialize(&range_size_buf)?;
         let (range_buf, bloom_buf) = rest_buf.split_at(range_size);
-        let bloom = Bloom::from_raw(bloom_buf)?;
-        let range = RangeFilter::<K>::from_raw(range_buf)?;
-        Ok((bloom, range, range_size + size_of::<u64>()))
+        let (bloom, tail_buf) = Bloom::from_raw_prefix(bloom_buf)?;
+        let (static_filter, prefix_filter) = deserialize_meta_tail(tail_buf)?;
+        Ok(MetaFilters {
+            bloom,
+            range: RangeFilter::<K>::from_raw(range_buf)?,
+            bloom_offset: range_size + size_of::<u64>(),
+            static_filter,
+            prefix_filter,
+        })
+    }
+
+    // reads meta parts around bloom filter data: range filter, bloom filter config,
+    // its bits count, static and prefix filters
+    async fn read_filters_offloaded(findex: &FileIndex) -> Result<MetaFilters<K>> {
+        let u64_size = size_of::<u64>();
+        let range_size_buf = findex.read_meta_ranges(&[(0, u64_size)]).await?;
+        let range_size: u64 = deserialize(&range_size_buf[0])?;
+        let max_prefix_size = Bloom::raw_prefix_size()?;
+        let head = findex
+            .read_meta_ranges(&[(u64_size as u64, (range_size + max_prefix_size) as usize)])
+            .await?;
+        let (range_buf, bloom_prefix) = head[0].split_at(range_size as usize);
+        let bloom_offset = u64_size as u64 + range_size;
+        let (prefix_size, buffer_size) = Bloom::raw_layout(bloom_prefix)?;
+        let tail_offset = bloom_offset + prefix_size + buffer_size;
+        let tail = findex
+            .read_meta_ranges(&[(tail_offset, usize::MAX)])
+            .await?;
+        let (bits_count_buf, tail_buf) = tail[0].split_at(u64_size);
+        let (static_filter, prefix_filter) = deserialize_meta_tail(tail_buf)?;
+        Ok(MetaFilters {
+            bloom: Bloom::offloaded_from_raw(bloom_prefix, bits_count_buf)?,
+            range: RangeFilter::<K>::from_raw(range_buf)?,
+            bloom_offset: bloom_offset as usize,
+            static_filter,
+            prefix_filter,
+        })
     }
 
     async fn load_in_memory(&mut self, findex: FileIndex) -> Result<()> {
@@ -237,9 +626,32 @@ impl<FileIndex: FileIndexTrait<K>, K: Key> IndexStruct<FileIndex, K> {
         self.mem = Some(compute_mem_attrs(&record_headers, records_count));
         self.inner = State::InMemory(record_headers);
         let meta_buf = findex.read_meta().await?;
-        let (bloom_filter, range_filter, _) = Self::deserialize_filters(&meta_buf)?;
-        self.bloom_filter = bloom_filter;
-        self.range_filter = range_filter;
+        let filters = Self::deserialize_filters(&meta_buf)?;
+        self.filter.bloom = Some(filters.bloom);
+        self.scalable_filter = Self::new_scalable_filter(&self.config);
+        if let (Some(scalable_filter), State::InMemory(headers)) =
+            (&mut self.scalable_filter, &self.inner)
+        {
+            for key in headers.keys() {
+                scalable_filter.add(key)?;
+            }
+            self.filter.bloom = None;
+        } else if let (Some(_), Some(config), State::InMemory(headers)) = (
+            filters.static_filter,
+            &self.config.bloom_config,
+            &self.inner,
+        ) {
+            // static filter can't be updated on writes, so bloom filter is restored from keys
+            let mut bloom_filter = Bloom::new(config.clone());
+            for key in headers.keys() {
+                bloom_filter.add(key)?;
+            }
+            self.filter.bloom = Some(bloom_filter);
+        }
+        self.static_filter = None;
+        // prefix filter is rebuilt on the next dump
+        self.filter.prefix = None;
+        self.filter.range = filters.range;
         self.bloom_offset = None;
         Ok(())
     }
@@ -278,9 +690,13 @@ where
         match &mut self.inner {
             State::InMemory(headers) => {
                 debug!("blob index simple push bloom filter add");
-                let _ = self.bloom_filter.add(h.key());
+                if let Some(filter) = &mut self.scalable_filter {
+                    let _ = filter.add(h.key());
+                } else if let Some(bloom) = &mut self.filter.bloom {
+                    let _ = bloom.add(h.key());
+                }
                 let key = h.key().to_vec().into();
-                self.range_filter.add(&key);
+                self.filter.range.add(&key);
                 debug!("blob index simple push key: {:?}", h.key());
                 let mem = self
                     .mem
@@ -312,7 +728,10 @@ where
 
     async fn get_all(&self, key: &K) -> Result<Option<Vec<RecordHeader>>> {
         match &self.inner {
-            State::InMemory(headers) => Ok(headers.get(key).cloned()),
+            // headers are returned newest first, as file indexes do
+            State::InMemory(headers) => Ok(headers
+                .get(key)
+                .map(|headers| headers.iter().rev().cloned().collect())),
             State::OnDisk(findex) => findex.find_by_key(key).await,
         }
     }
@@ -322,7 +741,7 @@ where
         match &self.inner {
             State::InMemory(headers) => {
                 debug!("index get any in memory headers: {}", headers.len());
-                Ok(headers.get(key).and_then(|h| h.first()).cloned())
+                Ok(headers.get(key).and_then(|h| h.last()).cloned())
             }
             State::OnDisk(findex) => {
                 debug!("index get any on disk");
@@ -360,21 +779,28 @@ where
 
 #[async_trait::async_trait]
 pub(crate) trait FileIndexTrait<K>: Sized + Send + Sync {
-    async fn from_file(name: FileName, ioring: Option<Rio>) -> Result<Self>;
+    async fn from_file(name: FileName, ioring: Option<Rio>, config: &IndexConfig) -> Result<Self>;
     async fn from_records(
         path: &Path,
         rio: Option<Rio>,
         headers: &InMemoryIndex<K>,
         meta: Vec<u8>,
-        recreate_index_file: bool,
+        config: &IndexConfig,
     ) -> Result<Self>;
     fn file_size(&self) -> u64;
     fn records_count(&self) -> usize;
+    fn records_hash(&self) -> &[u8];
     async fn read_meta(&self) -> Result<Vec<u8>>;
     async fn read_meta_at(&self, i: u64) -> Result<u8>;
+    async fn read_meta_ranges(&self, ranges: &[(u64, usize)]) -> Result<Vec<Vec<u8>>>;
     async fn find_by_key(&self, key: &K) -> Result<Option<Vec<RecordHeader>>>;
     async fn get_records_headers(&self) -> Result<(InMemoryIndex<K>, usize)>;
+    async fn for_each_header(
+        &self,
+        f: &mut (dyn for<'h> FnMut(&'h RecordHeader) + Send),
+    ) -> Result<()>;
     async fn get_any(&self, key: &K) -> Result<Option<RecordHeader>>;
+    async fn keys_with_prefix(&self, prefix: &[u8]) -> Result<Vec<K>>;
     fn validate(&self) -> Result<()>;
 }
 
@@ -390,4 +816,100 @@ impl<FileIndex: FileIndexTrait<K>, K: Key> BloomDataProvider for IndexStruct<Fil
             _ => Err(anyhow::anyhow!("Can't read from in-memory index")),
         }
     }
+
+    // bytes are served from the page cache, missing pages are read from the index file at once
+    async fn read_bytes(&self, indices: &[u64]) -> Result<Vec<u8>> {
+        let findex = match &self.inner {
+            State::OnDisk(findex) => findex,
+            _ => return Err(anyhow::anyhow!("Can't read from in-memory index")),
+        };
+        let bloom_offset = self.bloom_offset.expect("should be set after dump");
+        let cache = &self.config.filter_page_cache;
+        let blob_id = self.name.id();
+        let page_size = FILTER_PAGE_SIZE as u64;
+        let mut pages: BTreeMap<u64, Option<Bytes>> = indices
+            .iter()
+            .map(|index| (index / page_size, None))
+            .collect();
+        for (page, data) in &mut pages {
+            *data = cache.get(blob_id, *page);
+        }
+        let missed: Vec<_> = pages
+            .iter()
+            .filter(|(_, data)| data.is_none())
+            .map(|(page, _)| *page)
+            .collect();
+        if !missed.is_empty() {
+            let ranges: Vec<_> = missed
+                .iter()
+                .map(|page| (bloom_offset + page * page_size, FILTER_PAGE_SIZE))
+                .collect();
+            let bufs = findex.read_meta_ranges(&ranges).await?;
+            for (page, buf) in missed.into_iter().zip(bufs) {
+                let buf = Bytes::from(buf);
+                cache.insert(blob_id, page, buf.clone());
+                pages.insert(page, Some(buf));
+            }
+        }
+        indices
+            .iter()
+            .map(|index| {
+                pages[&(index / page_size)]
+                    .as_ref()
+                    .and_then(|page| page.get((index % page_size) as usize).copied())
+                    .ok_or_else(|| anyhow::anyhow!("read meta out of range"))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::FilterKind;
+
+    #[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+    struct TestKey(Vec<u8>);
+
+    impl Key for TestKey {
+        const LEN: u16 = 4;
+    }
+
+    impl From<Vec<u8>> for TestKey {
+        fn from(v: Vec<u8>) -> Self {
+            Self(v)
+        }
+    }
+
+    impl AsRef<[u8]> for TestKey {
+        fn as_ref(&self) -> &[u8] {
+            &self.0
+        }
+    }
+
+    #[tokio::test]
+    async fn dump_drops_scalable_filter() -> Result<()> {
+        let dir = std::env::temp_dir().join("pearl_dump_drops_scalable_filter");
+        std::fs::create_dir_all(&dir)?;
+        let name = FileName::new("test".to_owned(), 0, "index".to_owned(), dir.clone());
+        let config = IndexConfig {
+            bloom_config: Some(BloomConfig {
+                kind: FilterKind::Xor,
+                auto_size: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut index = Index::<TestKey>::new(name, None, config);
+        for key in 0_u32..1_000 {
+            index.push(RecordHeader::new(key.to_be_bytes().to_vec(), 1, 1, 1))?;
+        }
+        assert!(index.bloom_memory_allocated() > 0);
+        index.dump().await?;
+        // only static filter of the dumped index is left
+        assert!(index.scalable_filter.is_none());
+        assert_eq!(index.bloom_memory_allocated(), index.static_filter_memory());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
 }
@@ -0,0 +1,167 @@
+use super::prelude::*;
+
+/// Layout of `.index` files, which are written when blob is closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum IndexKind {
+    /// B+-tree over record headers sorted by key.
+    #[default]
+    BPTree,
+    /// Static open-addressing hash table, usually needs one read per lookup.
+    /// Suits point lookup workloads best.
+    Hash,
+    /// Sorted array of record headers, lookup is a binary search over the whole file.
+    Simple,
+}
+
+/// File index, which dispatches calls to the implementation chosen by [`IndexKind`].
+#[derive(Debug, Clone)]
+pub(crate) enum FileIndex<K> {
+    BPTree(Box<BPTreeFileIndex<K>>),
+    Hash(HashFileIndex<K>),
+    Simple(SimpleFileIndex),
+}
+
+#[async_trait::async_trait]
+impl<K: Key + 'static> FileIndexTrait<K> for FileIndex<K> {
+    async fn from_file(name: FileName, ioring: Option<Rio>, config: &IndexConfig) -> Result<Self> {
+        // kind is taken from the file itself, config only matters for newly created indexes
+        let file = File::open(name.to_path(), ioring.clone())
+            .await
+            .context(format!("failed to open index file: {}", name))?;
+        let header = IndexHeader::from_file(&file).await?;
+        match header.kind {
+            IndexKind::BPTree => BPTreeFileIndex::from_file(name, ioring, config)
+                .await
+                .map(|findex| Self::BPTree(Box::new(findex))),
+            IndexKind::Hash => HashFileIndex::from_file(name, ioring, config)
+                .await
+                .map(Self::Hash),
+            IndexKind::Simple => {
+                <SimpleFileIndex as FileIndexTrait<K>>::from_file(name, ioring, config)
+                    .await
+                    .map(Self::Simple)
+            }
+        }
+    }
+
+    async fn from_records(
+        path: &Path,
+        ioring: Option<Rio>,
+        headers: &InMemoryIndex<K>,
+        meta: Vec<u8>,
+        config: &IndexConfig,
+    ) -> Result<Self> {
+        match config.index_kind {
+            IndexKind::BPTree => BPTreeFileIndex::from_records(path, ioring, headers, meta, config)
+                .await
+                .map(|findex| Self::BPTree(Box::new(findex))),
+            IndexKind::Hash => HashFileIndex::from_records(path, ioring, headers, meta, config)
+                .await
+                .map(Self::Hash),
+            IndexKind::Simple => <SimpleFileIndex as FileIndexTrait<K>>::from_records(
+                path, ioring, headers, meta, config,
+            )
+            .await
+            .map(Self::Simple),
+        }
+    }
+
+    fn file_size(&self) -> u64 {
+        match self {
+            Self::BPTree(findex) => findex.file_size(),
+            Self::Hash(findex) => findex.file_size(),
+            Self::Simple(findex) => FileIndexTrait::<K>::file_size(findex),
+        }
+    }
+
+    fn records_count(&self) -> usize {
+        match self {
+            Self::BPTree(findex) => findex.records_count(),
+            Self::Hash(findex) => findex.records_count(),
+            Self::Simple(findex) => FileIndexTrait::<K>::records_count(findex),
+        }
+    }
+
+    fn records_hash(&self) -> &[u8] {
+        match self {
+            Self::BPTree(findex) => findex.records_hash(),
+            Self::Hash(findex) => findex.records_hash(),
+            Self::Simple(findex) => FileIndexTrait::<K>::records_hash(findex),
+        }
+    }
+
+    async fn read_meta(&self) -> Result<Vec<u8>> {
+        match self {
+            Self::BPTree(findex) => findex.read_meta().await,
+            Self::Hash(findex) => findex.read_meta().await,
+            Self::Simple(findex) => FileIndexTrait::<K>::read_meta(findex).await,
+        }
+    }
+
+    async fn read_meta_at(&self, i: u64) -> Result<u8> {
+        match self {
+            Self::BPTree(findex) => findex.read_meta_at(i).await,
+            Self::Hash(findex) => findex.read_meta_at(i).await,
+            Self::Simple(findex) => FileIndexTrait::<K>::read_meta_at(findex, i).await,
+        }
+    }
+
+    async fn read_meta_ranges(&self, ranges: &[(u64, usize)]) -> Result<Vec<Vec<u8>>> {
+        match self {
+            Self::BPTree(findex) => findex.read_meta_ranges(ranges).await,
+            Self::Hash(findex) => findex.read_meta_ranges(ranges).await,
+            Self::Simple(findex) => FileIndexTrait::<K>::read_meta_ranges(findex, ranges).await,
+        }
+    }
+
+    async fn find_by_key(&self, key: &K) -> Result<Option<Vec<RecordHeader>>> {
+        match self {
+            Self::BPTree(findex) => findex.find_by_key(key).await,
+            Self::Hash(findex) => findex.find_by_key(key).await,
+            Self::Simple(findex) => FileIndexTrait::<K>::find_by_key(findex, key).await,
+        }
+    }
+
+    async fn get_records_headers(&self) -> Result<(InMemoryIndex<K>, usize)> {
+        match self {
+            Self::BPTree(findex) => findex.get_records_headers().await,
+            Self::Hash(findex) => findex.get_records_headers().await,
+            Self::Simple(findex) => FileIndexTrait::<K>::get_records_headers(findex).await,
+        }
+    }
+
+    async fn for_each_header(
+        &self,
+        f: &mut (dyn for<'h> FnMut(&'h RecordHeader) + Send),
+    ) -> Result<()> {
+        match self {
+            Self::BPTree(findex) => findex.for_each_header(f).await,
+            Self::Hash(findex) => findex.for_each_header(f).await,
+            Self::Simple(findex) => FileIndexTrait::<K>::for_each_header(findex, f).await,
+        }
+    }
+
+    async fn get_any(&self, key: &K) -> Result<Option<RecordHeader>> {
+        match self {
+            Self::BPTree(findex) => findex.get_any(key).await,
+            Self::Hash(findex) => findex.get_any(key).await,
+            Self::Simple(findex) => FileIndexTrait::<K>::get_any(findex, key).await,
+        }
+    }
+
+    async fn keys_with_prefix(&self, prefix: &[u8]) -> Result<Vec<K>> {
+        match self {
+            Self::BPTree(findex) => findex.keys_with_prefix(prefix).await,
+            Self::Hash(findex) => findex.keys_with_prefix(prefix).await,
+            Self::Simple(findex) => FileIndexTrait::<K>::keys_with_prefix(findex, prefix).await,
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        match self {
+            Self::BPTree(findex) => findex.validate(),
+            Self::Hash(findex) => findex.validate(),
+            Self::Simple(findex) => FileIndexTrait::<K>::validate(findex),
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use super::prelude::*;
+use std::sync::Mutex;
+
+/// Size of the cached page of offloaded bloom filter data in bytes.
+pub(crate) const FILTER_PAGE_SIZE: usize = 4096;
+const FILTER_PAGES_LIMIT: usize = 64;
+
+type PageKey = (usize, u64);
+
+/// Storage-wide cache of recently read pages of offloaded bloom filters, the least recently
+/// used page is evicted first. Pages are identified by blob id and page number in filter data.
+#[derive(Clone)]
+pub(crate) struct FilterPageCache {
+    inner: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    pages: HashMap<PageKey, (Bytes, u64)>,
+    tick: u64,
+}
+
+impl FilterPageCache {
+    pub(crate) fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(State::default())),
+        }
+    }
+
+    pub(crate) fn get(&self, blob_id: usize, page: u64) -> Option<Bytes> {
+        let mut state = self.inner.lock().expect("filter page cache lock poisoned");
+        state.tick += 1;
+        let tick = state.tick;
+        state.pages.get_mut(&(blob_id, page)).map(|(data, used)| {
+            *used = tick;
+            data.clone()
+        })
+    }
+
+    pub(crate) fn insert(&self, blob_id: usize, page: u64, data: Bytes) {
+        let mut state = self.inner.lock().expect("filter page cache lock poisoned");
+        state.tick += 1;
+        let tick = state.tick;
+        if state.pages.len() >= FILTER_PAGES_LIMIT && !state.pages.contains_key(&(blob_id, page)) {
+            let oldest = state
+                .pages
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                state.pages.remove(&oldest);
+            }
+        }
+        state.pages.insert((blob_id, page), (data, tick));
+    }
+
+    /// Drops all pages of the blob, must be called when index file of the blob is rewritten.
+    pub(crate) fn remove_blob(&self, blob_id: usize) {
+        self.inner
+            .lock()
+            .expect("filter page cache lock poisoned")
+            .pages
+            .retain(|(id, _), _| *id != blob_id);
+    }
+
+    fn pages_count(&self) -> usize {
+        self.inner
+            .lock()
+            .expect("filter page cache lock poisoned")
+            .pages
+            .len()
+    }
+}
+
+impl Default for FilterPageCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for FilterPageCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("FilterPageCache")
+            .field("pages", &self.pages_count())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_page_cache_eviction() {
+        let cache = FilterPageCache::new();
+        for page in 0..FILTER_PAGES_LIMIT as u64 {
+            cache.insert(0, page, Bytes::from(vec![page as u8]));
+        }
+        // page 0 becomes the most recently used one, so page 1 is evicted
+        assert!(cache.get(0, 0).is_some());
+        cache.insert(1, 0, Bytes::from_static(b"new"));
+        assert_eq!(cache.pages_count(), FILTER_PAGES_LIMIT);
+        assert!(cache.get(0, 1).is_none());
+        assert_eq!(cache.get(0, 0), Some(Bytes::from(vec![0])));
+        cache.remove_blob(0);
+        assert_eq!(cache.pages_count(), 1);
+        assert_eq!(cache.get(1, 0), Some(Bytes::from_static(b"new")));
+    }
+}
@@ -0,0 +1,338 @@
+use crate::error::ValidationErrorKind;
+
+/// structure of hash index file from the beginning:
+/// 1. Header
+/// 2. hash index user buffer (now Bloom filter is stored as this buffer)
+/// 3. Table metadata (buckets_count and slots_count)
+/// 4. Table: array of fixed size slots, every slot is either zeroed or contains
+///    serialized record header. Header is placed into the first free slot starting from
+///    `hash(key) % buckets_count` (linear probing), so all headers with the same key lie
+///    between this slot and the next empty one. Probing never wraps around, slots after
+///    `buckets_count` are overflow of the last buckets.
+use super::prelude::*;
+
+// table contains twice as many buckets as records, so probe sequences stay short
+const BUCKETS_PER_RECORD: usize = 2;
+// probing reads slots by portions of this size
+const PROBE_BUF_SIZE: usize = 4096;
+// FNV-1a parameters, hash must be stable because it defines position of records in file
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+#[derive(Debug, Clone)]
+pub(crate) struct HashFileIndex<K> {
+    file: File,
+    header: IndexHeader,
+    metadata: TableMeta,
+    key_type_marker: PhantomData<K>,
+}
+
+#[async_trait::async_trait]
+impl<K: Key + 'static> FileIndexTrait<K> for HashFileIndex<K> {
+    async fn from_file(name: FileName, ioring: Option<Rio>, config: &IndexConfig) -> Result<Self> {
+        trace!("open index file");
+        let mut file = File::open(name.to_path(), ioring)
+            .await
+            .context(format!("failed to open index file: {}", name))?;
+        if config.mmap {
+            file.enable_mmap(Advice::WillNeed);
+        }
+        let header = IndexHeader::from_file(&file).await?;
+        let metadata = Self::read_table_meta(&file, &header).await?;
+
+        Ok(Self {
+            file,
+            header,
+            metadata,
+            key_type_marker: PhantomData,
+        })
+    }
+
+    async fn from_records(
+        path: &Path,
+        ioring: Option<Rio>,
+        headers: &InMemoryIndex<K>,
+        meta: Vec<u8>,
+        config: &IndexConfig,
+    ) -> Result<Self> {
+        clean_file(path, config.recreate_index_file)?;
+        let (mut header, metadata, buf) = Self::serialize(headers, meta)?;
+        let mut file = File::create(path, ioring)
+            .await
+            .with_context(|| format!("file open failed {:?}", path))?;
+        file.write_append(&buf).await?;
+        header.set_written(true);
+        let serialized_header = serialize(&header)?;
+        file.write_at(0, &serialized_header).await?;
+        file.fsyncdata().await?;
+        if config.mmap {
+            file.enable_mmap(Advice::WillNeed);
+        }
+        Ok(Self {
+            file,
+            header,
+            metadata,
+            key_type_marker: PhantomData,
+        })
+    }
+
+    fn file_size(&self) -> u64 {
+        self.file.size()
+    }
+
+    fn records_count(&self) -> usize {
+        self.header.records_count
+    }
+
+    fn records_hash(&self) -> &[u8] {
+        &self.header.hash
+    }
+
+    async fn read_meta(&self) -> Result<Vec<u8>> {
+        trace!("load meta");
+        let mut buf = vec![0; self.header.meta_size];
+        trace!("read meta into buf: [0; {}]", buf.len());
+        self.file
+            .read_at(&mut buf, self.header.serialized_size()?)
+            .await?;
+        Ok(buf)
+    }
+
+    async fn read_meta_at(&self, i: u64) -> Result<u8> {
+        trace!("load byte from meta");
+        if i >= self.header.meta_size as u64 {
+            return Err(anyhow::anyhow!("read meta out of range"));
+        }
+        let mut buf = [0; 1];
+        self.file
+            .read_at(&mut buf, self.header.serialized_size()? + i)
+            .await?;
+        Ok(buf[0])
+    }
+
+    async fn read_meta_ranges(&self, ranges: &[(u64, usize)]) -> Result<Vec<Vec<u8>>> {
+        read_meta_ranges(&self.file, &self.header, ranges).await
+    }
+
+    async fn find_by_key(&self, key: &K) -> Result<Option<Vec<RecordHeader>>> {
+        let headers = self.probe(key, false).await?;
+        if headers.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(headers))
+        }
+    }
+
+    async fn get_records_headers(&self) -> Result<(InMemoryIndex<K>, usize)> {
+        let mut buf = self.file.read_all().await?;
+        self.validate_header(&mut buf).await?;
+        let offset = self.table_offset()? as usize;
+        let slot_size = self.header.record_header_size;
+        let table_buf = &buf[offset..];
+        table_buf
+            .chunks(slot_size)
+            .take(self.metadata.slots_count as usize)
+            .filter(|raw| !Self::is_empty_slot(raw))
+            .try_fold(InMemoryIndex::new(), |mut headers, raw| {
+                let header: RecordHeader = deserialize(raw)?;
+                // We use get mut instead of entry(..).or_insert(..) because in second case we
+                // need to clone header.
+                let key = header.key().to_vec().into();
+                if let Some(v) = headers.get_mut(&key) {
+                    v.push(header)
+                } else {
+                    headers.insert(key, vec![header]);
+                }
+                Ok(headers)
+            })
+            .map(|headers| (headers, self.header.records_count))
+    }
+
+    async fn for_each_header(
+        &self,
+        f: &mut (dyn for<'h> FnMut(&'h RecordHeader) + Send),
+    ) -> Result<()> {
+        let start = self.table_offset()?;
+        let slot_size = self.header.record_header_size;
+        let range = (start, start + self.metadata.slots_count * slot_size as u64);
+        let is_header: fn(&[u8]) -> bool = |raw| !Self::is_empty_slot(raw);
+        for_each_header_in(&self.file, range, slot_size, is_header, f).await
+    }
+
+    // all headers are read, keys are sorted only in the in-memory index
+    async fn keys_with_prefix(&self, prefix: &[u8]) -> Result<Vec<K>> {
+        let (headers, _) = self.get_records_headers().await?;
+        Ok(keys_with_prefix(&headers, prefix))
+    }
+
+    async fn get_any(&self, key: &K) -> Result<Option<RecordHeader>> {
+        let headers = self.probe(key, true).await?;
+        Ok(headers.into_iter().next())
+    }
+
+    fn validate(&self) -> Result<()> {
+        // FIXME: check hash here?
+        if !self.header.is_written() {
+            let param = ValidationErrorKind::IndexIsWritten;
+            return Err(Error::validation(param, "Index Header version is not valid").into());
+        }
+        if self.header.version() != HEADER_VERSION {
+            let param = ValidationErrorKind::IndexVersion;
+            return Err(Error::validation(param, "Index Header version is not valid").into());
+        }
+        Ok(())
+    }
+}
+
+impl<K: Key + 'static> HashFileIndex<K> {
+    // Reads slots starting from key bucket until the first empty one and collects headers
+    // with the given key. Usually the whole probe sequence fits into a single read.
+    async fn probe(&self, key: &K, first_only: bool) -> Result<Vec<RecordHeader>> {
+        let mut headers = Vec::new();
+        if self.metadata.buckets_count == 0 {
+            return Ok(headers);
+        }
+        let slot_size = self.header.record_header_size;
+        let slots_per_read = (PROBE_BUF_SIZE / slot_size).max(1);
+        let mut buf = vec![0; slots_per_read * slot_size];
+        let mut slot = Self::bucket(key.as_ref(), self.metadata.buckets_count);
+        while slot < self.metadata.slots_count {
+            let slots_amount = slots_per_read.min((self.metadata.slots_count - slot) as usize);
+            let slots_buf = &mut buf[..slots_amount * slot_size];
+            self.file
+                .read_at(slots_buf, self.slot_offset(slot)?)
+                .await?;
+            for raw in slots_buf.chunks(slot_size) {
+                if Self::is_empty_slot(raw) {
+                    return Ok(headers);
+                }
+                let header: RecordHeader = deserialize(raw)?;
+                if header.key() == key.as_ref() {
+                    headers.push(header);
+                    if first_only {
+                        return Ok(headers);
+                    }
+                }
+            }
+            slot += slots_amount as u64;
+        }
+        Ok(headers)
+    }
+
+    fn bucket(key: &[u8], buckets_count: u64) -> u64 {
+        let hash = key.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+        });
+        hash % buckets_count
+    }
+
+    // serialized record header always starts with non-zero magic byte
+    fn is_empty_slot(raw: &[u8]) -> bool {
+        raw[..std::mem::size_of::<u64>()].iter().all(|b| *b == 0)
+    }
+
+    fn table_offset(&self) -> Result<u64> {
+        let hs = self.header.serialized_size()?;
+        let ms = TableMeta::serialized_size_default()?;
+        Ok(hs + self.header.meta_size as u64 + ms)
+    }
+
+    fn slot_offset(&self, slot: u64) -> Result<u64> {
+        Ok(self.table_offset()? + slot * self.header.record_header_size as u64)
+    }
+
+    async fn validate_header(&self, buf: &mut Vec<u8>) -> Result<()> {
+        self.validate()?;
+        if !Self::hash_valid(&self.header, buf)? {
+            let param = ValidationErrorKind::IndexChecksum;
+            return Err(Error::validation(param, "header hash mismatch").into());
+        }
+        Ok(())
+    }
+
+    fn hash_valid(header: &IndexHeader, buf: &mut Vec<u8>) -> Result<bool> {
+        let hash = header.hash.clone();
+        let mut header = header.clone();
+        header.hash = vec![0; ring::digest::SHA256.output_len];
+        header.set_written(false);
+        serialize_into(buf.as_mut_slice(), &header)?;
+        let new_hash = get_hash(buf);
+        Ok(hash == new_hash)
+    }
+
+    async fn read_table_meta(file: &File, header: &IndexHeader) -> Result<TableMeta> {
+        let meta_size = TableMeta::serialized_size_default()? as usize;
+        let mut buf = vec![0; meta_size];
+        let meta_offset = header.serialized_size()? + header.meta_size as u64;
+        file.read_at(&mut buf, meta_offset).await?;
+        TableMeta::from_raw(&buf).map_err(Into::into)
+    }
+
+    fn serialize(
+        headers: &InMemoryIndex<K>,
+        meta: Vec<u8>,
+    ) -> Result<(IndexHeader, TableMeta, Vec<u8>)> {
+        let record_header = headers
+            .values()
+            .next()
+            .and_then(|v| v.first())
+            .ok_or_else(|| anyhow!("BTree is empty, can't find info about key len!"))?;
+        let record_header_size: usize = record_header.serialized_size().try_into()?;
+        let records_count = headers.values().fold(0, |acc, v| acc + v.len());
+        let buckets_count = records_count * BUCKETS_PER_RECORD;
+        let slots = Self::place_headers(headers, buckets_count);
+        let metadata = TableMeta::new(buckets_count as u64, slots.len() as u64);
+        let header = IndexHeader::new(
+            IndexKind::Hash,
+            record_header_size,
+            records_count,
+            meta.len(),
+        );
+        let hs = header.serialized_size()? as usize;
+        let ms = metadata.serialized_size()? as usize;
+        let mut buf = Vec::with_capacity(hs + meta.len() + ms + slots.len() * record_header_size);
+        serialize_into(&mut buf, &header)?;
+        buf.extend_from_slice(&meta);
+        serialize_into(&mut buf, &metadata)?;
+        for slot in slots {
+            if let Some(record_header) = slot {
+                serialize_into(&mut buf, record_header)?;
+            } else {
+                buf.resize(buf.len() + record_header_size, 0);
+            }
+        }
+        let hash = get_hash(&buf);
+        let header = IndexHeader::with_hash(
+            IndexKind::Hash,
+            record_header_size,
+            records_count,
+            meta.len(),
+            hash,
+        );
+        serialize_into(buf.as_mut_slice(), &header)?;
+        Ok((header, metadata, buf))
+    }
+
+    fn place_headers(
+        headers: &InMemoryIndex<K>,
+        buckets_count: usize,
+    ) -> Vec<Option<&RecordHeader>> {
+        let mut slots = vec![None; buckets_count];
+        for (key, key_headers) in headers {
+            let mut slot = Self::bucket(key.as_ref(), buckets_count as u64) as usize;
+            // headers are placed in reversed order (as in b+-tree index), so the latest
+            // written record is met first during probing
+            for record_header in key_headers.iter().rev() {
+                while slot < slots.len() && slots[slot].is_some() {
+                    slot += 1;
+                }
+                if slot == slots.len() {
+                    slots.push(Some(record_header));
+                } else {
+                    slots[slot] = Some(record_header);
+                }
+            }
+        }
+        slots
+    }
+}
@@ -0,0 +1,33 @@
+use super::*;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(super) struct TableMeta {
+    // amount of slots, addressed by key hash
+    pub(super) buckets_count: u64,
+    // amount of all slots in table, including overflow ones at the end
+    pub(super) slots_count: u64,
+}
+
+impl TableMeta {
+    pub(super) fn new(buckets_count: u64, slots_count: u64) -> Self {
+        Self {
+            buckets_count,
+            slots_count,
+        }
+    }
+
+    pub(super) fn serialized_size_default() -> bincode::Result<u64> {
+        let meta = Self::default();
+        meta.serialized_size()
+    }
+
+    #[inline]
+    pub(super) fn serialized_size(&self) -> bincode::Result<u64> {
+        bincode::serialized_size(&self)
+    }
+
+    #[inline]
+    pub(super) fn from_raw(buf: &[u8]) -> bincode::Result<Self> {
+        bincode::deserialize(buf)
+    }
+}
@@ -0,0 +1,13 @@
+use super::prelude::*;
+
+mod core;
+mod meta;
+#[cfg(test)]
+mod tests;
+
+pub(crate) use self::core::HashFileIndex;
+
+mod prelude {
+    pub(super) use super::*;
+    pub(super) use meta::TableMeta;
+}
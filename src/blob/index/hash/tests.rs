@@ -0,0 +1,129 @@
+use super::prelude::*;
+
+const META_SIZE: usize = 100;
+const META_VALUE: u8 = 17;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct KeyType(Vec<u8>);
+
+impl Key for KeyType {
+    const LEN: u16 = 8;
+}
+
+impl From<Vec<u8>> for KeyType {
+    fn from(mut v: Vec<u8>) -> Self {
+        v.resize(KeyType::LEN as usize, 0);
+        Self(v)
+    }
+}
+
+impl AsRef<[u8]> for KeyType {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+impl Default for KeyType {
+    fn default() -> Self {
+        Self(vec![0_u8; Self::LEN as usize])
+    }
+}
+
+impl From<usize> for KeyType {
+    fn from(i: usize) -> Self {
+        let mut v = serialize(&i).unwrap();
+        v.resize(KeyType::LEN as usize, 0);
+        Self(v)
+    }
+}
+
+async fn create_index(path: &str, inmem: &InMemoryIndex<KeyType>) -> HashFileIndex<KeyType> {
+    let meta = vec![META_VALUE; META_SIZE];
+    HashFileIndex::<KeyType>::from_records(
+        Path::new(path),
+        None,
+        inmem,
+        meta,
+        &IndexConfig::default(),
+    )
+    .await
+    .expect("Can't create file index")
+}
+
+#[tokio::test]
+async fn serialize_deserialize_file() {
+    let mut inmem = InMemoryIndex::<KeyType>::new();
+    (0..10000).map(|i| i.into()).for_each(|key: KeyType| {
+        let rh = RecordHeader::new(key.to_vec(), 1, 1, 1);
+        inmem.insert(key, vec![rh]);
+    });
+    let findex = create_index("/tmp/hash_index.b", &inmem).await;
+    let (inmem_after, _size) = findex
+        .get_records_headers()
+        .await
+        .expect("Can't get InMemoryIndex");
+    assert_eq!(inmem, inmem_after);
+    assert_eq!(
+        vec![META_VALUE; META_SIZE],
+        findex.read_meta().await.unwrap()
+    );
+}
+
+#[tokio::test]
+async fn check_get_any() {
+    const RANGE_FROM: usize = 100;
+    const RANGE_TO: usize = 9000;
+
+    let mut inmem = InMemoryIndex::<KeyType>::new();
+    (RANGE_FROM..RANGE_TO)
+        .map(|i| i.into())
+        .for_each(|key: KeyType| {
+            let rh = RecordHeader::new(key.to_vec(), 1, 1, 1);
+            inmem.insert(key, vec![rh]);
+        });
+    let findex = create_index("/tmp/any_hash_index.b", &inmem).await;
+    for key in (RANGE_FROM..RANGE_TO).map(KeyType::from) {
+        let actual_header = findex
+            .get_any(&key)
+            .await
+            .expect("Error in get_any for file index")
+            .expect("Key is not found");
+        assert_eq!(inmem[&key][0], actual_header);
+    }
+    let not_presented_ranges = [0..RANGE_FROM, RANGE_TO..(RANGE_TO + 100)];
+    for not_presented_keys in not_presented_ranges.iter() {
+        for key in not_presented_keys.clone().map(KeyType::from) {
+            assert_eq!(None, findex.get_any(&key).await.unwrap());
+        }
+    }
+}
+
+#[tokio::test]
+async fn check_get() {
+    const MAX_AMOUNT: usize = 3;
+    const RANGE_FROM: usize = 100;
+    const RANGE_TO: usize = 9000;
+
+    let mut inmem = InMemoryIndex::<KeyType>::new();
+    (RANGE_FROM..RANGE_TO)
+        .map(|i| (i % MAX_AMOUNT + 1, i.into()))
+        .for_each(|(times, key): (_, KeyType)| {
+            let recs = (0..times)
+                .map(|data_size| RecordHeader::new(key.to_vec(), 1, data_size as u64, 1))
+                .collect();
+            inmem.insert(key, recs);
+        });
+    let findex = create_index("/tmp/all_hash_index.b", &inmem).await;
+    for key in (RANGE_FROM..RANGE_TO).map(KeyType::from) {
+        let mut expected = inmem[&key].clone();
+        expected.reverse();
+        let actual = findex.find_by_key(&key).await.unwrap();
+        assert_eq!(Some(expected), actual);
+    }
+    let not_presented_ranges = [0..RANGE_FROM, RANGE_TO..(RANGE_TO + 100)];
+    for not_presented_keys in not_presented_ranges.iter() {
+        for key in not_presented_keys.clone().map(KeyType::from) {
+            assert_eq!(None, findex.find_by_key(&key).await.unwrap());
+        }
+    }
+}
@@ -11,19 +11,28 @@ pub(crate) struct IndexHeader {
     // this field also contains `written` bit (the first one)
     // to get the version, you should proceed `version >> 1`
     pub(crate) version: u8,
+    // layout of the rest of the file, so index can be opened regardless of current config
+    pub(crate) kind: IndexKind,
 }
 
 impl IndexHeader {
-    pub fn new(record_header_size: usize, records_count: usize, meta_size: usize) -> Self {
+    pub fn new(
+        kind: IndexKind,
+        record_header_size: usize,
+        records_count: usize,
+        meta_size: usize,
+    ) -> Self {
         Self {
             records_count,
             record_header_size,
             meta_size,
+            kind,
             ..Self::default()
         }
     }
 
     pub fn with_hash(
+        kind: IndexKind,
         record_header_size: usize,
         records_count: usize,
         meta_size: usize,
@@ -34,6 +43,7 @@ impl IndexHeader {
             record_header_size,
             meta_size,
             hash,
+            kind,
             ..Self::default()
         }
     }
@@ -71,6 +81,13 @@ impl IndexHeader {
     pub(crate) fn from_raw(buf: &[u8]) -> bincode::Result<Self> {
         bincode::deserialize(buf)
     }
+
+    pub(crate) async fn from_file(file: &File) -> Result<Self> {
+        let header_size = Self::serialized_size_default()? as usize;
+        let mut buf = vec![0; header_size];
+        file.read_at(&mut buf, 0).await?;
+        Self::from_raw(&buf).map_err(Into::into)
+    }
 }
 
 impl Default for IndexHeader {
@@ -81,6 +98,7 @@ impl Default for IndexHeader {
             meta_size: 0,
             hash: vec![0; ring::digest::SHA256.output_len],
             version: HEADER_VERSION << 1,
+            kind: IndexKind::default(),
         }
     }
 }
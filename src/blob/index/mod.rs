@@ -2,6 +2,9 @@ use crate::prelude::*;
 
 mod bptree;
 mod core;
+mod file_index;
+mod filter_pages;
+mod hash;
 mod header;
 mod simple;
 mod tools;
@@ -10,22 +13,32 @@ mod tools;
 mod benchmarks;
 
 use bptree::BPTreeFileIndex;
+use file_index::FileIndex;
+use filter_pages::{FilterPageCache, FILTER_PAGE_SIZE};
+use hash::HashFileIndex;
 use header::IndexHeader;
+use simple::SimpleFileIndex;
 
+pub(crate) use self::bptree::NodeCache;
+pub use self::bptree::NodeCacheStats;
 pub(crate) use self::core::{
-    FileIndexTrait, InMemoryIndex, Index, IndexConfig, MemoryAttrs, HEADER_VERSION,
+    DetachedIndex, FileIndexTrait, InMemoryIndex, Index, IndexConfig, MemoryAttrs, HEADER_VERSION,
 };
+pub use self::file_index::IndexKind;
 pub(crate) use super::prelude::*;
 pub(crate) use crate::filter::range::RangeFilter;
 
 mod prelude {
     pub(crate) use super::*;
+    pub(crate) use memmap2::Advice;
     pub(crate) use tools::*;
 }
 
 #[async_trait::async_trait]
 pub(crate) trait IndexTrait<K>: Send + Sync {
+    /// Returns all headers with the key, the latest written goes first.
     async fn get_all(&self, key: &K) -> Result<Option<Vec<RecordHeader>>>;
+    /// Returns the latest written header with the key.
     async fn get_any(&self, key: &K) -> Result<Option<RecordHeader>>;
     fn push(&mut self, h: RecordHeader) -> Result<()>;
     async fn contains_key(&self, key: &K) -> Result<bool>;
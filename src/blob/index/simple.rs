@@ -10,12 +10,15 @@ pub(crate) struct SimpleFileIndex {
 
 #[async_trait::async_trait]
 impl<K: Key> FileIndexTrait<K> for SimpleFileIndex {
-    async fn from_file(name: FileName, ioring: Option<Rio>) -> Result<Self> {
+    async fn from_file(name: FileName, ioring: Option<Rio>, config: &IndexConfig) -> Result<Self> {
         trace!("open index file");
-        let file = File::open(name.to_path(), ioring)
+        let mut file = File::open(name.to_path(), ioring)
             .await
             .context(format!("failed to open index file: {}", name))?;
-        let header = Self::read_index_header(&file).await?;
+        if config.mmap {
+            file.enable_mmap(Advice::WillNeed);
+        }
+        let header = IndexHeader::from_file(&file).await?;
 
         Ok(Self { file, header })
     }
@@ -28,6 +31,10 @@ impl<K: Key> FileIndexTrait<K> for SimpleFileIndex {
         self.header.records_count
     }
 
+    fn records_hash(&self) -> &[u8] {
+        &self.header.hash
+    }
+
     async fn read_meta(&self) -> Result<Vec<u8>> {
         trace!("load meta");
         let mut buf = vec![0; self.header.meta_size];
@@ -50,6 +57,10 @@ impl<K: Key> FileIndexTrait<K> for SimpleFileIndex {
         Ok(buf[0])
     }
 
+    async fn read_meta_ranges(&self, ranges: &[(u64, usize)]) -> Result<Vec<Vec<u8>>> {
+        read_meta_ranges(&self.file, &self.header, ranges).await
+    }
+
     async fn find_by_key(&self, key: &K) -> Result<Option<Vec<RecordHeader>>> {
         Self::search_all(&self.file, key, &self.header).await
     }
@@ -59,7 +70,7 @@ impl<K: Key> FileIndexTrait<K> for SimpleFileIndex {
         ioring: Option<Rio>,
         headers: &InMemoryIndex<K>,
         meta: Vec<u8>,
-        recreate_index_file: bool,
+        config: &IndexConfig,
     ) -> Result<Self> {
         let res = Self::serialize(headers, meta)?;
         if res.is_none() {
@@ -67,8 +78,8 @@ impl<K: Key> FileIndexTrait<K> for SimpleFileIndex {
             return Err(anyhow!("empty in-memory indices".to_string()));
         }
         let (mut header, buf) = res.expect("None case is checked");
-        clean_file(path, recreate_index_file)?;
-        let file = File::create(path, ioring)
+        clean_file(path, config.recreate_index_file)?;
+        let mut file = File::create(path, ioring)
             .await
             .with_context(|| format!("file open failed {:?}", path))?;
         file.write_append(&buf).await?;
@@ -76,6 +87,9 @@ impl<K: Key> FileIndexTrait<K> for SimpleFileIndex {
         let serialized_header = serialize(&header)?;
         file.write_at(0, &serialized_header).await?;
         file.fsyncdata().await?;
+        if config.mmap {
+            file.enable_mmap(Advice::WillNeed);
+        }
         Ok(Self { file, header })
     }
 
@@ -101,10 +115,46 @@ impl<K: Key> FileIndexTrait<K> for SimpleFileIndex {
             .map(|headers| (headers, self.header.records_count))
     }
 
+    async fn for_each_header(
+        &self,
+        f: &mut (dyn for<'h> FnMut(&'h RecordHeader) + Send),
+    ) -> Result<()> {
+        let start = (self.header.meta_size + self.header.serialized_size()? as usize) as u64;
+        let size = self.header.record_header_size * self.header.records_count;
+        let range = (start, start + size as u64);
+        for_each_header_in(
+            &self.file,
+            range,
+            self.header.record_header_size,
+            |_| true,
+            f,
+        )
+        .await
+    }
+
+    // all headers are read, keys are sorted only in the in-memory index
+    async fn keys_with_prefix(&self, prefix: &[u8]) -> Result<Vec<K>> {
+        let (headers, _) = self.get_records_headers().await?;
+        Ok(keys_with_prefix(&headers, prefix))
+    }
+
     async fn get_any(&self, key: &K) -> Result<Option<RecordHeader>> {
-        Self::binary_search(&self.file, key, &self.header)
-            .await
-            .map(|res| res.map(|h| h.0))
+        if let Some((mut latest, mut pos)) =
+            Self::binary_search(&self.file, key, &self.header).await?
+        {
+            // headers are stored in order of writing, so the latest one is the rightmost
+            while pos + 1 < self.header.records_count {
+                let rh = Self::read_at(&self.file, pos + 1, &self.header).await?;
+                if rh.key() != key.as_ref() {
+                    break;
+                }
+                latest = rh;
+                pos += 1;
+            }
+            Ok(Some(latest))
+        } else {
+            Ok(None)
+        }
     }
 
     fn validate(&self) -> Result<()> {
@@ -133,13 +183,6 @@ impl SimpleFileIndex {
         Ok(hash == new_hash)
     }
 
-    async fn read_index_header(file: &File) -> Result<IndexHeader> {
-        let header_size = IndexHeader::serialized_size_default()? as usize;
-        let mut buf = vec![0; header_size];
-        file.read_at(&mut buf, 0).await?;
-        IndexHeader::from_raw(&buf).map_err(Into::into)
-    }
-
     async fn search_all<K: Key>(
         file: &File,
         key: &K,
@@ -154,38 +197,32 @@ impl SimpleFileIndex {
                 "blob index simple search all total {}, pos {}",
                 index_header.records_count, orig_pos
             );
-            let mut headers = vec![header_pos.0];
-            // go left
-            let mut pos = orig_pos;
-            debug!(
-                "blob index simple search all headers {}, pos {}",
-                headers.len(),
-                pos
-            );
-            while pos > 0 {
-                pos -= 1;
-                debug!(
-                    "blob index simple search all headers {}, pos {}",
-                    headers.len(),
-                    pos
-                );
+            // headers are stored in order of writing, but returned in reversed order (as in
+            // other file indexes), so right part is collected first
+            let mut right = Vec::new();
+            let mut pos = orig_pos + 1;
+            while pos < index_header.records_count {
                 let rh = Self::read_at(file, pos, &index_header)
                     .await
                     .with_context(|| "blob, index simple, search all, read at failed")?;
                 if rh.key() == key.as_ref() {
-                    headers.push(rh);
+                    right.push(rh);
+                    pos += 1;
                 } else {
                     break;
                 }
             }
+            let mut headers: Vec<_> = right.into_iter().rev().collect();
+            headers.push(header_pos.0);
+            // go left
+            let mut pos = orig_pos;
             debug!(
                 "blob index simple search all headers {}, pos {}",
                 headers.len(),
                 pos
             );
-            //go right
-            pos = orig_pos + 1;
-            while pos < index_header.records_count {
+            while pos > 0 {
+                pos -= 1;
                 debug!(
                     "blob index simple search all headers {}, pos {}",
                     headers.len(),
@@ -196,7 +233,6 @@ impl SimpleFileIndex {
                     .with_context(|| "blob, index simple, search all, read at failed")?;
                 if rh.key() == key.as_ref() {
                     headers.push(rh);
-                    pos += 1;
                 } else {
                     break;
                 }
@@ -235,11 +271,11 @@ impl SimpleFileIndex {
             debug!("mid read: {:?}, key: {:?}", mid_record_header.key(), key);
             debug!("before mid: {:?}, start: {:?}, end: {:?}", mid, start, end);
             match cmp {
-                CmpOrdering::Greater if mid > 0 => end = mid - 1,
+                CmpOrdering::Less if mid > 0 => end = mid - 1,
                 CmpOrdering::Equal => {
                     return Ok(Some((mid_record_header, mid)));
                 }
-                CmpOrdering::Less => start = mid + 1,
+                CmpOrdering::Greater => start = mid + 1,
                 other => {
                     debug!("binary search not found, cmp: {:?}, mid: {}", other, mid);
                     return Ok(None);
@@ -270,7 +306,12 @@ impl SimpleFileIndex {
             let record_header_size = record_header.serialized_size().try_into()?;
             trace!("record header serialized size: {}", record_header_size);
             let headers = headers.iter().flat_map(|r| r.1).collect::<Vec<_>>(); // produce sorted
-            let header = IndexHeader::new(record_header_size, headers.len(), meta.len());
+            let header = IndexHeader::new(
+                IndexKind::Simple,
+                record_header_size,
+                headers.len(),
+                meta.len(),
+            );
             let hs: usize = header.serialized_size()?.try_into().expect("u64 to usize");
             trace!("index header size: {}b", hs);
             let fsize = header.meta_size;
@@ -295,8 +336,13 @@ impl SimpleFileIndex {
                 buf.len()
             );
             let hash = get_hash(&buf);
-            let header =
-                IndexHeader::with_hash(record_header_size, headers.len(), meta.len(), hash);
+            let header = IndexHeader::with_hash(
+                IndexKind::Simple,
+                record_header_size,
+                headers.len(),
+                meta.len(),
+                hash,
+            );
             serialize_into(buf.as_mut_slice(), &header)?;
             Ok(Some((header, buf)))
         } else {
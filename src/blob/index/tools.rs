@@ -1,6 +1,9 @@
 use super::prelude::*;
 use std::mem::size_of;
 
+// record headers are read from index file by chunks of this size
+const HEADERS_READ_SIZE: usize = 1 << 16;
+
 pub(crate) fn get_hash(buf: &[u8]) -> Vec<u8> {
     use ring::digest::{Context, SHA256};
     let mut context = Context::new(&SHA256);
@@ -22,6 +25,29 @@ pub(crate) fn compute_mem_attrs<K: Key>(
     attrs
 }
 
+/// Smallest key, which can start with `prefix`.
+pub(crate) fn prefix_lower_bound<K: Key>(prefix: &[u8]) -> K {
+    let mut key = prefix.to_vec();
+    key.resize(K::LEN as usize, 0);
+    key.into()
+}
+
+/// Sorted keys of `headers`, which start with `prefix`. Keys with the prefix are adjacent
+/// only if keys are ordered by bytes, otherwise all keys are checked.
+pub(crate) fn keys_with_prefix<K: Key>(headers: &InMemoryIndex<K>, prefix: &[u8]) -> Vec<K> {
+    let has_prefix = |key: &&K| key.as_ref().starts_with(prefix);
+    if K::BYTE_ORDERED {
+        headers
+            .range(prefix_lower_bound::<K>(prefix)..)
+            .map(|(key, _)| key)
+            .take_while(has_prefix)
+            .cloned()
+            .collect()
+    } else {
+        headers.keys().filter(has_prefix).cloned().collect()
+    }
+}
+
 pub(crate) fn set_key_related_fields<K: Key>(attrs: &mut MemoryAttrs) {
     let key_size = K::LEN as usize;
     attrs.key_size = key_size;
@@ -40,3 +66,108 @@ pub(crate) fn clean_file(path: impl AsRef<Path>, recreate_index_file: bool) -> R
         Err(anyhow!(msg))
     }
 }
+
+/// Passes headers stored one after another in `file` from `start` to `end` to `f`, slots
+/// rejected by `is_header` are skipped. Headers are read by chunks, so the whole index isn't
+/// loaded into memory.
+pub(crate) async fn for_each_header_in(
+    file: &File,
+    (start, end): (u64, u64),
+    header_size: usize,
+    is_header: fn(&[u8]) -> bool,
+    f: &mut (dyn FnMut(&RecordHeader) + Send + '_),
+) -> Result<()> {
+    let headers_per_read = (HEADERS_READ_SIZE / header_size).max(1) as u64;
+    let mut offset = start;
+    while offset < end {
+        let size = (end - offset).min(headers_per_read * header_size as u64);
+        let mut buf = vec![0; size as usize];
+        if file.read_at(&mut buf, offset).await? != buf.len() {
+            return Err(anyhow!("Can't read record headers"));
+        }
+        for raw in buf.chunks(header_size).filter(|raw| is_header(raw)) {
+            f(&deserialize(raw)?);
+        }
+        offset += size;
+    }
+    Ok(())
+}
+
+/// Reads `(offset, len)` ranges of index meta in one batch, ranges are cut by the end of meta.
+pub(crate) async fn read_meta_ranges(
+    file: &File,
+    header: &IndexHeader,
+    ranges: &[(u64, usize)],
+) -> Result<Vec<Vec<u8>>> {
+    trace!("load ranges of meta");
+    let meta_start = header.serialized_size()?;
+    let meta_size = header.meta_size as u64;
+    let reads = ranges
+        .iter()
+        .map(|&(i, len)| {
+            if i >= meta_size {
+                Err(anyhow::anyhow!("read meta out of range"))
+            } else {
+                Ok((meta_start + i, len.min((meta_size - i) as usize)))
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    file.read_at_batch(&reads).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // little-endian number, which is ordered by value, not by bytes
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    struct NumberKey(Vec<u8>);
+
+    impl NumberKey {
+        fn value(&self) -> u16 {
+            u16::from_le_bytes([self.0[0], self.0[1]])
+        }
+    }
+
+    impl PartialOrd for NumberKey {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for NumberKey {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.value().cmp(&other.value())
+        }
+    }
+
+    impl AsRef<[u8]> for NumberKey {
+        fn as_ref(&self) -> &[u8] {
+            &self.0
+        }
+    }
+
+    impl From<Vec<u8>> for NumberKey {
+        fn from(mut v: Vec<u8>) -> Self {
+            v.resize(NumberKey::LEN as usize, 0);
+            Self(v)
+        }
+    }
+
+    impl Key for NumberKey {
+        const LEN: u16 = 2;
+    }
+
+    #[test]
+    fn keys_with_prefix_not_byte_ordered() {
+        let headers: InMemoryIndex<NumberKey> = [1_u16, 256, 257, 513]
+            .iter()
+            .map(|i| (i.to_le_bytes().to_vec().into(), vec![]))
+            .collect();
+        let keys: Vec<_> = keys_with_prefix(&headers, &[1])
+            .iter()
+            .map(NumberKey::value)
+            .collect();
+        assert_eq!(keys, [1, 257, 513]);
+    }
+}
@@ -0,0 +1,70 @@
+use super::prelude::*;
+
+pub(crate) const BLOB_META_INDEX_FILE_EXTENSION: &str = "meta_index";
+
+/// Secondary index of blob records by values of chosen meta attributes.
+/// Index is kept in memory while the blob is active and saved next to the blob index,
+/// when the blob is dumped. Saved index is valid only with the index file it was saved with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct MetaIndex {
+    // meta name -> meta value -> headers of records with this value, in order of writing
+    headers: BTreeMap<String, BTreeMap<Vec<u8>, Vec<RecordHeader>>>,
+}
+
+impl MetaIndex {
+    /// Returns `None` if there are no meta attributes to index.
+    pub(crate) fn new(names: &[String]) -> Option<Self> {
+        if names.is_empty() {
+            return None;
+        }
+        let headers = names
+            .iter()
+            .map(|name| (name.clone(), BTreeMap::new()))
+            .collect();
+        Some(Self { headers })
+    }
+
+    fn is_built_for(&self, names: &[String]) -> bool {
+        self.headers.len() == names.len() && names.iter().all(|n| self.headers.contains_key(n))
+    }
+
+    pub(crate) fn push(&mut self, header: &RecordHeader, meta: &Meta) {
+        for (name, values) in &mut self.headers {
+            if let Some(value) = meta.get(name) {
+                values
+                    .entry(value.clone())
+                    .or_default()
+                    .push(header.clone());
+            }
+        }
+    }
+
+    pub(crate) fn get(&self, name: &str, value: &[u8]) -> Option<&[RecordHeader]> {
+        self.headers.get(name)?.get(value).map(Vec::as_slice)
+    }
+
+    /// Saves index together with `index_hash`, hash of records of the blob index.
+    pub(crate) async fn dump(
+        &self,
+        name: &FileName,
+        ioring: Option<Rio>,
+        index_hash: &[u8],
+    ) -> Result<()> {
+        let buf = serialize(&(index_hash, self))?;
+        File::write_atomically(&name.to_path(), &buf, ioring).await?;
+        Ok(())
+    }
+
+    /// Returns `None` if index in file was built for other meta attributes or with other
+    /// blob index records.
+    pub(crate) async fn from_file(
+        name: &FileName,
+        ioring: Option<Rio>,
+        names: &[String],
+        index_hash: &[u8],
+    ) -> Result<Option<Self>> {
+        let file = File::open(name.to_path(), ioring).await?;
+        let (saved_hash, index): (Vec<u8>, Self) = deserialize(&file.read_all().await?)?;
+        Ok(Some(index).filter(|index| saved_hash == index_hash && index.is_built_for(names)))
+    }
+}
@@ -3,16 +3,20 @@ mod entry;
 mod file;
 mod header;
 mod index;
+mod meta_index;
 
 pub(crate) use self::core::BLOB_INDEX_FILE_EXTENSION;
-pub(crate) use self::core::{Blob, FileName};
+pub(crate) use self::core::{Blob, FileName, RecordsTail};
 pub use self::entry::Entry;
 pub(crate) use self::file::File;
-pub(crate) use self::index::IndexConfig;
+pub(crate) use self::index::{DetachedIndex, IndexConfig, NodeCache};
+pub use self::index::{IndexKind, NodeCacheStats};
+pub(crate) use self::meta_index::BLOB_META_INDEX_FILE_EXTENSION;
 pub(crate) use super::prelude::*;
 
 mod prelude {
     pub(crate) use super::*;
     pub(crate) use index::Index;
+    pub(crate) use meta_index::MetaIndex;
     pub(crate) use std::sync::atomic::AtomicU64;
 }
@@ -0,0 +1,169 @@
+use crate::prelude::*;
+use tokio::runtime::{Builder as RuntimeBuilder, Handle, Runtime};
+
+const WORKER_THREADS: usize = 2;
+
+/// Synchronous wrapper over [`Storage`], which runs its operations on a tokio runtime.
+///
+/// Runtime is either owned by the wrapper or provided by the caller. Background work of the
+/// storage, like rotation of blobs and dump of indexes, runs on the runtime workers between
+/// calls. Methods must not be called from an async context, because they block the thread.
+///
+/// This type is clonable, cloning it will only create a new reference to the same storage
+/// and runtime.
+///
+/// [`Storage`]: ../struct.Storage.html
+#[derive(Debug, Clone)]
+pub struct Storage<K: Key> {
+    storage: crate::Storage<K>,
+    // owned runtime is kept alive while any of the clones exists
+    _runtime: Option<Arc<Runtime>>,
+    handle: Handle,
+}
+
+impl<K: Key + 'static> Storage<K> {
+    /// Builds storage, which owns a small multi-threaded runtime.
+    /// # Errors
+    /// Fails if required params of `builder` are missed or runtime can't be started
+    pub fn new(builder: Builder) -> Result<Self> {
+        let runtime = RuntimeBuilder::new_multi_thread()
+            .worker_threads(WORKER_THREADS)
+            .thread_name("pearl-blocking")
+            .enable_all()
+            .build()
+            .context("failed to start runtime")?;
+        let handle = runtime.handle().clone();
+        Ok(Self {
+            storage: builder.build()?,
+            _runtime: Some(Arc::new(runtime)),
+            handle,
+        })
+    }
+
+    /// Builds storage, which runs on the caller's runtime.
+    /// Runtime must have time driver enabled and must run its tasks between calls,
+    /// so multi-threaded runtime is required.
+    /// # Errors
+    /// Fails if required params of `builder` are missed
+    pub fn with_handle(builder: Builder, handle: Handle) -> Result<Self> {
+        Ok(Self {
+            storage: builder.build()?,
+            _runtime: None,
+            handle,
+        })
+    }
+
+    /// Returns the async storage, which can be used on the same runtime.
+    #[must_use]
+    pub fn as_async(&self) -> &crate::Storage<K> {
+        &self.storage
+    }
+
+    /// See [`Storage::init`](../struct.Storage.html#method.init).
+    /// # Errors
+    /// Same as async version
+    pub fn init(&mut self) -> Result<()> {
+        let storage = &mut self.storage;
+        self.handle.block_on(storage.init())
+    }
+
+    /// See [`Storage::write`](../struct.Storage.html#method.write).
+    /// # Errors
+    /// Same as async version
+    pub fn write(&self, key: impl AsRef<K>, value: impl Into<Bytes>) -> Result<()> {
+        self.handle.block_on(self.storage.write(key, value))
+    }
+
+    /// See [`Storage::write_with`](../struct.Storage.html#method.write_with).
+    /// # Errors
+    /// Same as async version
+    pub fn write_with(
+        &self,
+        key: impl AsRef<K>,
+        value: impl Into<Bytes>,
+        meta: Meta,
+    ) -> Result<()> {
+        self.handle
+            .block_on(self.storage.write_with(key, value, meta))
+    }
+
+    /// See [`Storage::read`](../struct.Storage.html#method.read).
+    /// # Errors
+    /// Same as async version
+    pub fn read(&self, key: impl AsRef<K>) -> Result<Bytes> {
+        self.handle.block_on(self.storage.read(key))
+    }
+
+    /// See [`Storage::read_with`](../struct.Storage.html#method.read_with).
+    /// # Errors
+    /// Same as async version
+    pub fn read_with(&self, key: impl AsRef<K>, meta: &Meta) -> Result<Bytes> {
+        self.handle.block_on(self.storage.read_with(key, meta))
+    }
+
+    /// Returns data of all records with `key`, the latest goes first. Unlike
+    /// [`Storage::read_all`](../struct.Storage.html#method.read_all), data is loaded.
+    /// # Errors
+    /// Same as async version, also fails if data can't be loaded
+    pub fn read_all(&self, key: impl AsRef<K>) -> Result<Vec<Bytes>> {
+        self.handle.block_on(async {
+            let entries = self.storage.read_all(key).await?;
+            let mut data = Vec::with_capacity(entries.len());
+            for entry in entries {
+                data.push(entry.load_data().await?);
+            }
+            Ok(data)
+        })
+    }
+
+    /// See [`Storage::contains`](../struct.Storage.html#method.contains).
+    /// # Errors
+    /// Same as async version
+    pub fn contains(&self, key: impl AsRef<K>) -> Result<bool> {
+        self.handle.block_on(self.storage.contains(key))
+    }
+
+    /// See [`Storage::close`](../struct.Storage.html#method.close).
+    /// # Errors
+    /// Same as async version
+    pub fn close(self) -> Result<()> {
+        self.handle.block_on(self.storage.close())
+    }
+
+    /// See [`Storage::records_count`](../struct.Storage.html#method.records_count).
+    #[must_use]
+    pub fn records_count(&self) -> usize {
+        self.handle.block_on(self.storage.records_count())
+    }
+
+    /// See [`Storage::records_count_detailed`](../struct.Storage.html#method.records_count_detailed).
+    #[must_use]
+    pub fn records_count_detailed(&self) -> Vec<(usize, usize)> {
+        self.handle.block_on(self.storage.records_count_detailed())
+    }
+
+    /// See [`Storage::records_count_in_active_blob`](../struct.Storage.html#method.records_count_in_active_blob).
+    #[must_use]
+    pub fn records_count_in_active_blob(&self) -> Option<usize> {
+        self.handle
+            .block_on(self.storage.records_count_in_active_blob())
+    }
+
+    /// See [`Storage::blobs_count`](../struct.Storage.html#method.blobs_count).
+    #[must_use]
+    pub fn blobs_count(&self) -> usize {
+        self.handle.block_on(self.storage.blobs_count())
+    }
+
+    /// See [`Storage::index_memory`](../struct.Storage.html#method.index_memory).
+    #[must_use]
+    pub fn index_memory(&self) -> usize {
+        self.handle.block_on(self.storage.index_memory())
+    }
+
+    /// See [`Storage::next_blob_id`](../struct.Storage.html#method.next_blob_id).
+    #[must_use]
+    pub fn next_blob_id(&self) -> usize {
+        self.storage.next_blob_id()
+    }
+}
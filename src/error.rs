@@ -46,6 +46,22 @@ impl Error {
         Self::new(Kind::RecordNotFound)
     }
 
+    pub(crate) fn record_exists() -> Self {
+        Self::new(Kind::RecordExists)
+    }
+
+    pub(crate) fn namespace(msg: String) -> Self {
+        Self::new(Kind::Namespace(msg))
+    }
+
+    pub(crate) fn subscriber_lagged(skipped: u64) -> Self {
+        Self::new(Kind::SubscriberLagged(skipped))
+    }
+
+    pub(crate) fn replication(msg: String) -> Self {
+        Self::new(Kind::Replication(msg))
+    }
+
     pub(crate) fn io(s: String) -> Self {
         Self::new(Kind::IO(s))
     }
@@ -128,6 +144,13 @@ pub enum Kind {
     WrongFileNamePattern(PathBuf),
     /// Conversion error
     Conversion(String),
+    /// Namespace can't be created or found
+    Namespace(String),
+    /// Subscriber of the change feed was too slow and missed some events,
+    /// they can be replayed with `Storage::tail_from`
+    SubscriberLagged(u64),
+    /// Replicated record can't be applied
+    Replication(String),
     /// Validation errors, eg. magic byte check
     Validation {
         /// Describes what check failed.
@@ -161,6 +184,8 @@ pub enum ValidationErrorKind {
     RecordHeaderChecksum,
     /// Record magic byte.
     RecordMagicByte,
+    /// Record size.
+    RecordSize,
 }
 
 /// Convenient helper for downcasting anyhow error to pearl error.
@@ -0,0 +1,135 @@
+use super::*;
+
+// 512 bits block fits into one cache line
+const BLOCK_WORDS: usize = 8;
+const BLOCK_BITS: u64 = (BLOCK_WORDS * 64) as u64;
+
+/// Cache-blocked bloom filter: all bits of a key are set in one 512 bits block,
+/// so check of a key touches one cache line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockedBloom {
+    blocks_count: u32,
+    hashers_count: u32,
+    buf: Vec<u64>,
+    // saved in index meta before the filter, filters saved without it are hashed with ahash
+    #[serde(skip)]
+    pub(crate) hash_algorithm: HashAlgorithm,
+}
+
+impl BlockedBloom {
+    /// Creates filter for `elements` keys with parameters from config.
+    pub fn new(config: &Config, elements: usize) -> Self {
+        let hashers_count = config.hashers_count.max(1);
+        let bits_count = m_from_fpr(
+            config.preferred_false_positive_rate,
+            hashers_count as f64,
+            elements.max(1) as f64,
+        );
+        let blocks_count = ((bits_count / BLOCK_BITS as f64).ceil() as u32).max(1);
+        Self {
+            blocks_count,
+            hashers_count: hashers_count as u32,
+            buf: vec![0; blocks_count as usize * BLOCK_WORDS],
+            hash_algorithm: config.hash_algorithm,
+        }
+    }
+
+    /// Builds filter for the given keys.
+    pub fn from_keys<T: AsRef<[u8]>>(
+        config: &Config,
+        keys: impl ExactSizeIterator<Item = T>,
+    ) -> Self {
+        let mut filter = Self::new(config, keys.len());
+        keys.for_each(|key| filter.add(key));
+        filter
+    }
+
+    /// Add value to filter
+    pub fn add(&mut self, item: impl AsRef<[u8]>) {
+        let (block, bits) = self.probes(item.as_ref());
+        for bit in bits {
+            self.buf[block + bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    /// Check if key may be in the set.
+    pub fn contains(&self, item: impl AsRef<[u8]>) -> FilterResult {
+        let (block, mut bits) = self.probes(item.as_ref());
+        if bits.all(|bit| self.buf[block + bit / 64] & (1 << (bit % 64)) != 0) {
+            FilterResult::NeedAdditionalCheck
+        } else {
+            FilterResult::NotContains
+        }
+    }
+
+    // Returns offset of the key block in buffer and positions of key bits in the block
+    fn probes(&self, item: &[u8]) -> (usize, impl Iterator<Item = usize>) {
+        let hash = key_hash(self.hash_algorithm, item);
+        let block = (((hash >> 32) * u64::from(self.blocks_count)) >> 32) as usize;
+        let h1 = hash as u32;
+        let h2 = hash.rotate_left(16) as u32 | 1;
+        let bits = (0..self.hashers_count)
+            .map(move |i| (u64::from(h1.wrapping_add(i.wrapping_mul(h2))) % BLOCK_BITS) as usize);
+        (block * BLOCK_WORDS, bits)
+    }
+
+    /// Get amount of memory allocated for filter
+    pub fn memory_allocated(&self) -> usize {
+        self.buf.capacity() * std::mem::size_of::<u64>()
+    }
+}
+
+#[async_trait::async_trait]
+impl<K> FilterTrait<K> for BlockedBloom
+where
+    K: AsRef<[u8]> + Sync + Send,
+{
+    fn add(&mut self, key: &K) {
+        self.add(key);
+    }
+
+    fn contains_fast(&self, key: &K) -> FilterResult {
+        self.contains(key)
+    }
+
+    fn checked_add_assign(&mut self, other: &Self) -> bool {
+        if self.blocks_count != other.blocks_count
+            || self.hashers_count != other.hashers_count
+            || self.hash_algorithm != other.hash_algorithm
+        {
+            return false;
+        }
+        self.buf
+            .iter_mut()
+            .zip(&other.buf)
+            .for_each(|(a, b)| *a |= *b);
+        true
+    }
+
+    fn memory_allocated(&self) -> usize {
+        self.memory_allocated()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocked_bloom_no_false_negatives() {
+        let config = Config {
+            preferred_false_positive_rate: 0.01,
+            ..Default::default()
+        };
+        let keys: Vec<_> = (0_u32..10_000).map(u32::to_le_bytes).collect();
+        let filter = BlockedBloom::from_keys(&config, keys.iter());
+        assert!(keys
+            .iter()
+            .all(|key| filter.contains(key) == FilterResult::NeedAdditionalCheck));
+        let false_positives = (10_000_u32..110_000)
+            .filter(|key| filter.contains(key.to_le_bytes()) == FilterResult::NeedAdditionalCheck)
+            .count();
+        // blocking adds a little to the rate of the plain bloom filter
+        assert!(false_positives < 2_000, "{}", false_positives);
+    }
+}
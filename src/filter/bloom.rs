@@ -3,6 +3,12 @@ use ahash::AHasher;
 use bitvec::order::Lsb0;
 use bitvec::prelude::*;
 use std::hash::Hasher;
+use xxhash_rust::xxh3::xxh3_128;
+
+// serialized filter starts with this marker combined with the hash algorithm tag,
+// filters saved before algorithm was recorded and filters with ahash start with elements
+// count of config, which never has the marker bits set
+const ALGORITHM_MARKER: u64 = 0xffff_ffff_0000_0000;
 
 // All usizes in structures are serialized as u64 in binary
 #[derive(Clone)]
@@ -10,10 +16,104 @@ use std::hash::Hasher;
 pub struct Bloom {
     inner: Option<BitVec<Lsb0, u64>>,
     bits_count: usize,
-    hashers: Vec<AHasher>,
+    hashers: Hashers,
     config: Config,
 }
 
+/// Hash algorithm, which bloom filter derives probed bits of a key from. Algorithm is saved
+/// with the filter, so saved filter is always checked with the algorithm it was built with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashAlgorithm {
+    /// `ahash` 0.7.4 hasher with own keys for each probe. Filters saved before the algorithm
+    /// was recorded are read with it.
+    AHash,
+    /// xxh3 128-bit hash, halves of which give all probes by double hashing.
+    #[default]
+    Xxh3,
+}
+
+impl HashAlgorithm {
+    // tags are saved with filters, so they must never change
+    pub(crate) fn tag(self) -> u64 {
+        match self {
+            Self::AHash => 0,
+            Self::Xxh3 => 1,
+        }
+    }
+
+    pub(crate) fn from_tag(tag: u64) -> Result<Self> {
+        match tag {
+            0 => Ok(Self::AHash),
+            1 => Ok(Self::Xxh3),
+            _ => Err(anyhow::anyhow!(
+                "unknown bloom filter hash algorithm {}",
+                tag
+            )),
+        }
+    }
+
+    // reads algorithm from the beginning of serialized filter, if it's recorded
+    fn read_from(buf: &mut &[u8]) -> Result<Self> {
+        if buf.len() < size_of::<u64>() {
+            return Ok(Self::AHash);
+        }
+        let word: u64 = bincode::deserialize(&buf[..size_of::<u64>()])?;
+        if word & ALGORITHM_MARKER == ALGORITHM_MARKER {
+            *buf = &buf[size_of::<u64>()..];
+            Self::from_tag(word & !ALGORITHM_MARKER)
+        } else {
+            Ok(Self::AHash)
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Hashers {
+    AHash(Vec<AHasher>),
+    Xxh3(usize),
+}
+
+impl Hashers {
+    fn new(algorithm: HashAlgorithm, k: usize) -> Self {
+        match algorithm {
+            HashAlgorithm::AHash => Self::AHash(
+                (0..k)
+                    .map(|i| AHasher::new_with_keys((i + 1) as u128, (i + 2) as u128))
+                    .collect(),
+            ),
+            HashAlgorithm::Xxh3 => Self::Xxh3(k),
+        }
+    }
+
+    fn count(&self) -> usize {
+        match self {
+            Self::AHash(hashers) => hashers.len(),
+            Self::Xxh3(k) => *k,
+        }
+    }
+
+    // Returns empty iterator on len == 0
+    fn indices<'a>(&'a self, len: u64, item: &'a [u8]) -> impl Iterator<Item = u64> + 'a {
+        let hash = match self {
+            Self::AHash(_) => 0,
+            Self::Xxh3(_) => xxh3_128(item),
+        };
+        // second hash is odd, so probes don't repeat for power of two lengths
+        let (first, second) = (hash as u64, (hash >> 64) as u64 | 1);
+        (0..self.count()).filter_map(move |i| {
+            let hash = match self {
+                Self::AHash(hashers) => {
+                    let mut hasher = hashers[i].clone();
+                    hasher.write(item);
+                    hasher.finish()
+                }
+                Self::Xxh3(_) => first.wrapping_add((i as u64).wrapping_mul(second)),
+            };
+            hash.checked_rem(len)
+        })
+    }
+}
+
 impl Debug for Bloom {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         struct InnerDebug(usize, usize);
@@ -40,7 +140,7 @@ impl Default for Bloom {
         Self {
             inner: Some(Default::default()),
             bits_count: 0,
-            hashers: vec![],
+            hashers: Hashers::new(HashAlgorithm::default(), 0),
             config: Default::default(),
         }
     }
@@ -79,6 +179,14 @@ where
     fn memory_allocated(&self) -> usize {
         self.memory_allocated()
     }
+
+    fn fill_ratio(&self) -> Option<f64> {
+        self.fill_ratio()
+    }
+
+    fn estimated_false_positive_rate(&self) -> Option<f64> {
+        self.estimated_false_positive_rate()
+    }
 }
 
 /// Bloom filter configuration parameters.
@@ -98,6 +206,23 @@ pub struct Config {
     /// size by step and checks result false positive rate to be less than param.
     /// It stops once buffer reaches size of max_buf_bits_count.
     pub preferred_false_positive_rate: f64,
+    /// kind of the filter for closed blobs, it's not saved with bloom filter.
+    #[serde(skip)]
+    pub kind: FilterKind,
+    /// size filters by actual keys count instead of `elements`: active blob gets
+    /// [`ScalableBloom`], which grows with keys, and bloom filter of closed blob is rebuilt
+    /// for its keys count on dump. Keys count is rounded up to a few size classes, so filters
+    /// of blobs with close keys counts have the same size and are merged into filters of blob
+    /// groups. It's not saved with bloom filter.
+    ///
+    /// [`ScalableBloom`]: ../scalable/struct.ScalableBloom.html
+    #[serde(skip)]
+    pub auto_size: bool,
+    /// hash algorithm of new filters, it's saved in front of the serialized filter.
+    /// Filters of existing blobs keep their algorithm, filters with different algorithms
+    /// aren't merged into filters of blob groups.
+    #[serde(skip)]
+    pub hash_algorithm: HashAlgorithm,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -115,11 +240,38 @@ impl Default for Config {
             max_buf_bits_count: 8_388_608, // 1Mb
             buf_increase_step: 8196,
             preferred_false_positive_rate: 0.001,
+            kind: FilterKind::Bloom,
+            auto_size: false,
+            hash_algorithm: HashAlgorithm::default(),
         }
     }
 }
 
-fn m_from_fpr(fpr: f64, k: f64, n: f64) -> f64 {
+impl Config {
+    // size classes are `elements` halved up to this number of times or doubled
+    const SMALLER_SIZE_CLASSES: usize = 6;
+
+    /// Config of the filter sized for `keys_count` keys, `elements` is rounded up to
+    /// the size class.
+    pub(crate) fn sized_for(&self, keys_count: usize) -> Self {
+        let mut elements = self.elements.max(1);
+        for _ in 0..Self::SMALLER_SIZE_CLASSES {
+            if elements / 2 < keys_count.max(1) {
+                break;
+            }
+            elements /= 2;
+        }
+        while elements < keys_count {
+            elements *= 2;
+        }
+        Self {
+            elements,
+            ..self.clone()
+        }
+    }
+}
+
+pub(crate) fn m_from_fpr(fpr: f64, k: f64, n: f64) -> f64 {
     -k * n / (1_f64 - fpr.powf(1_f64 / k)).ln()
 }
 
@@ -167,7 +319,7 @@ impl Bloom {
         let bits_count = bits_count_from_formula(&config);
         Self {
             inner: Some(bitvec![Lsb0, u64; 0; bits_count]),
-            hashers: Self::hashers(config.hashers_count),
+            hashers: Hashers::new(config.hash_algorithm, config.hashers_count),
             config,
             bits_count,
         }
@@ -176,6 +328,11 @@ impl Bloom {
     /// Merge filters
     #[must_use]
     pub fn checked_add_assign(&mut self, other: &Bloom) -> bool {
+        if self.config.hash_algorithm != other.config.hash_algorithm
+            || self.config.hashers_count != other.config.hashers_count
+        {
+            return false;
+        }
         match (&mut self.inner, &other.inner) {
             (Some(inner), Some(other_inner)) if inner.len() == other_inner.len() => {
                 inner
@@ -194,6 +351,10 @@ impl Bloom {
         self.inner = Some(bitvec![Lsb0, u64; 0; self.bits_count]);
     }
 
+    pub(crate) fn hash_algorithm(&self) -> HashAlgorithm {
+        self.config.hash_algorithm
+    }
+
     /// Check if filter offloaded
     pub fn is_offloaded(&self) -> bool {
         self.inner.is_none()
@@ -206,13 +367,6 @@ impl Bloom {
         freed
     }
 
-    fn hashers(k: usize) -> Vec<AHasher> {
-        trace!("@TODO create configurable hashers???");
-        (0..k)
-            .map(|i| AHasher::new_with_keys((i + 1) as u128, (i + 2) as u128))
-            .collect()
-    }
-
     fn save(&self) -> Option<Save> {
         if let Some(inner) = &self.inner {
             Some(Save {
@@ -225,12 +379,16 @@ impl Bloom {
         }
     }
 
-    fn from(save: Save) -> Self {
+    fn from(save: Save, hash_algorithm: HashAlgorithm) -> Self {
         let mut inner = BitVec::from_vec(save.buf);
         inner.truncate(save.bits_count);
+        let config = Config {
+            hash_algorithm,
+            ..save.config
+        };
         Self {
-            hashers: Self::hashers(save.config.hashers_count),
-            config: save.config,
+            hashers: Hashers::new(hash_algorithm, config.hashers_count),
+            config,
             inner: Some(inner),
             bits_count: save.bits_count,
         }
@@ -241,20 +399,69 @@ impl Bloom {
         let save = self
             .save()
             .ok_or_else(|| anyhow::anyhow!("Filter buffer offloaded, can't serialize"))?;
-        bincode::serialize(&save).map_err(Into::into)
+        let mut buf = match self.config.hash_algorithm {
+            // filters with ahash keep layout of filters without recorded algorithm
+            HashAlgorithm::AHash => Vec::new(),
+            algorithm => bincode::serialize(&(ALGORITHM_MARKER | algorithm.tag()))?,
+        };
+        bincode::serialize_into(&mut buf, &save)?;
+        Ok(buf)
     }
 
     /// Deserialize filter from bytes
     pub fn from_raw(buf: &[u8]) -> Result<Self> {
-        let save: Save = bincode::deserialize(buf)?;
-        Ok(Self::from(save))
+        Ok(Self::from_raw_prefix(buf)?.0)
+    }
+
+    /// Deserialize filter from the beginning of bytes, returns the rest of them.
+    pub(crate) fn from_raw_prefix(mut buf: &[u8]) -> Result<(Self, &[u8])> {
+        let hash_algorithm = HashAlgorithm::read_from(&mut buf)?;
+        let save: Save = bincode::deserialize_from(&mut buf)?;
+        Ok((Self::from(save, hash_algorithm), buf))
+    }
+
+    /// Max size of the serialized filter part, which precedes the buffer data: hash algorithm,
+    /// config and buffer length. Filters without hash algorithm are followed by at least
+    /// bits count, so this size can be read for any filter.
+    pub(crate) fn raw_prefix_size() -> Result<u64> {
+        Ok(bincode::serialized_size(&Config::default())? + 2 * size_of::<u64>() as u64)
+    }
+
+    /// Size of the serialized filter part, which precedes the buffer data, and size in bytes
+    /// of the buffer data, which follows it.
+    pub(crate) fn raw_layout(mut prefix: &[u8]) -> Result<(u64, u64)> {
+        let prefix_len = prefix.len();
+        HashAlgorithm::read_from(&mut prefix)?;
+        let (_, words): (Config, u64) = bincode::deserialize(prefix)?;
+        let skipped = (prefix_len - prefix.len()) as u64;
+        let prefix_size =
+            skipped + bincode::serialized_size(&Config::default())? + size_of::<u64>() as u64;
+        Ok((prefix_size, words * size_of::<u64>() as u64))
+    }
+
+    /// Restore offloaded filter from the serialized `prefix` and bits count, which is
+    /// serialized right after the buffer data, so the buffer itself isn't read.
+    pub(crate) fn offloaded_from_raw(mut prefix: &[u8], bits_count: &[u8]) -> Result<Self> {
+        let hash_algorithm = HashAlgorithm::read_from(&mut prefix)?;
+        let (config, _): (Config, u64) = bincode::deserialize(prefix)?;
+        let bits_count = bincode::deserialize(bits_count)?;
+        let config = Config {
+            hash_algorithm,
+            ..config
+        };
+        Ok(Self {
+            hashers: Hashers::new(hash_algorithm, config.hashers_count),
+            config,
+            inner: None,
+            bits_count,
+        })
     }
 
     /// Add value to filter
     pub fn add(&mut self, item: impl AsRef<[u8]>) -> Result<()> {
         if let Some(inner) = &mut self.inner {
             let len = inner.len() as u64;
-            for h in Self::iter_indices_for_key(&self.hashers, len, item.as_ref()) {
+            for h in self.hashers.indices(len, item.as_ref()) {
                 *inner
                     .get_mut(h as usize)
                     .expect("impossible due to mod by len") = true;
@@ -273,7 +480,9 @@ impl Bloom {
             if len == 0 {
                 return None;
             }
-            if Self::iter_indices_for_key(&self.hashers, len, item.as_ref())
+            if self
+                .hashers
+                .indices(len, item.as_ref())
                 .all(|i| *inner.get(i as usize).expect("unreachable"))
             {
                 Some(FilterResult::NeedAdditionalCheck)
@@ -285,18 +494,6 @@ impl Bloom {
         }
     }
 
-    // Returns empty iterator on len == 0
-    fn iter_indices_for_key<'a>(
-        hashers: &'a Vec<AHasher>,
-        len: u64,
-        item: &'a [u8],
-    ) -> impl Iterator<Item = u64> + 'a {
-        hashers.iter().cloned().filter_map(move |mut hasher| {
-            hasher.write(item.as_ref());
-            hasher.finish().checked_rem(len)
-        })
-    }
-
     /// Check filter by reading bits from file
     pub async fn contains_in_file<P: BloomDataProvider>(
         &self,
@@ -306,14 +503,15 @@ impl Bloom {
         if self.bits_count == 0 {
             return Ok(FilterResult::NeedAdditionalCheck);
         }
-        let mut hashers = self.hashers.clone();
-        let start_pos = self.buffer_start_position().unwrap();
-        for index in hashers.iter_mut().map(|hasher| {
-            hasher.write(item.as_ref());
-            hasher.finish() % self.bits_count as u64
-        }) {
-            let byte = provider.read_byte(start_pos + index / 8).await?;
-
+        let start_pos = self.buffer_start_position()?;
+        let indices: Vec<_> = self
+            .hashers
+            .indices(self.bits_count as u64, item.as_ref())
+            .collect();
+        // all probed bytes are requested at once, so provider can batch reads
+        let positions: Vec<_> = indices.iter().map(|index| start_pos + index / 8).collect();
+        let bytes = provider.read_bytes(&positions).await?;
+        for (index, byte) in indices.into_iter().zip(bytes) {
             if !byte
                 .view_bits::<Lsb0>()
                 .get(index as usize % 8)
@@ -325,15 +523,40 @@ impl Bloom {
         Ok(FilterResult::NeedAdditionalCheck)
     }
 
-    // bincode write len as u64 before Vec elements. sizeof(config) + sizeof(u64)
+    // bincode write len as u64 before Vec elements.
+    // sizeof(algorithm) + sizeof(config) + sizeof(u64), algorithm isn't saved for ahash
     fn buffer_start_position(&self) -> Result<u64> {
-        Ok(bincode::serialized_size(&self.config)? + std::mem::size_of::<u64>() as u64)
+        let algorithm_size = match self.config.hash_algorithm {
+            HashAlgorithm::AHash => 0,
+            HashAlgorithm::Xxh3 => size_of::<u64>() as u64,
+        };
+        Ok(algorithm_size + bincode::serialized_size(&self.config)? + size_of::<u64>() as u64)
     }
 
     /// Get amount of memory allocated for filter
     pub fn memory_allocated(&self) -> usize {
         self.inner.as_ref().map_or(0, |buf| buf.capacity() / 8)
     }
+
+    /// Share of set bits, `None` if filter is offloaded or empty.
+    pub fn fill_ratio(&self) -> Option<f64> {
+        let inner = self.inner.as_ref().filter(|inner| !inner.is_empty())?;
+        Some(inner.count_ones() as f64 / inner.len() as f64)
+    }
+
+    /// Probability that all bits of an absent key are set, estimated from the fill ratio.
+    pub fn estimated_false_positive_rate(&self) -> Option<f64> {
+        self.fill_ratio()
+            .map(|fill| fill.powi(self.hashers.count() as i32))
+    }
+
+    /// Memory allocated by the filter or by the offloaded filter after its loading.
+    pub(crate) fn memory_required(&self) -> usize {
+        self.inner.as_ref().map_or_else(
+            || self.bits_count.div_ceil(64) * std::mem::size_of::<u64>(),
+            |buf| buf.capacity() / 8,
+        )
+    }
 }
 
 mod tests {
@@ -382,4 +605,92 @@ mod tests {
             diff
         );
     }
+
+    #[test]
+    fn auto_size_classes() {
+        use super::Config;
+        let config = Config::default();
+        let elements = |keys_count| config.sized_for(keys_count).elements;
+        assert_eq!(elements(900), 1_562);
+        assert_eq!(elements(1_100), 1_562);
+        assert_eq!(elements(1_563), 3_125);
+        assert_eq!(elements(0), 1_562);
+        assert_eq!(elements(100_000), 100_000);
+        assert_eq!(elements(150_000), 200_000);
+    }
+
+    #[test]
+    fn legacy_ahash_filter_loads() {
+        use super::{Bloom, Config, HashAlgorithm, Save};
+        // bits set by ahash 0.7.4 for key "legacy" in 64 bits filter
+        const PROBED_BITS: u64 = 0x2000_0200_0000_0000;
+        let config = Config {
+            elements: 10,
+            max_buf_bits_count: 64,
+            ..Default::default()
+        };
+        // filter saved before hash algorithm was recorded, bits of key "legacy" are set
+        let save = Save {
+            config: config.clone(),
+            buf: vec![PROBED_BITS],
+            bits_count: 64,
+        };
+        let filter = Bloom::from_raw(&bincode::serialize(&save).unwrap()).unwrap();
+        assert_eq!(filter.config.hash_algorithm, HashAlgorithm::AHash);
+        assert_eq!(
+            filter.contains_in_memory("legacy"),
+            Some(super::FilterResult::NeedAdditionalCheck)
+        );
+
+        // output of ahash must not change, otherwise saved filters are corrupted
+        let mut filter = Bloom::new(Config {
+            hash_algorithm: HashAlgorithm::AHash,
+            ..config
+        });
+        filter.add("legacy").unwrap();
+        assert_eq!(
+            filter.inner.as_ref().unwrap().as_raw_slice(),
+            &[PROBED_BITS]
+        );
+        assert_eq!(filter.to_raw().unwrap(), bincode::serialize(&save).unwrap());
+    }
+
+    #[test]
+    fn hash_algorithm_is_saved() {
+        use super::{Bloom, Config, FilterResult, HashAlgorithm};
+        let filled = |hash_algorithm| {
+            let mut filter = Bloom::new(Config {
+                elements: 1_000,
+                hash_algorithm,
+                ..Default::default()
+            });
+            for key in 0_u32..1_000 {
+                filter.add(key.to_be_bytes()).unwrap();
+            }
+            filter
+        };
+        let filter = filled(HashAlgorithm::Xxh3);
+        let raw = filter.to_raw().unwrap();
+        let restored = Bloom::from_raw(&raw).unwrap();
+        assert_eq!(restored.config.hash_algorithm, HashAlgorithm::Xxh3);
+        assert!((0_u32..1_000).all(|key| {
+            restored.contains_in_memory(key.to_be_bytes())
+                == Some(FilterResult::NeedAdditionalCheck)
+        }));
+        let false_positives = (1_000_u32..11_000)
+            .filter(|key| {
+                restored.contains_in_memory(key.to_be_bytes())
+                    == Some(FilterResult::NeedAdditionalCheck)
+            })
+            .count();
+        assert!(false_positives < 50, "{}", false_positives);
+
+        let (prefix_size, buffer_size) = Bloom::raw_layout(&raw).unwrap();
+        assert_eq!(prefix_size, Bloom::raw_prefix_size().unwrap());
+        assert_eq!(prefix_size + buffer_size + 8, raw.len() as u64);
+
+        // filters with different hash algorithms can't be merged
+        let mut legacy = filled(HashAlgorithm::AHash);
+        assert!(!legacy.checked_add_assign(&restored));
+    }
 }
@@ -0,0 +1,127 @@
+use super::*;
+
+/// Range, bloom and prefix filters of a blob or a group of blobs. Range filter is checked
+/// first, so keys out of key ranges of the blobs are rejected without bloom filter lookups.
+/// Range filters are always merged, bloom and prefix filters are dropped if they can't be
+/// merged.
+#[derive(Debug, Clone)]
+pub struct CombinedFilter<K: Key> {
+    pub(crate) range: RangeFilter<K>,
+    pub(crate) bloom: Option<Bloom>,
+    pub(crate) prefix: Option<PrefixFilter>,
+}
+
+impl<K: Key> CombinedFilter<K> {
+    /// Create filter
+    pub fn new(range: RangeFilter<K>, bloom: Option<Bloom>, prefix: Option<PrefixFilter>) -> Self {
+        Self {
+            range,
+            bloom,
+            prefix,
+        }
+    }
+
+    /// Range filter
+    pub fn range(&self) -> &RangeFilter<K> {
+        &self.range
+    }
+
+    /// Bloom filter, `None` if bloom filters of blobs couldn't be merged
+    pub fn bloom(&self) -> Option<&Bloom> {
+        self.bloom.as_ref()
+    }
+
+    /// Prefix filter, `None` if it's not built or prefix filters of blobs couldn't be merged
+    pub fn prefix(&self) -> Option<&PrefixFilter> {
+        self.prefix.as_ref()
+    }
+}
+
+#[async_trait::async_trait]
+impl<K> FilterTrait<K> for CombinedFilter<K>
+where
+    K: Key,
+{
+    fn add(&mut self, key: &K) {
+        self.range.add(key);
+        if let Some(bloom) = &mut self.bloom {
+            let _ = bloom.add(key);
+        }
+        if let Some(prefix) = &mut self.prefix {
+            let _ = prefix.add_key(key);
+        }
+    }
+
+    fn contains_fast(&self, key: &K) -> FilterResult {
+        if !self.range.contains(key) {
+            return FilterResult::NotContains;
+        }
+        self.bloom
+            .as_ref()
+            .and_then(|bloom| bloom.contains_in_memory(key))
+            .unwrap_or_default()
+    }
+
+    fn contains_prefix_fast(&self, prefix: &[u8]) -> FilterResult {
+        if !self.range.contains_prefix(prefix) {
+            return FilterResult::NotContains;
+        }
+        self.prefix
+            .as_ref()
+            .map_or(FilterResult::NeedAdditionalCheck, |filter| {
+                filter.contains_prefix(prefix)
+            })
+    }
+
+    async fn contains<P: BloomDataProvider>(&self, provider: &P, key: &K) -> FilterResult {
+        if !self.range.contains(key) {
+            return FilterResult::NotContains;
+        }
+        match &self.bloom {
+            Some(bloom) => FilterTrait::contains(bloom, provider, key).await,
+            None => FilterResult::NeedAdditionalCheck,
+        }
+    }
+
+    fn offload_filter(&mut self) -> usize {
+        self.bloom.as_mut().map_or(0, Bloom::offload_from_memory)
+    }
+
+    fn checked_add_assign(&mut self, other: &Self) -> bool {
+        // range filters are always merged
+        let _ = self.range.checked_add_assign(&other.range);
+        let merged = match (&mut self.bloom, &other.bloom) {
+            (Some(bloom), Some(other)) => bloom.checked_add_assign(other),
+            _ => false,
+        };
+        if !merged {
+            self.bloom = None;
+        }
+        let merged = match (&mut self.prefix, &other.prefix) {
+            (Some(prefix), Some(other)) => prefix.checked_add_assign(other),
+            _ => false,
+        };
+        if !merged {
+            self.prefix = None;
+        }
+        true
+    }
+
+    fn memory_allocated(&self) -> usize {
+        self.bloom.as_ref().map_or(0, Bloom::memory_allocated)
+            + self
+                .prefix
+                .as_ref()
+                .map_or(0, PrefixFilter::memory_allocated)
+    }
+
+    fn fill_ratio(&self) -> Option<f64> {
+        self.bloom.as_ref().and_then(Bloom::fill_ratio)
+    }
+
+    fn estimated_false_positive_rate(&self) -> Option<f64> {
+        self.bloom
+            .as_ref()
+            .and_then(Bloom::estimated_false_positive_rate)
+    }
+}
@@ -65,6 +65,7 @@ struct InnerNode<Key, Filter> {
     filter: Option<Filter>,
     children: Vec<InnerId>,
     parent: Option<InnerId>,
+    counters: FilterCounters,
     _marker: PhantomData<Key>,
 }
 
@@ -74,6 +75,7 @@ impl<Key, Filter> Default for InnerNode<Key, Filter> {
             filter: None,
             children: vec![],
             parent: Default::default(),
+            counters: Default::default(),
             _marker: Default::default(),
         }
     }
@@ -275,67 +277,147 @@ where
         self.extend(values).await;
     }
 
+    /// Recomputes filters of the nodes above `children` from filters of their children, so
+    /// changed filters of children get into filters of groups. Unlike [`reload`] children
+    /// keep their ids and nodes keep their counters.
+    ///
+    /// [`reload`]: HierarchicalFilters::reload
+    pub async fn rebuild_filters(&mut self, children: impl IntoIterator<Item = ChildId>) {
+        let mut nodes = BTreeSet::new();
+        for id in children {
+            let mut parent = self.get_child(id).map(|child| child.parent);
+            let mut depth = 0;
+            while let Some(id) = parent {
+                nodes.insert((depth, id));
+                parent = self.get(id).parent;
+                depth += 1;
+            }
+        }
+        // nodes are ordered by height, so parents are rebuilt after their children
+        for (_, id) in nodes {
+            self.rebuild_node_filter(id).await;
+        }
+    }
+
+    // Merges filters of the node children, removed children are skipped
+    async fn rebuild_node_filter(&mut self, id: InnerId) {
+        let mut filter = None;
+        let mut is_first = true;
+        for &inner_id in &self.get(id).children {
+            let item_filter = match self.get_inner(inner_id) {
+                Some(Inner::Leaf(leaf)) => match self.get_child(leaf.leaf) {
+                    Some(child) => Self::get_filter_from_child(&child.data).await,
+                    None => continue,
+                },
+                Some(Inner::Node(node)) => node.filter.as_ref().map(Cow::Borrowed),
+                None => continue,
+            };
+            if is_first {
+                Self::init_filter_from_cow(&mut filter, &item_filter);
+                is_first = false;
+            } else {
+                Self::add_filter_from_cow(&mut filter, &item_filter);
+            }
+        }
+        self.get_mut(id).filter = filter;
+    }
+
     /// Add child to collection
     pub async fn push(&mut self, child: Child) -> ChildId {
+        let node = self.next_parent();
+        let item_filter = Self::get_filter_from_child(&child).await;
+        self.add_filter_to_node(node, &item_filter);
+        drop(item_filter);
+        let res = self.add_child(node, child);
+        self.split_full_root();
+        res
+    }
+
+    /// Add children of the whole group with the known filter of the group, so filters of
+    /// the children aren't needed. If children don't form the next group, they are pushed
+    /// one by one.
+    pub async fn push_group(&mut self, children: Vec<Child>, filter: Filter) {
+        if self.group_size == 0
+            || children.len() != self.group_size
+            || !self.children.len().is_multiple_of(self.group_size)
+        {
+            self.extend(children).await;
+            return;
+        }
+        // merge of the group filter with itself doesn't change it
+        let item_filter = Some(Cow::Borrowed(&filter));
+        for child in children {
+            let node = self.next_parent();
+            self.add_filter_to_node(node, &item_filter);
+            self.add_child(node, child);
+            self.split_full_root();
+        }
+    }
+
+    // Returns node for the next child, creates new node if needed
+    fn next_parent(&mut self) -> InnerId {
         if self.children.len() < self.group_size {
             // Add child to root child list
-            let res = self.add_child(self.root, child).await;
-            if self.children.len() >= self.group_size {
-                // Replace root with new node and push to it
-                let new_root_id = self.inner.len();
-                let filter = {
-                    let root = self.root_mut();
-                    root.parent = Some(new_root_id);
-                    root.filter.clone()
-                };
-                let new_root = Inner::Node(InnerNode {
-                    filter,
-                    children: vec![self.root],
-                    ..Default::default()
-                });
-                self.root = new_root_id;
-                self.inner.push(Some(new_root));
-            }
-            res
+            self.root
         } else {
             // Push child to node
-            let mut id = self.last_inner_node().unwrap();
+            let id = self.last_inner_node().unwrap();
             if self.get(id).children.len() >= self.group_size {
                 // Create new node if needed
-                id = self.new_inner_node();
+                self.new_inner_node()
+            } else {
+                id
             }
-            self.add_child(id, child).await
         }
     }
 
-    // Add child to node and adds child filter data to node and it's parents
-    async fn add_child(&mut self, node: InnerId, child: Child) -> ChildId {
-        let item_filter = Self::get_filter_from_child(&child).await;
-        let inner_id = self.inner.len();
-        let child_id = self.children.len();
-        self.inner.push(Some(Inner::Leaf(InnerLeaf {
-            parent: node,
-            leaf: child_id,
-        })));
+    // Replace root with new node, when root children count reaches group size
+    fn split_full_root(&mut self) {
+        if self.children.len() == self.group_size {
+            let new_root_id = self.inner.len();
+            let filter = {
+                let root = self.root_mut();
+                root.parent = Some(new_root_id);
+                root.filter.clone()
+            };
+            let new_root = Inner::Node(InnerNode {
+                filter,
+                children: vec![self.root],
+                ..Default::default()
+            });
+            self.root = new_root_id;
+            self.inner.push(Some(new_root));
+        }
+    }
 
+    // Adds child filter data to node and it's parents, must be called before child is added
+    fn add_filter_to_node(&mut self, node: InnerId, item_filter: &Option<Cow<'_, Filter>>) {
         let mut parent = {
             let node = self.get_mut(node);
             if node.children.is_empty() {
-                Self::init_filter_from_cow(&mut node.filter, &item_filter);
+                Self::init_filter_from_cow(&mut node.filter, item_filter);
             } else {
-                Self::add_filter_from_cow(&mut node.filter, &item_filter);
+                Self::add_filter_from_cow(&mut node.filter, item_filter);
             }
-            node.children.push(inner_id);
             node.parent
         };
 
         while let Some(id) = parent {
             let node = self.get_mut(id);
-            Self::add_filter_from_cow(&mut node.filter, &item_filter);
+            Self::add_filter_from_cow(&mut node.filter, item_filter);
             parent = node.parent;
         }
+    }
 
-        drop(item_filter);
+    // Add child to node
+    fn add_child(&mut self, node: InnerId, child: Child) -> ChildId {
+        let inner_id = self.inner.len();
+        let child_id = self.children.len();
+        self.inner.push(Some(Inner::Leaf(InnerLeaf {
+            parent: node,
+            leaf: child_id,
+        })));
+        self.get_mut(node).children.push(inner_id);
         let child = Leaf {
             data: child,
             parent: node,
@@ -377,6 +459,14 @@ where
         PossibleRevIter::new(self, key, true)
     }
 
+    /// Iter over childs which may contain keys starting with `prefix`
+    pub fn iter_possible_childs_by_prefix<'a>(
+        &'a self,
+        prefix: &'a [u8],
+    ) -> PossibleRevIter<'a, Key, Filter, Child> {
+        PossibleRevIter::with_query(self, Query::Prefix(prefix), false)
+    }
+
     /// Add key to all parents in collection
     pub fn add_to_parents(&mut self, child_id: ChildId, item: &Key) {
         if let Some(child) = self.get_child(child_id) {
@@ -511,6 +601,80 @@ impl<Key, Filter, Child> HierarchicalFilters<Key, Filter, Child> {
         self.inner.get_mut(id).map(|x| x.as_mut()).flatten()
     }
 
+    /// Returns full groups of children with filters of the groups. Groups with removed
+    /// children are skipped.
+    pub fn groups(&self) -> Vec<(Vec<&Child>, Option<&Filter>)> {
+        if self.group_size == 0 || self.children.len() < self.group_size {
+            return Vec::new();
+        }
+        self.root()
+            .children
+            .iter()
+            .filter_map(|&id| {
+                let node = self.get(id);
+                let children: Vec<_> = node
+                    .children
+                    .iter()
+                    .map(|&id| match self.get_inner(id) {
+                        Some(Inner::Leaf(leaf)) => self.get_child(leaf.leaf).map(|x| &x.data),
+                        _ => None,
+                    })
+                    .collect::<Option<_>>()?;
+                if children.len() == self.group_size {
+                    Some((children, node.filter.as_ref()))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Returns filter stats of the nodes by levels, starting from root
+    pub fn level_stats(&self) -> Vec<LevelFilterStats>
+    where
+        Key: Send + Sync,
+        Filter: FilterTrait<Key>,
+    {
+        let mut levels = Vec::new();
+        let mut ids = vec![self.root];
+        while !ids.is_empty() {
+            let nodes: Vec<_> = ids
+                .iter()
+                .filter_map(|&id| match self.get_inner(id) {
+                    Some(Inner::Node(node)) => Some(node),
+                    _ => None,
+                })
+                .collect();
+            if nodes.is_empty() {
+                break;
+            }
+            let mut stats = LevelFilterStats {
+                depth: levels.len(),
+                nodes: nodes.len(),
+                ..Default::default()
+            };
+            let mut fills = Vec::new();
+            let mut rates = Vec::new();
+            for node in &nodes {
+                let checks = node.counters.snapshot();
+                stats.lookups += checks.bloom_rejected + checks.passed;
+                stats.rejected += checks.bloom_rejected;
+                if let Some(filter) = &node.filter {
+                    fills.extend(filter.fill_ratio());
+                    rates.extend(filter.estimated_false_positive_rate());
+                }
+            }
+            stats.fill_ratio = average(&fills);
+            stats.estimated_false_positive_rate = average(&rates);
+            levels.push(stats);
+            ids = nodes
+                .iter()
+                .flat_map(|node| node.children.iter().copied())
+                .collect();
+        }
+        levels
+    }
+
     /// Returns a iterator over the childs
     pub fn iter(&self) -> impl Iterator<Item = &Child> {
         self.children
@@ -576,25 +740,60 @@ where
     }
 }
 
+fn average(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
 #[derive(Debug)]
 /// PossibleIter
 pub struct PossibleRevIter<'a, Key, Filter, Child> {
     this: &'a HierarchicalFilters<Key, Filter, Child>,
     stack: Vec<(usize, &'a Inner<Key, Filter>)>,
-    key: &'a Key,
+    query: Query<'a, Key>,
     rev: bool,
 }
 
+// what children of iterated nodes should contain
+#[derive(Debug)]
+enum Query<'a, Key> {
+    Key(&'a Key),
+    Prefix(&'a [u8]),
+}
+
+impl<'a, Key> Query<'a, Key>
+where
+    Key: Sync + Send,
+{
+    fn check<Filter: FilterTrait<Key>>(&self, filter: &Filter) -> FilterResult {
+        match self {
+            Self::Key(key) => filter.contains_fast(key),
+            Self::Prefix(prefix) => filter.contains_prefix_fast(prefix),
+        }
+    }
+}
+
 impl<'a, Key, Filter, Child> PossibleRevIter<'a, Key, Filter, Child>
 where
     Key: Sync + Send,
     Filter: FilterTrait<Key>,
 {
     fn new(this: &'a HierarchicalFilters<Key, Filter, Child>, key: &'a Key, rev: bool) -> Self {
+        Self::with_query(this, Query::Key(key), rev)
+    }
+
+    fn with_query(
+        this: &'a HierarchicalFilters<Key, Filter, Child>,
+        query: Query<'a, Key>,
+        rev: bool,
+    ) -> Self {
         Self {
             stack: vec![(0, this.get_inner(this.root).expect("should exist"))],
             this,
-            key,
+            query,
             rev,
         }
     }
@@ -627,8 +826,11 @@ where
                     match inner {
                         // Ignore nodes which is not contains key
                         Inner::Node(node)
-                            if node.filter.as_ref().map(|f| f.contains_fast(&self.key))
-                                == Some(FilterResult::NotContains) => {}
+                            if node.filter.as_ref().map(|f| {
+                                let res = self.query.check(f);
+                                node.counters.record(&res);
+                                res
+                            }) == Some(FilterResult::NotContains) => {}
                         // Ignore if leaf was removed
                         Inner::Leaf(leaf) if self.this.get_child(leaf.leaf).is_none() => {}
                         _ => {
@@ -1,18 +1,41 @@
+/// Cache-blocked bloom filter
+pub mod blocked;
 /// Bloom filter
 pub mod bloom;
+/// Range and bloom filters combined
+pub mod combined;
 /// Hierarchical
 pub mod hierarchical;
+/// Prefix filter
+pub mod prefix;
 /// Range filter
 pub mod range;
+/// Scalable bloom filter
+pub mod scalable;
+/// Filters of closed blobs, built on index dump
+pub mod static_filter;
+/// Filters statistics
+pub mod stats;
 /// Traits
 pub mod traits;
+/// Xor filter
+pub mod xor;
 
 use super::prelude::*;
+pub use blocked::*;
 pub use bloom::*;
+pub use combined::*;
 pub use hierarchical::*;
+pub use prefix::*;
 pub use range::*;
+pub use scalable::*;
+pub use static_filter::FilterKind;
+pub(crate) use static_filter::{key_hash, StaticFilter};
+pub(crate) use stats::FilterCounters;
+pub use stats::{BlobFilterStats, FilterCheckStats, FilterStats, LevelFilterStats};
 use std::ops::Add;
 pub use traits::*;
+pub use xor::*;
 
 #[derive(PartialEq, Eq, Debug)]
 /// Filter result
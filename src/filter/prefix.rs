@@ -0,0 +1,153 @@
+use super::*;
+
+/// Bloom filter of key prefixes of fixed length, which tells if a blob may contain
+/// keys starting with some prefix.
+#[derive(Debug, Clone)]
+pub struct PrefixFilter {
+    prefix_len: usize,
+    bloom: Bloom,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Save {
+    prefix_len: usize,
+    bloom: Vec<u8>,
+}
+
+impl PrefixFilter {
+    /// Create empty filter of prefixes of `prefix_len` bytes
+    pub fn new(prefix_len: usize, config: Config) -> Self {
+        Self {
+            prefix_len,
+            bloom: Bloom::new(config),
+        }
+    }
+
+    /// Create filter from prefixes of sorted keys. Filter is sized for the number of distinct
+    /// prefixes rounded up to the size class, so filters of blobs with close numbers of
+    /// prefixes can be merged.
+    pub fn build<T: AsRef<[u8]>>(
+        prefix_len: usize,
+        config: Config,
+        keys: impl IntoIterator<Item = T>,
+    ) -> Result<Self> {
+        let mut prefixes: Vec<Vec<u8>> = Vec::new();
+        for key in keys {
+            let prefix = key_prefix(prefix_len, key.as_ref());
+            // prefixes of adjacent keys are mostly equal
+            if prefixes.last().map(Vec::as_slice) != Some(prefix) {
+                prefixes.push(prefix.to_vec());
+            }
+        }
+        prefixes.sort_unstable();
+        prefixes.dedup();
+        let mut filter = Self::new(prefix_len, config.sized_for(prefixes.len()));
+        for prefix in &prefixes {
+            filter.bloom.add(prefix)?;
+        }
+        Ok(filter)
+    }
+
+    fn key_prefix<'a>(&self, key: &'a [u8]) -> &'a [u8] {
+        key_prefix(self.prefix_len, key)
+    }
+
+    /// Length of prefixes in filter
+    pub fn prefix_len(&self) -> usize {
+        self.prefix_len
+    }
+
+    /// Add prefix of the key to filter
+    pub fn add_key(&mut self, key: impl AsRef<[u8]>) -> Result<()> {
+        let prefix = self.key_prefix(key.as_ref());
+        self.bloom.add(prefix)
+    }
+
+    /// Check if filter may contain keys starting with `prefix`. Prefixes shorter than
+    /// prefix length of filter can't be checked, longer ones are checked by their beginning.
+    pub fn contains_prefix(&self, prefix: &[u8]) -> FilterResult {
+        if prefix.len() < self.prefix_len {
+            FilterResult::NeedAdditionalCheck
+        } else {
+            self.bloom
+                .contains_in_memory(&prefix[..self.prefix_len])
+                .unwrap_or_default()
+        }
+    }
+
+    /// Merge filters
+    #[must_use]
+    pub fn checked_add_assign(&mut self, other: &Self) -> bool {
+        self.prefix_len == other.prefix_len && self.bloom.checked_add_assign(&other.bloom)
+    }
+
+    /// Get amount of memory allocated for filter
+    pub fn memory_allocated(&self) -> usize {
+        self.bloom.memory_allocated()
+    }
+
+    /// Create filter from raw bytes
+    pub fn from_raw(buf: &[u8]) -> Result<Self> {
+        let save: Save = bincode::deserialize(buf)?;
+        Ok(Self {
+            prefix_len: save.prefix_len,
+            bloom: Bloom::from_raw(&save.bloom)?,
+        })
+    }
+
+    /// Convert filter to raw bytes
+    pub fn to_raw(&self) -> Result<Vec<u8>> {
+        let save = Save {
+            prefix_len: self.prefix_len,
+            bloom: self.bloom.to_raw()?,
+        };
+        bincode::serialize(&save).map_err(Into::into)
+    }
+}
+
+fn key_prefix(prefix_len: usize, key: &[u8]) -> &[u8] {
+    &key[..prefix_len.min(key.len())]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefix_filter_checks_prefixes() {
+        let keys: Vec<_> = (0_u32..10)
+            .flat_map(|tenant| {
+                (0_u32..100).map(move |id| [tenant.to_be_bytes(), id.to_be_bytes()].concat())
+            })
+            .collect();
+        let filter = PrefixFilter::build(4, Config::default(), &keys).unwrap();
+        for tenant in 0_u32..10 {
+            let prefix = tenant.to_be_bytes();
+            assert_eq!(
+                filter.contains_prefix(&prefix),
+                FilterResult::NeedAdditionalCheck
+            );
+        }
+        let absent = (1_000_u32..2_000)
+            .filter(|tenant| {
+                filter.contains_prefix(&tenant.to_be_bytes()) == FilterResult::NeedAdditionalCheck
+            })
+            .count();
+        assert!(absent < 10, "{}", absent);
+        // filter is sized for 10 prefixes, not for all keys
+        let full_size = Bloom::new(Config::default()).memory_allocated();
+        assert!(filter.memory_allocated() < full_size / 10);
+        // short prefixes can't be checked
+        assert_eq!(
+            filter.contains_prefix(&[9; 2]),
+            FilterResult::NeedAdditionalCheck
+        );
+
+        let restored = PrefixFilter::from_raw(&filter.to_raw().unwrap()).unwrap();
+        assert_eq!(restored.prefix_len(), 4);
+        assert_eq!(
+            restored.contains_prefix(&keys[150]),
+            FilterResult::NeedAdditionalCheck
+        );
+    }
+}
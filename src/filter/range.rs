@@ -1,4 +1,10 @@
 use super::*;
+use std::io::Cursor;
+
+// intervals count is limited, so filter of a blob or a group stays small
+const MAX_RANGES: usize = 16;
+// bytes of key prefix, which are used to measure distance between keys
+const DISTANCE_PREFIX_LEN: usize = size_of::<u128>();
 
 /// NOTE: le and lt operations are written for big-endian format of keys
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -8,6 +14,28 @@ pub struct RangeFilter<K: Key> {
     #[serde(serialize_with = "serialize_key", deserialize_with = "deserialize_key")]
     max: K,
     initialized: bool,
+    // sorted disjoint intervals between min and max, which contain all keys,
+    // empty if only min and max are known; saved after min and max for compatibility
+    #[serde(skip)]
+    ranges: Vec<KeyRange<K>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KeyRange<K: Key> {
+    #[serde(serialize_with = "serialize_key", deserialize_with = "deserialize_key")]
+    start: K,
+    #[serde(serialize_with = "serialize_key", deserialize_with = "deserialize_key")]
+    end: K,
+}
+
+impl<K: Key> KeyRange<K> {
+    fn new(start: K, end: K) -> Self {
+        Self { start, end }
+    }
+
+    fn contains(&self, key: &K) -> bool {
+        &self.start <= key && key <= &self.end
+    }
 }
 
 #[async_trait::async_trait]
@@ -27,9 +55,23 @@ where
         }
     }
 
+    fn contains_prefix_fast(&self, prefix: &[u8]) -> FilterResult {
+        if self.contains_prefix(prefix) {
+            FilterResult::NeedAdditionalCheck
+        } else {
+            FilterResult::NotContains
+        }
+    }
+
     fn checked_add_assign(&mut self, other: &Self) -> bool {
-        self.add(&other.min);
-        self.add(&other.max);
+        let mut ranges = self.intervals();
+        ranges.extend(other.intervals());
+        if let Some(other_min) = other.initialized.then_some(&other.min) {
+            self.add_to_bounds(other_min);
+            self.add_to_bounds(&other.max);
+        }
+        ranges.sort_by(|a, b| a.start.cmp(&b.start));
+        self.ranges = coalesce(ranges);
         true
     }
 }
@@ -43,8 +85,54 @@ impl<K: Key> RangeFilter<K> {
         }
     }
 
+    /// Create filter from sorted keys, keys are summarized with up to 16 disjoint intervals,
+    /// split at the largest gaps between keys.
+    pub fn from_sorted_keys<'a>(keys: impl IntoIterator<Item = &'a K>) -> Self
+    where
+        K: 'a,
+    {
+        let mut filter = Self::new();
+        let keys: Vec<&K> = keys.into_iter().collect();
+        let (first, last) = match (keys.first(), keys.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return filter,
+        };
+        filter.add_to_bounds(first);
+        filter.add_to_bounds(last);
+        // indexes of keys, which start new intervals, ordered by descending gap before them
+        let mut splits: Vec<(u128, usize)> = Vec::with_capacity(MAX_RANGES);
+        for (i, pair) in keys.windows(2).enumerate() {
+            let gap = distance(pair[0], pair[1]);
+            if gap == 0 || (splits.len() == MAX_RANGES - 1 && splits[MAX_RANGES - 2].0 >= gap) {
+                continue;
+            }
+            let pos = splits.partition_point(|(other, _)| *other >= gap);
+            splits.insert(pos, (gap, i + 1));
+            splits.truncate(MAX_RANGES - 1);
+        }
+        let mut starts: Vec<usize> = splits.into_iter().map(|(_, i)| i).collect();
+        starts.sort_unstable();
+        let mut start = 0;
+        for end in starts.into_iter().chain(Some(keys.len())) {
+            filter
+                .ranges
+                .push(KeyRange::new(keys[start].clone(), keys[end - 1].clone()));
+            start = end;
+        }
+        filter
+    }
+
     /// Add key to filter
     pub fn add(&mut self, key: &K) {
+        // intervals are built from all keys at once, so they are dropped if the new key
+        // doesn't fit into them
+        if !self.ranges.is_empty() && !self.ranges_contain(key) {
+            self.ranges.clear();
+        }
+        self.add_to_bounds(key);
+    }
+
+    fn add_to_bounds(&mut self, key: &K) {
         if !self.initialized {
             self.min = key.clone();
             self.max = key.clone();
@@ -58,23 +146,115 @@ impl<K: Key> RangeFilter<K> {
 
     /// Check if key contains in filter
     pub fn contains(&self, key: &K) -> bool {
-        self.initialized && &self.min <= key && key <= &self.max
+        self.initialized
+            && &self.min <= key
+            && key <= &self.max
+            && (self.ranges.is_empty() || self.ranges_contain(key))
+    }
+
+    /// Check if filter may contain keys starting with `prefix`, key ranges are checked only
+    /// for keys ordered by bytes
+    pub fn contains_prefix(&self, prefix: &[u8]) -> bool {
+        if !K::BYTE_ORDERED {
+            return self.initialized;
+        }
+        let overlaps = |start: &K, end: &K| {
+            let len = prefix.len();
+            &start.as_ref()[..len.min(start.as_ref().len())] <= prefix
+                && prefix <= &end.as_ref()[..len.min(end.as_ref().len())]
+        };
+        self.initialized
+            && overlaps(&self.min, &self.max)
+            && (self.ranges.is_empty()
+                || self
+                    .ranges
+                    .iter()
+                    .any(|range| overlaps(&range.start, &range.end)))
+    }
+
+    fn ranges_contain(&self, key: &K) -> bool {
+        let pos = self.ranges.partition_point(|range| &range.start <= key);
+        pos > 0 && self.ranges[pos - 1].contains(key)
+    }
+
+    // intervals, which contain all keys of the filter
+    fn intervals(&self) -> Vec<KeyRange<K>> {
+        if !self.ranges.is_empty() {
+            self.ranges.clone()
+        } else if self.initialized {
+            vec![KeyRange::new(self.min.clone(), self.max.clone())]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Count of disjoint intervals of keys, 0 if only min and max keys are known
+    pub fn ranges_count(&self) -> usize {
+        self.ranges.len()
     }
 
     /// Clear filter
     pub fn clear(&mut self) {
         self.initialized = false;
+        self.ranges.clear();
     }
 
     /// Create filter from raw bytes
     pub fn from_raw(buf: &[u8]) -> Result<Self> {
-        bincode::deserialize(&buf).map_err(|e| e.into())
+        let mut cursor = Cursor::new(buf);
+        let mut filter: Self = bincode::deserialize_from(&mut cursor)?;
+        // filters saved by older versions have no intervals
+        if (cursor.position() as usize) < buf.len() {
+            filter.ranges = bincode::deserialize_from(&mut cursor)?;
+        }
+        Ok(filter)
     }
 
     /// Convert filter to raw bytes
     pub fn to_raw(&self) -> Result<Vec<u8>> {
-        bincode::serialize(&self).map_err(|e| e.into())
+        let mut buf = bincode::serialize(&self)?;
+        if !self.ranges.is_empty() {
+            bincode::serialize_into(&mut buf, &self.ranges)?;
+        }
+        Ok(buf)
+    }
+}
+
+// merges overlapping intervals sorted by start, then merges intervals with the smallest
+// gaps between them, until count of intervals fits the limit
+fn coalesce<K: Key>(ranges: Vec<KeyRange<K>>) -> Vec<KeyRange<K>> {
+    let mut merged: Vec<KeyRange<K>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => {
+                if range.end > last.end {
+                    last.end = range.end;
+                }
+            }
+            _ => merged.push(range),
+        }
     }
+    while merged.len() > MAX_RANGES {
+        let closest = (1..merged.len())
+            .min_by_key(|&i| distance(&merged[i - 1].end, &merged[i].start))
+            .expect("there are more than one interval");
+        let range = merged.remove(closest);
+        merged[closest - 1].end = range.end;
+    }
+    merged
+}
+
+// distance between keys, measured on big-endian prefix of keys
+fn distance<K: Key>(from: &K, to: &K) -> u128 {
+    key_prefix(to).saturating_sub(key_prefix(from))
+}
+
+fn key_prefix<K: Key>(key: &K) -> u128 {
+    let mut prefix = [0; DISTANCE_PREFIX_LEN];
+    let key = key.as_ref();
+    let len = key.len().min(DISTANCE_PREFIX_LEN);
+    prefix[..len].copy_from_slice(&key[..len]);
+    u128::from_be_bytes(prefix)
 }
 
 fn serialize_key<K: Key, S: serde::Serializer>(key: &K, serializer: S) -> Result<S::Ok, S::Error> {
@@ -105,7 +285,7 @@ fn deserialize_key<'de, K: Key, D: serde::Deserializer<'de>>(
 
 #[cfg(test)]
 mod tests {
-    use crate::Key;
+    use crate::{filter::FilterTrait, Key};
 
     use super::RangeFilter;
 
@@ -129,6 +309,7 @@ mod tests {
 
     impl Key for ProperKey {
         const LEN: u16 = LEN;
+        const BYTE_ORDERED: bool = true;
     }
 
     #[derive(Debug, Clone, Default, PartialEq, Eq)]
@@ -199,4 +380,60 @@ mod tests {
         assert!(wrong_key_filter.contains(&greater_key));
         assert!(wrong_key_filter.contains(&in_key));
     }
+
+    fn to_be_key(i: u64) -> ProperKey {
+        i.to_be_bytes().to_vec().into()
+    }
+
+    #[test]
+    fn test_range_filter_prefix() {
+        let mut filter: RangeFilter<ProperKey> = RangeFilter::new();
+        for key in [50, 100, 150].iter().map(|&i| to_be_key(i)) {
+            filter.add(&key);
+        }
+        assert!(filter.contains_prefix(&[0; 7]));
+        assert!(!filter.contains_prefix(&[1]));
+        // keys aren't ordered by bytes, so prefixes out of the range may be in the filter
+        let buf = bincode::serialize(&filter).unwrap();
+        let wrong_key_filter: RangeFilter<WrongKey> = RangeFilter::from_raw(&buf).unwrap();
+        assert!(wrong_key_filter.contains_prefix(&[1]));
+        assert!(!RangeFilter::<WrongKey>::new().contains_prefix(&[1]));
+    }
+
+    #[test]
+    fn test_range_filter_from_sorted_keys() {
+        let keys: Vec<_> = (0..20)
+            .flat_map(|part| (0..100).map(move |i| to_be_key(part * 10_000 + i)))
+            .collect();
+        let filter = RangeFilter::from_sorted_keys(&keys);
+        assert_eq!(filter.ranges_count(), 16);
+        assert!(keys.iter().all(|key| filter.contains(key)));
+        // gaps between the first 16 parts are excluded
+        assert!(!filter.contains(&to_be_key(5_000)));
+        assert!(!filter.contains(&to_be_key(140_500)));
+        assert!(!filter.contains(&to_be_key(300_000)));
+
+        let restored: RangeFilter<ProperKey> =
+            RangeFilter::from_raw(&filter.to_raw().unwrap()).unwrap();
+        assert_eq!(restored.ranges_count(), 16);
+        assert!(!restored.contains(&to_be_key(5_000)));
+        assert!(restored.contains(&to_be_key(10_050)));
+    }
+
+    #[test]
+    fn test_range_filter_merge() {
+        let first: Vec<_> = (0..10).map(|i| to_be_key(i * 1_000)).collect();
+        let second: Vec<_> = (0..10).map(|i| to_be_key(100_000 + i)).collect();
+        let mut filter = RangeFilter::from_sorted_keys(&first);
+        assert!(filter.checked_add_assign(&RangeFilter::from_sorted_keys(&second)));
+        assert!(first.iter().chain(&second).all(|key| filter.contains(key)));
+        assert!(!filter.contains(&to_be_key(50_000)));
+        assert!(filter.ranges_count() <= 16);
+
+        // key out of intervals drops them, so filter is checked by min and max
+        filter.add(&to_be_key(50_000));
+        assert_eq!(filter.ranges_count(), 0);
+        assert!(filter.contains(&to_be_key(70_000)));
+        assert!(!filter.contains(&to_be_key(200_000)));
+    }
 }
@@ -0,0 +1,109 @@
+use super::*;
+
+const INITIAL_CAPACITY: usize = 1024;
+const GROWTH_FACTOR: usize = 2;
+// false positive rate of each next stage is tightened, so total rate stays below preferred one
+const TIGHTENING_RATIO: f64 = 0.5;
+
+/// Scalable bloom filter: a chain of bloom filters, where each next filter is created,
+/// when the previous one is full, and is larger than the previous one.
+/// Total false positive rate stays below preferred one without knowing keys count.
+#[derive(Debug, Clone)]
+pub struct ScalableBloom {
+    config: Config,
+    stages: Vec<Bloom>,
+    capacity: usize,
+    count: usize,
+}
+
+impl ScalableBloom {
+    /// Create filter with one small stage, `elements` of config are ignored.
+    pub fn new(config: Config) -> Self {
+        let mut filter = Self {
+            config,
+            stages: Vec::new(),
+            capacity: 0,
+            count: 0,
+        };
+        filter.add_stage();
+        filter
+    }
+
+    fn add_stage(&mut self) {
+        let stage = self.stages.len() as i32;
+        self.capacity = INITIAL_CAPACITY * GROWTH_FACTOR.pow(stage as u32);
+        self.count = 0;
+        let fpr = self.config.preferred_false_positive_rate
+            * (1_f64 - TIGHTENING_RATIO)
+            * TIGHTENING_RATIO.powi(stage);
+        self.stages.push(Bloom::new(Config {
+            elements: self.capacity,
+            preferred_false_positive_rate: fpr,
+            ..self.config.clone()
+        }));
+    }
+
+    /// Add value to filter
+    pub fn add(&mut self, item: impl AsRef<[u8]>) -> Result<()> {
+        if self.count >= self.capacity {
+            self.add_stage();
+        }
+        self.count += 1;
+        self.stages
+            .last_mut()
+            .expect("filter has at least one stage")
+            .add(item)
+    }
+
+    /// Check if key may be in the set.
+    pub fn contains(&self, item: impl AsRef<[u8]>) -> FilterResult {
+        let item = item.as_ref();
+        if self
+            .stages
+            .iter()
+            .any(|stage| stage.contains_in_memory(item) != Some(FilterResult::NotContains))
+        {
+            FilterResult::NeedAdditionalCheck
+        } else {
+            FilterResult::NotContains
+        }
+    }
+
+    /// Count of filters in chain
+    pub fn stages_count(&self) -> usize {
+        self.stages.len()
+    }
+
+    /// Get amount of memory allocated for filter
+    pub fn memory_allocated(&self) -> usize {
+        self.stages.iter().map(Bloom::memory_allocated).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scalable_bloom_grows() {
+        let config = Config {
+            preferred_false_positive_rate: 0.01,
+            ..Default::default()
+        };
+        let mut filter = ScalableBloom::new(config);
+        let small = filter.memory_allocated();
+        for key in 0_u32..100_000 {
+            filter.add(key.to_le_bytes()).unwrap();
+        }
+        // 1024 * (2^6 - 1) < 100_000 < 1024 * (2^7 - 1)
+        assert_eq!(filter.stages_count(), 7);
+        assert!(filter.memory_allocated() > small * 100);
+        assert!((0_u32..100_000)
+            .all(|key| filter.contains(key.to_le_bytes()) == FilterResult::NeedAdditionalCheck));
+        let false_positives = (100_000_u32..200_000)
+            .filter(|key| filter.contains(key.to_le_bytes()) == FilterResult::NeedAdditionalCheck)
+            .count();
+        // total rate is about the preferred one
+        assert!(false_positives < 1_500, "{}", false_positives);
+    }
+}
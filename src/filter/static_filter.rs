@@ -0,0 +1,89 @@
+use super::*;
+use ahash::AHasher;
+use std::hash::Hasher;
+use xxhash_rust::xxh3::xxh3_64;
+
+/// Kind of the filter, which checks keys of closed blobs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FilterKind {
+    /// Bloom filter, which is filled on writes and is kept after blob is closed.
+    #[default]
+    Bloom,
+    /// Cache-blocked bloom filter [`BlockedBloom`], built when blob index is dumped.
+    ///
+    /// [`BlockedBloom`]: blocked/struct.BlockedBloom.html
+    BlockedBloom,
+    /// Xor filter [`XorFilter`], built when blob index is dumped.
+    /// It takes less memory than bloom filter, but can't be offloaded or merged into
+    /// filters of blob groups.
+    ///
+    /// [`XorFilter`]: xor/struct.XorFilter.html
+    Xor,
+}
+
+/// Filter of a closed blob, which is built once from all keys of the blob, when its
+/// index is dumped. It's saved in index meta right after the bloom filter, so index files
+/// without it are read as files with bloom filter only.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) enum StaticFilter {
+    BlockedBloom(BlockedBloom),
+    Xor(XorFilter),
+}
+
+impl StaticFilter {
+    /// Returns `None` for filter kinds, which aren't static.
+    pub(crate) fn build<T: AsRef<[u8]>>(
+        config: &Config,
+        keys: impl ExactSizeIterator<Item = T>,
+    ) -> Option<Self> {
+        match config.kind {
+            FilterKind::Bloom => None,
+            FilterKind::BlockedBloom => {
+                Some(Self::BlockedBloom(BlockedBloom::from_keys(config, keys)))
+            }
+            FilterKind::Xor => Some(Self::Xor(XorFilter::from_keys(config.hash_algorithm, keys))),
+        }
+    }
+
+    pub(crate) fn contains(&self, item: impl AsRef<[u8]>) -> FilterResult {
+        match self {
+            Self::BlockedBloom(filter) => filter.contains(item),
+            Self::Xor(filter) => filter.contains(item),
+        }
+    }
+
+    pub(crate) fn memory_allocated(&self) -> usize {
+        match self {
+            Self::BlockedBloom(filter) => filter.memory_allocated(),
+            Self::Xor(filter) => filter.memory_allocated(),
+        }
+    }
+
+    pub(crate) fn hash_algorithm(&self) -> HashAlgorithm {
+        match self {
+            Self::BlockedBloom(filter) => filter.hash_algorithm,
+            Self::Xor(filter) => filter.hash_algorithm,
+        }
+    }
+
+    // algorithm isn't serialized with the filter, it's saved in index meta before it
+    pub(crate) fn with_hash_algorithm(mut self, hash_algorithm: HashAlgorithm) -> Self {
+        match &mut self {
+            Self::BlockedBloom(filter) => filter.hash_algorithm = hash_algorithm,
+            Self::Xor(filter) => filter.hash_algorithm = hash_algorithm,
+        }
+        self
+    }
+}
+
+// hash of the key, which static filters derive their probes from
+pub(crate) fn key_hash(hash_algorithm: HashAlgorithm, item: &[u8]) -> u64 {
+    match hash_algorithm {
+        HashAlgorithm::AHash => {
+            let mut hasher = AHasher::new_with_keys(1, 2);
+            hasher.write(item);
+            hasher.finish()
+        }
+        HashAlgorithm::Xxh3 => xxh3_64(item),
+    }
+}
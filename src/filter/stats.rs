@@ -0,0 +1,118 @@
+use super::*;
+use std::sync::atomic::AtomicU64;
+
+/// Counters of key checks by filters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FilterCheckStats {
+    /// Number of keys rejected by the range filter.
+    pub range_rejected: u64,
+    /// Number of keys rejected by the bloom (or static) filter.
+    pub bloom_rejected: u64,
+    /// Number of keys passed all filters.
+    pub passed: u64,
+    /// Number of passed keys, which weren't found in index.
+    pub false_positives: u64,
+}
+
+impl FilterCheckStats {
+    /// Share of absent keys passed by the bloom filter, `None` if no absent keys were checked.
+    #[must_use]
+    pub fn observed_false_positive_rate(&self) -> Option<f64> {
+        let absent = self.bloom_rejected + self.false_positives;
+        if absent == 0 {
+            None
+        } else {
+            Some(self.false_positives as f64 / absent as f64)
+        }
+    }
+}
+
+/// Filter statistics of a blob.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BlobFilterStats {
+    /// Blob id.
+    pub blob_id: usize,
+    /// Checks of keys by filters of the blob.
+    pub checks: FilterCheckStats,
+    /// Share of set bits of the bloom filter, `None` if filter is offloaded, empty or static.
+    pub fill_ratio: Option<f64>,
+    /// False positive rate of the bloom filter, estimated from its fill ratio.
+    pub estimated_false_positive_rate: Option<f64>,
+    /// Hash algorithm of the blob filters, `None` if filters are off.
+    pub hash_algorithm: Option<HashAlgorithm>,
+}
+
+/// Filter statistics of the nodes of one level of blob groups hierarchy.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LevelFilterStats {
+    /// Depth of the level, root is on depth 0.
+    pub depth: usize,
+    /// Number of nodes on the level.
+    pub nodes: usize,
+    /// Number of keys checked by filters of the level nodes.
+    pub lookups: u64,
+    /// Number of keys rejected by filters of the level nodes.
+    pub rejected: u64,
+    /// Average share of set bits of the level nodes filters, which are in memory.
+    pub fill_ratio: Option<f64>,
+    /// Average false positive rate of the level nodes filters, estimated from fill ratios.
+    pub estimated_false_positive_rate: Option<f64>,
+}
+
+/// Statistics of storage filters, which help to tune [`BloomConfig`].
+///
+/// [`BloomConfig`]: struct.Config.html
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FilterStats {
+    /// Stats of closed blobs in order of ids, followed by stats of the active blob.
+    pub blobs: Vec<BlobFilterStats>,
+    /// Stats of blob groups hierarchy levels, starting from root.
+    pub levels: Vec<LevelFilterStats>,
+}
+
+/// Counters of key checks, which are updated through shared reference.
+#[derive(Debug, Default)]
+pub(crate) struct FilterCounters {
+    range_rejected: AtomicU64,
+    bloom_rejected: AtomicU64,
+    passed: AtomicU64,
+    false_positives: AtomicU64,
+}
+
+impl FilterCounters {
+    pub(crate) fn record_range_rejected(&self) {
+        self.range_rejected.fetch_add(1, ORD);
+    }
+
+    pub(crate) fn record(&self, result: &FilterResult) {
+        match result {
+            FilterResult::NotContains => self.bloom_rejected.fetch_add(1, ORD),
+            FilterResult::NeedAdditionalCheck => self.passed.fetch_add(1, ORD),
+        };
+    }
+
+    pub(crate) fn record_false_positive(&self) {
+        self.false_positives.fetch_add(1, ORD);
+    }
+
+    pub(crate) fn snapshot(&self) -> FilterCheckStats {
+        FilterCheckStats {
+            range_rejected: self.range_rejected.load(ORD),
+            bloom_rejected: self.bloom_rejected.load(ORD),
+            passed: self.passed.load(ORD),
+            false_positives: self.false_positives.load(ORD),
+        }
+    }
+}
+
+impl Clone for FilterCounters {
+    fn clone(&self) -> Self {
+        let stats = self.snapshot();
+        Self {
+            range_rejected: AtomicU64::new(stats.range_rejected),
+            bloom_rejected: AtomicU64::new(stats.bloom_rejected),
+            passed: AtomicU64::new(stats.passed),
+            false_positives: AtomicU64::new(stats.false_positives),
+        }
+    }
+}
@@ -5,6 +5,17 @@ use super::*;
 pub trait BloomDataProvider: Send + Sync {
     /// Read byte from raw filter data
     async fn read_byte(&self, index: u64) -> Result<u8>;
+
+    /// Read bytes at several positions of raw filter data.
+    /// Providers should override it to fetch all positions at once,
+    /// default implementation reads bytes one by one.
+    async fn read_bytes(&self, indices: &[u64]) -> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(indices.len());
+        for &index in indices {
+            bytes.push(self.read_byte(index).await?);
+        }
+        Ok(bytes)
+    }
 }
 
 /// Trait for scructs which contains bloom filters
@@ -35,6 +46,11 @@ pub trait FilterTrait<Key: Send + Sync>: Clone + Sync + Send {
     /// Check if key in filter (should be implemented if filter can be checked without waiting)
     fn contains_fast(&self, key: &Key) -> FilterResult;
 
+    /// Check if filter may contain keys starting with `prefix`
+    fn contains_prefix_fast(&self, _prefix: &[u8]) -> FilterResult {
+        FilterResult::NeedAdditionalCheck
+    }
+
     /// Check if key in filter (can take some time)
     async fn contains<P: BloomDataProvider>(&self, _provider: &P, key: &Key) -> FilterResult {
         self.contains_fast(key)
@@ -53,4 +69,14 @@ pub trait FilterTrait<Key: Send + Sync>: Clone + Sync + Send {
     fn memory_allocated(&self) -> usize {
         0
     }
+
+    /// Share of set bits, if filter has them and is in memory
+    fn fill_ratio(&self) -> Option<f64> {
+        None
+    }
+
+    /// False positive rate estimated from filter fill
+    fn estimated_false_positive_rate(&self) -> Option<f64> {
+        None
+    }
 }
@@ -0,0 +1,195 @@
+use super::*;
+
+const MAX_CONSTRUCTION_ATTEMPTS: usize = 64;
+
+/// Static xor filter with 8-bit fingerprints.
+/// It's built once for the known set of keys and takes about 9.84 bits per key,
+/// false positive rate is about 0.4%.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct XorFilter {
+    seed: u64,
+    block_length: u32,
+    fingerprints: Vec<u8>,
+    // filter can't be updated, so once unknown key is added it can't reject keys anymore
+    #[serde(skip)]
+    outdated: bool,
+    // saved in index meta before the filter, filters saved without it are hashed with ahash
+    #[serde(skip)]
+    pub(crate) hash_algorithm: HashAlgorithm,
+}
+
+impl XorFilter {
+    /// Builds filter for the given keys, which are hashed with `hash_algorithm`.
+    pub fn from_keys<T: AsRef<[u8]>>(
+        hash_algorithm: HashAlgorithm,
+        keys: impl IntoIterator<Item = T>,
+    ) -> Self {
+        let mut hashes: Vec<_> = keys
+            .into_iter()
+            .map(|key| key_hash(hash_algorithm, key.as_ref()))
+            .collect();
+        hashes.sort_unstable();
+        hashes.dedup();
+        let capacity = 32 + (1.23 * hashes.len() as f64).ceil() as usize;
+        let block_length = (capacity / 3) as u32;
+        let mut seed = 0;
+        for _ in 0..MAX_CONSTRUCTION_ATTEMPTS {
+            seed = next_seed(seed);
+            if let Some(fingerprints) = Self::try_build(&hashes, seed, block_length) {
+                return Self {
+                    seed,
+                    block_length,
+                    fingerprints,
+                    outdated: false,
+                    hash_algorithm,
+                };
+            }
+        }
+        warn!("xor filter construction failed, filter is disabled");
+        Self {
+            seed,
+            block_length,
+            fingerprints: Vec::new(),
+            outdated: true,
+            hash_algorithm,
+        }
+    }
+
+    // Peels keys off slots, which are used by only one key, and assigns fingerprints in
+    // reverse order, so each key has its own slot, which isn't used by keys assigned later.
+    fn try_build(hashes: &[u64], seed: u64, block_length: u32) -> Option<Vec<u8>> {
+        let size = block_length as usize * 3;
+        let mut counts = vec![0_u32; size];
+        let mut xors = vec![0_u64; size];
+        for &hash in hashes {
+            let hash = mix(hash, seed);
+            for slot in slots(hash, block_length) {
+                counts[slot] += 1;
+                xors[slot] ^= hash;
+            }
+        }
+        let mut queue: Vec<_> = (0..size).filter(|&slot| counts[slot] == 1).collect();
+        let mut stack = Vec::with_capacity(hashes.len());
+        while let Some(slot) = queue.pop() {
+            if counts[slot] != 1 {
+                continue;
+            }
+            let hash = xors[slot];
+            stack.push((hash, slot));
+            for other in slots(hash, block_length) {
+                counts[other] -= 1;
+                xors[other] ^= hash;
+                if counts[other] == 1 {
+                    queue.push(other);
+                }
+            }
+        }
+        if stack.len() != hashes.len() {
+            return None;
+        }
+        let mut fingerprints = vec![0_u8; size];
+        for &(hash, slot) in stack.iter().rev() {
+            let [a, b, c] = slots(hash, block_length);
+            fingerprints[slot] =
+                fingerprint(hash) ^ fingerprints[a] ^ fingerprints[b] ^ fingerprints[c];
+        }
+        Some(fingerprints)
+    }
+
+    /// Check if key may be in the set.
+    pub fn contains(&self, item: impl AsRef<[u8]>) -> FilterResult {
+        if self.outdated || self.fingerprints.is_empty() {
+            return FilterResult::NeedAdditionalCheck;
+        }
+        let hash = mix(key_hash(self.hash_algorithm, item.as_ref()), self.seed);
+        let [a, b, c] = slots(hash, self.block_length);
+        if fingerprint(hash) == self.fingerprints[a] ^ self.fingerprints[b] ^ self.fingerprints[c] {
+            FilterResult::NeedAdditionalCheck
+        } else {
+            FilterResult::NotContains
+        }
+    }
+
+    /// Get amount of memory allocated for filter
+    pub fn memory_allocated(&self) -> usize {
+        self.fingerprints.capacity()
+    }
+}
+
+#[async_trait::async_trait]
+impl<K> FilterTrait<K> for XorFilter
+where
+    K: AsRef<[u8]> + Sync + Send,
+{
+    fn add(&mut self, _key: &K) {
+        self.outdated = true;
+    }
+
+    fn contains_fast(&self, key: &K) -> FilterResult {
+        self.contains(key)
+    }
+
+    fn checked_add_assign(&mut self, _other: &Self) -> bool {
+        false
+    }
+
+    fn memory_allocated(&self) -> usize {
+        self.memory_allocated()
+    }
+}
+
+fn slots(hash: u64, block_length: u32) -> [usize; 3] {
+    let block_length_usize = block_length as usize;
+    [
+        reduce(hash as u32, block_length),
+        reduce(hash.rotate_left(21) as u32, block_length) + block_length_usize,
+        reduce(hash.rotate_left(42) as u32, block_length) + 2 * block_length_usize,
+    ]
+}
+
+// maps hash onto [0, n) without division
+fn reduce(hash: u32, n: u32) -> usize {
+    ((u64::from(hash) * u64::from(n)) >> 32) as usize
+}
+
+fn fingerprint(hash: u64) -> u8 {
+    (hash ^ (hash >> 32)) as u8
+}
+
+// murmur3 finalizer
+fn mix(hash: u64, seed: u64) -> u64 {
+    let mut h = hash.wrapping_add(seed);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^ (h >> 33)
+}
+
+// splitmix64 step, so seeds sequence is the same on every construction
+fn next_seed(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xor_filter_no_false_negatives() {
+        let keys: Vec<_> = (0_u32..10_000).map(u32::to_le_bytes).collect();
+        let filter = XorFilter::from_keys(HashAlgorithm::default(), &keys);
+        assert!(keys
+            .iter()
+            .all(|key| filter.contains(key) == FilterResult::NeedAdditionalCheck));
+        let false_positives = (10_000_u32..110_000)
+            .filter(|key| filter.contains(key.to_le_bytes()) == FilterResult::NeedAdditionalCheck)
+            .count();
+        // expected rate is 1/256
+        assert!(false_positives < 1_000, "{}", false_positives);
+        assert!(filter.memory_allocated() < 10_000 * 10 / 8 + 64);
+    }
+}
@@ -62,6 +62,8 @@ extern crate ring;
 pub mod build_info;
 
 mod blob;
+/// Synchronous API for programs without async runtime.
+pub mod blocking;
 /// Types representing various errors that can occur in pearl.
 pub mod error;
 mod record;
@@ -69,13 +71,22 @@ mod storage;
 
 /// bloom filter for faster check record contains in blob
 pub mod filter;
-pub use filter::{Bloom, BloomDataProvider, BloomProvider, Config as BloomConfig, FilterResult};
+pub use filter::{
+    BlobFilterStats, Bloom, BloomDataProvider, BloomProvider, CombinedFilter,
+    Config as BloomConfig, FilterCheckStats, FilterKind, FilterResult, FilterStats, HashAlgorithm,
+    LevelFilterStats,
+};
 
-pub use blob::Entry;
+pub use blob::{Entry, IndexKind, NodeCacheStats};
+pub use bytes;
 pub use error::{Error, Kind as ErrorKind};
 pub use record::Meta;
 pub use rio;
-pub use storage::{Builder, Key, Storage};
+pub use storage::{
+    replication_channel, BlobDigest, Builder, ChannelReceiver, ChannelSender, Digest,
+    ExpectedVersion, Key, KeyRange, Position, ReadCacheAdmission, ReadCacheStats, ReadPolicy,
+    Replica, ReplicationChunk, ReplicationReceiver, ReplicationSender, Storage, WriteEvent,
+};
 
 mod prelude {
     use crc::{Crc, CRC_32_ISCSI};
@@ -87,8 +98,12 @@ mod prelude {
 
     pub(crate) use anyhow::{Context as ErrorContexts, Result};
     pub(crate) use bincode::{deserialize, serialize, serialize_into, serialized_size};
-    pub(crate) use blob::{self, Blob, IndexConfig};
-    pub(crate) use filter::{Bloom, BloomProvider, Config as BloomConfig, HierarchicalFilters};
+    pub(crate) use blob::{self, Blob, IndexConfig, NodeCache};
+    pub(crate) use bytes::Bytes;
+    pub(crate) use filter::{
+        Bloom, BloomProvider, CombinedFilter, Config as BloomConfig, HashAlgorithm,
+        HierarchicalFilters,
+    };
     pub(crate) use futures::{
         future,
         lock::Mutex,
@@ -6,7 +6,7 @@ pub(crate) const RECORD_MAGIC_BYTE: u64 = 0xacdc_bcde;
 pub struct Record {
     header: Header,
     meta: Meta,
-    data: Vec<u8>,
+    data: Bytes,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
@@ -68,16 +68,16 @@ impl Meta {
 }
 
 impl Record {
-    pub(crate) fn new(header: Header, meta: Meta, data: Vec<u8>) -> Self {
+    pub(crate) fn new(header: Header, meta: Meta, data: Bytes) -> Self {
         Self { header, meta, data }
     }
 
-    pub fn into_data(self) -> Vec<u8> {
+    pub fn into_data(self) -> Bytes {
         self.data
     }
 
     /// Creates new `Record` with provided data, key and meta.
-    pub fn create<K: Key>(key: &K, data: Vec<u8>, meta: Meta) -> bincode::Result<Self> {
+    pub fn create<K: Key>(key: &K, data: Bytes, meta: Meta) -> bincode::Result<Self> {
         let key = key.as_ref().to_vec();
         let meta_size = meta.serialized_size()?;
         let data_checksum = CRC32C.checksum(&data);
@@ -90,14 +90,32 @@ impl Record {
         &self.header
     }
 
+    /// Deserializes record from bytes produced by [`to_raw`] and validates it.
+    ///
+    /// [`to_raw`]: Record::to_raw
+    pub(crate) fn from_raw(raw: Bytes) -> Result<Self> {
+        let header = Header::from_raw(&raw).context("failed to deserialize record header")?;
+        let header_size = header.serialized_size() as usize;
+        let meta_end = header_size + header.meta_size() as usize;
+        if raw.len() as u64 != meta_end as u64 + header.data_size() {
+            let msg = format!("record size mismatch: {}b of {:?}", raw.len(), header);
+            return Err(Error::validation(ValidationErrorKind::RecordSize, msg).into());
+        }
+        let meta = Meta::from_raw(&raw[header_size..meta_end])?;
+        let data = raw.slice(meta_end..);
+        Self::new(header, meta, data).validate()
+    }
+
     /// # Description
     /// Serialize record to bytes
     pub fn to_raw(&self) -> bincode::Result<Vec<u8>> {
-        let mut buf = self.header.to_raw()?;
-        trace!("raw header: len: {}", buf.len());
+        let raw_header = self.header.to_raw()?;
+        trace!("raw header: len: {}", raw_header.len());
         let raw_meta = self.meta.to_raw()?;
-        buf.extend(&raw_meta);
-        buf.extend(&self.data);
+        let mut buf = Vec::with_capacity(raw_header.len() + raw_meta.len() + self.data.len());
+        buf.extend_from_slice(&raw_header);
+        buf.extend_from_slice(&raw_meta);
+        buf.extend_from_slice(&self.data);
         Ok(buf)
     }
 
@@ -107,22 +125,14 @@ impl Record {
     }
 
     pub(crate) fn validate(self) -> Result<Self> {
-        self.check_magic_byte()?;
+        self.header.check_magic_byte()?;
         self.check_data_checksum()?;
-        self.check_header_checksum()
+        self.header
+            .check_header_checksum()
             .with_context(|| "check header checksum failed")?;
         Ok(self)
     }
 
-    fn check_magic_byte(&self) -> Result<()> {
-        if self.header().magic_byte == RECORD_MAGIC_BYTE {
-            Ok(())
-        } else {
-            let param = ValidationErrorKind::RecordMagicByte;
-            Err(Error::validation(param, "wrong magic byte").into())
-        }
-    }
-
     fn check_data_checksum(&self) -> Result<()> {
         let calc_crc = CRC32C.checksum(&self.data);
         if calc_crc == self.header.data_checksum {
@@ -139,26 +149,6 @@ impl Record {
         }
     }
 
-    fn check_header_checksum(&self) -> Result<()> {
-        let mut header = self.header.clone();
-        header.header_checksum = 0;
-        let calc_crc = header
-            .crc32()
-            .with_context(|| "header checksum calculation failed")?;
-        if calc_crc == self.header.header_checksum {
-            Ok(())
-        } else {
-            let cause = format!(
-                "wrong header checksum {} vs {}",
-                calc_crc, self.header.header_checksum
-            );
-            let param = ValidationErrorKind::RecordHeaderChecksum;
-            let e = Error::validation(param, cause);
-            error!("{:#?}", e);
-            Err(Error::from(e).into())
-        }
-    }
-
     pub fn meta(&self) -> &Meta {
         &self.meta
     }
@@ -221,6 +211,21 @@ impl Header {
         self.blob_offset
     }
 
+    #[inline]
+    pub(crate) const fn created(&self) -> u64 {
+        self.created
+    }
+
+    #[inline]
+    pub(crate) const fn data_checksum(&self) -> u32 {
+        self.data_checksum
+    }
+
+    #[inline]
+    pub(crate) const fn header_checksum(&self) -> u32 {
+        self.header_checksum
+    }
+
     #[inline]
     pub fn key(&self) -> &[u8] {
         &self.key
@@ -236,6 +241,35 @@ impl Header {
         bincode::serialized_size(&self).expect("calc record serialized size")
     }
 
+    pub(crate) fn check_magic_byte(&self) -> Result<()> {
+        if self.magic_byte == RECORD_MAGIC_BYTE {
+            Ok(())
+        } else {
+            let param = ValidationErrorKind::RecordMagicByte;
+            Err(Error::validation(param, "wrong magic byte").into())
+        }
+    }
+
+    pub(crate) fn check_header_checksum(&self) -> Result<()> {
+        let mut header = self.clone();
+        header.header_checksum = 0;
+        let calc_crc = header
+            .crc32()
+            .with_context(|| "header checksum calculation failed")?;
+        if calc_crc == self.header_checksum {
+            Ok(())
+        } else {
+            let cause = format!(
+                "wrong header checksum {} vs {}",
+                calc_crc, self.header_checksum
+            );
+            let param = ValidationErrorKind::RecordHeaderChecksum;
+            let e = Error::validation(param, cause);
+            error!("{:#?}", e);
+            Err(Error::from(e).into())
+        }
+    }
+
     fn update_checksum(&mut self) -> bincode::Result<()> {
         self.header_checksum = 0;
         self.header_checksum = self.crc32()?;
@@ -49,6 +49,24 @@ impl Builder {
     /// # Errors
     /// Return error if some of the required params is missed or wrong
     pub fn build<K: Key + 'static>(self) -> Result<Storage<K>> {
+        self.check_params()?;
+        Ok(Storage::new(self.config, self.ioring))
+    }
+
+    // namespace shares observer worker, dump semaphore and io_uring with its parent storage
+    pub(crate) fn build_namespace<K: Key + 'static>(
+        mut self,
+        work_dir: PathBuf,
+        ioring: Option<Rio>,
+        observer: &Observer,
+    ) -> Result<Storage<K>> {
+        self.config.set_work_dir(work_dir);
+        self.config.set_dump_sem(observer.get_dump_sem());
+        self.check_params()?;
+        Ok(Storage::new_namespace(self.config, ioring, observer))
+    }
+
+    fn check_params(&self) -> Result<()> {
         let mut error_params = String::new();
         if self.config.work_dir().is_none() {
             error_params.push_str("> work_dir\n");
@@ -65,7 +83,7 @@ impl Builder {
             error_params.push_str("> blob_file_name_prefix\n");
         }
         if error_params.is_empty() {
-            Ok(Storage::new(self.config, self.ioring))
+            Ok(())
         } else {
             error!("{}", error_params);
             Err(Error::uninitialized().into())
@@ -164,6 +182,18 @@ impl Builder {
         self
     }
 
+    /// [Optional]
+    /// Enables memory mapped reads from closed blobs and index files.
+    /// If mapping of a file fails, reads from it fall back to regular `read_at`.
+    /// Default value is `false`
+    #[must_use]
+    pub fn enable_mmap(mut self) -> Self {
+        let mut index_config = self.config.index();
+        index_config.mmap = true;
+        self.config.set_index(index_config);
+        self
+    }
+
     /// [Optional]
     /// Sets whether to create work directory if its missing on storage initialization.
     /// Default value is `true`
@@ -186,4 +216,134 @@ impl Builder {
         self.config.set_bloom_filter_group_size(size);
         self
     }
+
+    /// [Optional]
+    /// Enables cache of b+-tree index nodes, shared by all blobs of the storage.
+    /// Cached nodes occupy at most `limit` bytes, rarely accessed nodes are evicted first.
+    /// Cache is disabled by default
+    #[must_use]
+    pub fn node_cache_memory_limit(mut self, limit: usize) -> Self {
+        let mut index_config = self.config.index();
+        index_config.node_cache = Some(NodeCache::new(limit));
+        self.config.set_index(index_config);
+        self
+    }
+
+    /// [Optional]
+    /// Enables cache of values read by [`Storage::read`], which holds at most `limit` bytes.
+    /// `admission` defines which of the read values get into the cache.
+    /// Cached value is dropped on every write with the same key.
+    /// Cache is disabled by default
+    ///
+    /// [`Storage::read`]: struct.Storage.html#method.read
+    #[must_use]
+    pub fn read_cache(mut self, limit: usize, admission: ReadCacheAdmission) -> Self {
+        self.config.set_read_cache(ReadCache::new(limit, admission));
+        self
+    }
+
+    /// [Optional]
+    /// Sets the number of write events, which are buffered for each subscriber of
+    /// [`Storage::subscribe`]. Subscriber, which falls behind more, misses events and
+    /// gets [`ErrorKind::SubscriberLagged`] error.
+    /// Default value is 1024
+    ///
+    /// [`Storage::subscribe`]: struct.Storage.html#method.subscribe
+    /// [`ErrorKind::SubscriberLagged`]: enum.ErrorKind.html#variant.SubscriberLagged
+    #[must_use]
+    pub fn change_feed_capacity(mut self, capacity: usize) -> Self {
+        if capacity > 0 {
+            self.config.set_change_feed_capacity(capacity);
+        } else {
+            error!("zero change feed capacity is not allowed");
+        }
+        self
+    }
+
+    /// [Optional]
+    /// Makes storage a replica, which is filled only by [`Replica`]. Writes to such storage
+    /// fail, blobs are not rotated by size limits, they follow blobs of the primary storage.
+    /// Storage is not a replica by default
+    ///
+    /// [`Replica`]: struct.Replica.html
+    #[must_use]
+    pub fn replica(mut self) -> Self {
+        self.config.set_replica(true);
+        self
+    }
+
+    /// [Optional]
+    /// Keeps memory allocated by bloom filters of the storage under `limit` bytes.
+    /// The limit is applied on init and then periodically by the observer.
+    /// When the limit is exceeded, filters of the blobs with the least lookups are offloaded
+    /// to disk, they are loaded back, when the blob is looked up often again and the limit
+    /// allows it. Filters of the active blob and of the blobs with not yet dumped index
+    /// can't be offloaded.
+    /// Filter memory is not limited by default
+    #[must_use]
+    pub fn filter_memory_limit(mut self, limit: usize) -> Self {
+        self.config.set_filter_memory_limit(limit);
+        self
+    }
+
+    /// [Optional]
+    /// Sets whether to save filters of full blob groups to `.group_filter` files, so they
+    /// aren't rebuilt from filters of the blobs on startup.
+    /// Default value is `true`
+    #[must_use]
+    pub fn save_group_filters(mut self, save: bool) -> Self {
+        self.config.set_save_group_filters(save);
+        self
+    }
+
+    /// [Optional]
+    /// Adds secondary index by values of meta attribute `name`, which allows to find records
+    /// with [`Storage::find_by_meta`]. Index of the active blob is kept in memory and saved
+    /// to `.meta_index` file, when the blob is closed. Indexes of closed blobs are read from
+    /// their files on lookup.
+    /// No attributes are indexed by default
+    ///
+    /// [`Storage::find_by_meta`]: struct.Storage.html#method.find_by_meta
+    #[must_use]
+    pub fn meta_index(mut self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        let mut index_config = self.config.index();
+        if !index_config.meta_indexes.contains(&name) {
+            index_config.meta_indexes.push(name);
+        }
+        self.config.set_index(index_config);
+        self
+    }
+
+    /// [Optional]
+    /// Sets prefix extractor, which takes first `len` bytes of keys. Index of each closed
+    /// blob gets bloom filter of key prefixes, which is saved in index file next to the bloom
+    /// filter of keys and is used by [`Storage::scan_prefix`] to skip blobs and groups of
+    /// blobs without keys with the prefix. Requires filter config, keys must be ordered by
+    /// their bytes.
+    /// Prefix filters are disabled by default
+    ///
+    /// [`Storage::scan_prefix`]: struct.Storage.html#method.scan_prefix
+    #[must_use]
+    pub fn key_prefix_len(mut self, len: usize) -> Self {
+        if len > 0 {
+            let mut index_config = self.config.index();
+            index_config.prefix_len = Some(len);
+            self.config.set_index(index_config);
+        } else {
+            error!("zero key prefix length is not allowed");
+        }
+        self
+    }
+
+    /// [Optional]
+    /// Sets layout of index files, which are created for closed blobs.
+    /// Default value is [`IndexKind::BPTree`]
+    #[must_use]
+    pub fn set_index_kind(mut self, kind: IndexKind) -> Self {
+        let mut index_config = self.config.index();
+        index_config.index_kind = kind;
+        self.config.set_index(index_config);
+        self
+    }
 }
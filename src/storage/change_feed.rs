@@ -0,0 +1,109 @@
+use super::prelude::*;
+use futures::stream::{self, Stream};
+use std::sync::RwLock;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Position of a record in the storage: id of the blob and offset of the record in it.
+/// Positions are ordered in the order of writing.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+pub struct Position {
+    /// Id of the blob
+    pub blob_id: usize,
+    /// Offset of the record in the blob
+    pub offset: u64,
+}
+
+impl Position {
+    /// Creates position of the record at `offset` in the blob with id `blob_id`.
+    #[must_use]
+    pub const fn new(blob_id: usize, offset: u64) -> Self {
+        Self { blob_id, offset }
+    }
+}
+
+/// Event of the storage change feed, describes written record.
+#[derive(Debug, Clone)]
+pub struct WriteEvent<K> {
+    key: K,
+    entry: Entry,
+}
+
+impl<K: Key> WriteEvent<K> {
+    pub(crate) fn from_entry(entry: Entry) -> Self {
+        let key = entry.header().key().to_vec().into();
+        Self { key, entry }
+    }
+
+    /// Returns key of the written record.
+    #[must_use]
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Returns entry of the written record with its header, it can be used to load data.
+    #[must_use]
+    pub fn entry(&self) -> &Entry {
+        &self.entry
+    }
+
+    /// Returns position of the written record.
+    #[must_use]
+    pub fn position(&self) -> Position {
+        Position::new(self.entry.blob_id(), self.entry.blob_offset())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ChangeFeed<K> {
+    // `None` after the storage is closed
+    sender: Arc<RwLock<Option<broadcast::Sender<WriteEvent<K>>>>>,
+}
+
+impl<K: Key + 'static> ChangeFeed<K> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self {
+            sender: Arc::new(RwLock::new(Some(sender))),
+        }
+    }
+
+    // entry is created only if there are subscribers
+    pub(crate) fn publish(&self, entry: impl FnOnce() -> Entry) {
+        let sender = self.sender.read().expect("change feed lock poisoned");
+        if let Some(sender) = sender.as_ref().filter(|sender| sender.receiver_count() > 0) {
+            // fails only if all subscribers are gone after the check
+            let _ = sender.send(WriteEvent::from_entry(entry()));
+        }
+    }
+
+    /// Returns empty stream, if feed is closed.
+    pub(crate) fn subscribe(&self) -> impl Stream<Item = Result<WriteEvent<K>>> {
+        let receiver = self
+            .sender
+            .read()
+            .expect("change feed lock poisoned")
+            .as_ref()
+            .map(broadcast::Sender::subscribe);
+        stream::unfold(receiver, |receiver| async move {
+            let mut receiver = receiver?;
+            match receiver.recv().await {
+                Ok(event) => Some((Ok(event), Some(receiver))),
+                Err(RecvError::Lagged(skipped)) => Some((
+                    Err(Error::subscriber_lagged(skipped).into()),
+                    Some(receiver),
+                )),
+                Err(RecvError::Closed) => None,
+            }
+        })
+    }
+
+    /// Ends streams of all subscribers after they receive already published events.
+    pub(crate) fn close(&self) {
+        self.sender
+            .write()
+            .expect("change feed lock poisoned")
+            .take();
+    }
+}
@@ -14,6 +14,11 @@ pub(crate) struct Config {
     dump_sem: Arc<Semaphore>,
     corrupted_dir_name: String,
     bloom_filter_group_size: usize,
+    read_cache: Option<ReadCache>,
+    change_feed_capacity: usize,
+    replica: bool,
+    filter_memory_limit: Option<usize>,
+    save_group_filters: bool,
 }
 
 // Getters
@@ -76,6 +81,31 @@ impl Config {
     pub fn bloom_filter_group_size(&self) -> usize {
         self.bloom_filter_group_size
     }
+
+    #[inline]
+    pub fn read_cache(&self) -> Option<&ReadCache> {
+        self.read_cache.as_ref()
+    }
+
+    #[inline]
+    pub fn change_feed_capacity(&self) -> usize {
+        self.change_feed_capacity
+    }
+
+    #[inline]
+    pub fn replica(&self) -> bool {
+        self.replica
+    }
+
+    #[inline]
+    pub fn filter_memory_limit(&self) -> Option<usize> {
+        self.filter_memory_limit
+    }
+
+    #[inline]
+    pub fn save_group_filters(&self) -> bool {
+        self.save_group_filters
+    }
 }
 
 //Setters
@@ -123,6 +153,26 @@ impl Config {
     pub fn set_bloom_filter_group_size(&mut self, bloom_filter_group_size: usize) {
         self.bloom_filter_group_size = bloom_filter_group_size
     }
+
+    pub fn set_read_cache(&mut self, read_cache: ReadCache) {
+        self.read_cache = Some(read_cache);
+    }
+
+    pub fn set_change_feed_capacity(&mut self, capacity: usize) {
+        self.change_feed_capacity = capacity;
+    }
+
+    pub fn set_replica(&mut self, replica: bool) {
+        self.replica = replica;
+    }
+
+    pub fn set_filter_memory_limit(&mut self, limit: usize) {
+        self.filter_memory_limit = Some(limit);
+    }
+
+    pub fn set_save_group_filters(&mut self, save: bool) {
+        self.save_group_filters = save;
+    }
 }
 
 // Impl Traits
@@ -141,6 +191,11 @@ impl Default for Config {
             dump_sem: Arc::new(Semaphore::new(1)),
             corrupted_dir_name: "corrupted".into(),
             bloom_filter_group_size: 8,
+            read_cache: None,
+            change_feed_capacity: 1024,
+            replica: false,
+            filter_memory_limit: None,
+            save_group_filters: true,
         }
     }
 }
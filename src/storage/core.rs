@@ -1,7 +1,12 @@
-use crate::error::ValidationErrorKind;
+use crate::{blob::RecordsTail, error::ValidationErrorKind, filter::FilterStats};
 
+use super::group_filters::{GroupFilter, GroupFilterFiles};
 use super::prelude::*;
-use futures::stream::FuturesOrdered;
+use futures::stream::{self, FuturesOrdered, Stream};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeSet, HashSet},
+};
 use tokio::fs::{create_dir, create_dir_all};
 
 const BLOB_FILE_EXTENSION: &str = "blob";
@@ -35,7 +40,7 @@ const BLOB_FILE_EXTENSION: &str = "blob";
 #[derive(Debug, Clone)]
 pub struct Storage<K: Key> {
     pub(crate) inner: Inner<K>,
-    observer: Observer<K>,
+    observer: Observer,
 }
 
 #[derive(Debug, Clone)]
@@ -44,12 +49,17 @@ pub(crate) struct Inner<K: Key> {
     pub(crate) safe: Arc<RwLock<Safe<K>>>,
     next_blob_id: Arc<AtomicUsize>,
     pub(crate) ioring: Option<Rio>,
+    pub(crate) namespaces: Namespaces,
+    pub(crate) change_feed: ChangeFeed<K>,
 }
 
+/// Closed blobs with hierarchy of their filters
+pub(crate) type BlobFilters<K> = HierarchicalFilters<K, CombinedFilter<K>, Blob<K>>;
+
 #[derive(Debug)]
 pub(crate) struct Safe<K: Key> {
     pub(crate) active_blob: Option<Box<Blob<K>>>,
-    pub(crate) blobs: Arc<RwLock<HierarchicalFilters<K, Bloom, Blob<K>>>>,
+    pub(crate) blobs: Arc<RwLock<BlobFilters<K>>>,
 }
 
 async fn work_dir_content(wd: &Path) -> Result<Option<Vec<DirEntry>>> {
@@ -83,6 +93,12 @@ impl<K: Key + 'static> Storage<K> {
         Self { inner, observer }
     }
 
+    pub(crate) fn new_namespace(config: Config, ioring: Option<Rio>, parent: &Observer) -> Self {
+        let inner = Inner::new(config, ioring);
+        let observer = parent.attach(inner.clone());
+        Self { inner, observer }
+    }
+
     /// [`init()`] used to prepare all environment to further work.
     ///
     /// Storage works in directory provided to builder. If directory don't exist,
@@ -133,6 +149,12 @@ impl<K: Key + 'static> Storage<K> {
             self.init_new().await?
         };
         trace!("new storage initialized");
+        if let Some(limit) = self.inner.config.filter_memory_limit() {
+            self.inner
+                .limit_filter_memory(limit)
+                .await
+                .context("failed to limit filter memory")?;
+        }
         self.launch_observer();
         trace!("observer started");
         Ok(())
@@ -231,8 +253,9 @@ impl<K: Key + 'static> Storage<K> {
     /// Fails with the same errors as [`write_with`]
     ///
     /// [`write_with`]: Storage::write_with
-    pub async fn write(&self, key: impl AsRef<K>, value: Vec<u8>) -> Result<()> {
-        self.write_with_optional_meta(key, value, None).await
+    pub async fn write(&self, key: impl AsRef<K>, value: impl Into<Bytes>) -> Result<()> {
+        self.write_with_condition(key, value, None, WriteCondition::Any)
+            .await
     }
 
     /// Similar to [`write`] but with metadata
@@ -248,38 +271,118 @@ impl<K: Key + 'static> Storage<K> {
     /// ```
     /// # Errors
     /// Fails if duplicates are not allowed and record already exists.
-    pub async fn write_with(&self, key: impl AsRef<K>, value: Vec<u8>, meta: Meta) -> Result<()> {
-        self.write_with_optional_meta(key, value, Some(meta)).await
+    pub async fn write_with(
+        &self,
+        key: impl AsRef<K>,
+        value: impl Into<Bytes>,
+        meta: Meta,
+    ) -> Result<()> {
+        self.write_with_condition(key, value, Some(meta), WriteCondition::Any)
+            .await
+    }
+
+    /// Writes data only if there is no record with the same key, the check and the write
+    /// are performed atomically.
+    /// # Errors
+    /// Fails with [`ErrorKind::RecordExists`] if the key is already written, and with the same
+    /// errors as [`write_with`].
+    ///
+    /// [`ErrorKind::RecordExists`]: enum.ErrorKind.html#variant.RecordExists
+    /// [`write_with`]: Storage::write_with
+    pub async fn write_if_absent(&self, key: impl AsRef<K>, value: impl Into<Bytes>) -> Result<()> {
+        self.write_with_condition(key, value, None, WriteCondition::Absent)
+            .await
+    }
+
+    /// Writes data only if the latest record with the same key matches `expected` version,
+    /// the check and the write are performed atomically.
+    /// # Errors
+    /// Same as [`write_with_if_version`]
+    ///
+    /// [`write_with_if_version`]: Storage::write_with_if_version
+    pub async fn write_if_version(
+        &self,
+        key: impl AsRef<K>,
+        value: impl Into<Bytes>,
+        expected: ExpectedVersion,
+    ) -> Result<()> {
+        self.write_with_condition(key, value, None, WriteCondition::Version(expected))
+            .await
     }
 
-    async fn write_with_optional_meta(
+    /// Similar to [`write_if_version`] but with metadata
+    /// # Errors
+    /// Fails with [`ErrorKind::RecordNotFound`] if there is no record with the key, with
+    /// [`ErrorKind::RecordExists`] if the latest record doesn't match `expected` version, and
+    /// with the same errors as [`write_with`].
+    ///
+    /// [`ErrorKind::RecordNotFound`]: enum.ErrorKind.html#variant.RecordNotFound
+    /// [`ErrorKind::RecordExists`]: enum.ErrorKind.html#variant.RecordExists
+    /// [`write_if_version`]: Storage::write_if_version
+    /// [`write_with`]: Storage::write_with
+    pub async fn write_with_if_version(
         &self,
         key: impl AsRef<K>,
-        value: Vec<u8>,
+        value: impl Into<Bytes>,
+        meta: Meta,
+        expected: ExpectedVersion,
+    ) -> Result<()> {
+        self.write_with_condition(key, value, Some(meta), WriteCondition::Version(expected))
+            .await
+    }
+
+    async fn write_with_condition(
+        &self,
+        key: impl AsRef<K>,
+        value: impl Into<Bytes>,
         meta: Option<Meta>,
+        condition: WriteCondition,
     ) -> Result<()> {
+        if self.inner.config.replica() {
+            let msg = "storage is a replica, it accepts only replicated records".to_owned();
+            return Err(Error::replication(msg).into());
+        }
         let key = key.as_ref();
-        debug!("storage write with {:?}, {}b, {:?}", key, value.len(), meta);
+        let value = value.into();
+        debug!(
+            "storage write with {:?}, {}b, {:?}, {:?}",
+            key,
+            value.len(),
+            meta,
+            condition
+        );
         // if active blob is set, this function will only check this fact and return false
         if self.try_create_active_blob().await.is_ok() {
             info!("Active blob was set during write operation");
         }
-        if !self.inner.config.allow_duplicates() && self.contains_with(key, meta.as_ref()).await? {
-            warn!(
-                "record with key {:?} and meta {:?} exists",
-                key.as_ref(),
-                meta
-            );
-            return Ok(());
-        }
+        let with_meta = meta.is_some();
         let record = Record::create(key, value, meta.unwrap_or_default())
             .with_context(|| "storage write with record creation failed")?;
         let mut safe = self.inner.safe.write().await;
+        // conditions are checked under the write lock, so no other write can get between the
+        // check and the write
+        match condition {
+            WriteCondition::Any => {
+                let meta = Some(record.meta()).filter(|_| with_meta);
+                if !self.inner.config.allow_duplicates()
+                    && Self::contains_in(&safe, key, meta).await?
+                {
+                    warn!("record with key {:?} and meta {:?} exists", key, meta);
+                    return Ok(());
+                }
+            }
+            WriteCondition::Absent => {
+                if Self::contains_in(&safe, key, None).await? {
+                    return Err(Error::record_exists().into());
+                }
+            }
+            WriteCondition::Version(expected) => Self::check_version(&safe, key, &expected).await?,
+        }
         let blob = safe
             .active_blob
             .as_mut()
             .ok_or_else(Error::active_blob_not_set)?;
-        blob.write(record).await.or_else(|err| {
+        let header = blob.write(record).await.or_else(|err| -> Result<_> {
             let e = err.downcast::<Error>()?;
             if let ErrorKind::FileUnavailable(kind) = e.kind() {
                 let work_dir = self
@@ -291,9 +394,18 @@ impl<K: Key + 'static> Storage<K> {
             } else {
                 Err(e.into())
             }
-        })
+        })?;
+        if let Some(cache) = self.inner.config.read_cache() {
+            cache.invalidate(key.as_ref());
+        }
+        self.inner
+            .change_feed
+            .publish(|| blob.header_to_entry(header));
+        Ok(())
     }
-    /// Reads the first found data matching given key.
+
+    /// Reads data of the latest written record with given key, i.e. the record from the blob
+    /// with the greatest id and with the greatest offset in it.
     /// # Examples
     /// ```no-run
     /// async fn read_data() {
@@ -307,12 +419,12 @@ impl<K: Key + 'static> Storage<K> {
     /// [`Error::RecordNotFound`]: enum.Error.html#RecordNotFound
     /// [`read_with`]: Storage::read_with
     #[inline]
-    pub async fn read(&self, key: impl AsRef<K>) -> Result<Vec<u8>> {
+    pub async fn read(&self, key: impl AsRef<K>) -> Result<Bytes> {
         let key = key.as_ref();
         debug!("storage read {:?}", key);
         self.read_with_optional_meta(key, None).await
     }
-    /// Reads data matching given key and metadata
+    /// Reads data of the latest record matching given key and metadata
     /// # Examples
     /// ```no-run
     /// async fn read_data() {
@@ -327,7 +439,7 @@ impl<K: Key + 'static> Storage<K> {
     ///
     /// [`Error::RecordNotFound`]: enum.Error.html#RecordNotFound
     #[inline]
-    pub async fn read_with(&self, key: impl AsRef<K>, meta: &Meta) -> Result<Vec<u8>> {
+    pub async fn read_with(&self, key: impl AsRef<K>, meta: &Meta) -> Result<Bytes> {
         let key = key.as_ref();
         debug!("storage read with {:?}", key);
         self.read_with_optional_meta(key, Some(meta))
@@ -335,10 +447,19 @@ impl<K: Key + 'static> Storage<K> {
             .with_context(|| "read with optional meta failed")
     }
 
-    /// Returns entries with matching key
+    /// Returns entries with matching key in order of writing, the latest goes first.
+    /// Order is defined by position of records in blobs, not by their timestamps.
     /// # Errors
     /// Fails after any disk IO errors.
     pub async fn read_all(&self, key: impl AsRef<K>) -> Result<Vec<Entry>> {
+        self.read_entries(key, ReadPolicy::All).await
+    }
+
+    /// Returns entries with matching key, selected according to `policy`.
+    /// For [`ReadPolicy::Latest`] and [`ReadPolicy::Oldest`] result contains at most one entry.
+    /// # Errors
+    /// Fails after any disk IO errors.
+    pub async fn read_entries(&self, key: impl AsRef<K>, policy: ReadPolicy) -> Result<Vec<Entry>> {
         let key = key.as_ref();
         let mut all_entries = Vec::new();
         let safe = self.inner.safe.read().await;
@@ -367,11 +488,318 @@ impl<K: Key + 'static> Storage<K> {
             })
             .await?;
         debug!("storage core read all total {} entries", all_entries.len());
-        Ok(all_entries)
+        Self::sort_latest_first(&mut all_entries);
+        let mut entries = all_entries.into_iter();
+        let entries = match policy {
+            ReadPolicy::Latest => entries.next().into_iter().collect(),
+            ReadPolicy::Oldest => entries.last().into_iter().collect(),
+            ReadPolicy::All => entries.collect(),
+        };
+        Ok(entries)
+    }
+
+    /// Returns keys and entries of records, which have `value` of meta attribute `name`,
+    /// the latest goes first. Attribute must be indexed, see [`Builder::meta_index`].
+    /// # Errors
+    /// Fails if attribute is not indexed.
+    ///
+    /// [`Builder::meta_index`]: struct.Builder.html#method.meta_index
+    pub async fn find_by_meta(
+        &self,
+        name: &str,
+        value: impl AsRef<[u8]>,
+    ) -> Result<Vec<(K, Entry)>> {
+        let value = value.as_ref();
+        if !self
+            .inner
+            .config
+            .index()
+            .meta_indexes
+            .iter()
+            .any(|n| n == name)
+        {
+            let msg = format!("meta attribute {} is not indexed", name);
+            return Err(Error::from(ErrorKind::Index(msg)).into());
+        }
+        let mut all_entries = Vec::new();
+        let safe = self.inner.safe.read().await;
+        if let Some(active_blob) = &safe.active_blob {
+            all_entries.extend(active_blob.find_by_meta(name, value).await?);
+        }
+        let blobs = safe.blobs.read().await;
+        for blob in blobs.iter() {
+            all_entries.extend(blob.find_by_meta(name, value).await?);
+        }
+        debug!("storage find by meta {} entries", all_entries.len());
+        Self::sort_latest_first(&mut all_entries);
+        let entries = all_entries
+            .into_iter()
+            .map(|entry| (entry.header().key().to_vec().into(), entry))
+            .collect();
+        Ok(entries)
+    }
+
+    /// Returns sorted unique keys, which start with `prefix`. Blobs and groups of blobs,
+    /// which range or prefix filters reject the prefix, are skipped, so indexes only of
+    /// the remaining blobs are read. Prefix filters are enabled with
+    /// [`Builder::key_prefix_len`]. Range filters and sorted indexes are used only for keys
+    /// ordered by their bytes, see [`Key::BYTE_ORDERED`], otherwise all keys of the remaining
+    /// blobs are checked.
+    /// # Errors
+    /// Fails if index of some blob can't be read.
+    ///
+    /// [`Builder::key_prefix_len`]: struct.Builder.html#method.key_prefix_len
+    pub async fn scan_prefix(&self, prefix: impl AsRef<[u8]>) -> Result<Vec<K>> {
+        let prefix = prefix.as_ref();
+        let mut keys = BTreeSet::new();
+        let safe = self.inner.safe.read().await;
+        if let Some(active_blob) = &safe.active_blob {
+            keys.extend(active_blob.keys_with_prefix(prefix).await?);
+        }
+        let blobs = safe.blobs.read().await;
+        for (_, leaf) in blobs.iter_possible_childs_by_prefix(prefix) {
+            keys.extend(leaf.data.keys_with_prefix(prefix).await?);
+        }
+        debug!("storage scan prefix {} keys", keys.len());
+        Ok(keys.into_iter().collect())
+    }
+
+    /// Returns stream of events of all successful writes made after the call.
+    /// Events go in order of writing. If subscriber falls behind more than
+    /// [`Builder::change_feed_capacity`] events, it gets [`ErrorKind::SubscriberLagged`]
+    /// error, skipped records can be replayed with [`tail_from`]. Stream ends when the
+    /// storage is closed.
+    ///
+    /// [`Builder::change_feed_capacity`]: struct.Builder.html#method.change_feed_capacity
+    /// [`ErrorKind::SubscriberLagged`]: enum.ErrorKind.html#variant.SubscriberLagged
+    /// [`tail_from`]: Storage::tail_from
+    pub fn subscribe(&self) -> impl Stream<Item = Result<WriteEvent<K>>> {
+        self.inner.change_feed.subscribe()
+    }
+
+    /// Replays records written at `position` and after it in order of writing, so consumer
+    /// can resume after restart from the position of the last processed record.
+    /// Only records written before the call are replayed. To follow the storage without
+    /// missing writes, call [`subscribe`] first and skip subscription events with positions
+    /// not greater than position of the last replayed record.
+    /// # Errors
+    /// Fails if blob header can't be serialized. Stream returns errors if `position`
+    /// doesn't point to the beginning of a record or because of IO errors
+    ///
+    /// [`subscribe`]: Storage::subscribe
+    pub async fn tail_from(
+        &self,
+        position: Position,
+    ) -> Result<impl Stream<Item = Result<WriteEvent<K>>>> {
+        let mut tails = Vec::new();
+        {
+            let safe = self.inner.safe.read().await;
+            let offset = |blob_id| {
+                if blob_id == position.blob_id {
+                    position.offset
+                } else {
+                    0
+                }
+            };
+            let blobs = safe.blobs.read().await;
+            for blob in blobs.iter().filter(|blob| blob.id() >= position.blob_id) {
+                tails.push(blob.records_from(offset(blob.id()))?);
+            }
+            if let Some(blob) = &safe.active_blob {
+                if blob.id() >= position.blob_id {
+                    tails.push(blob.records_from(offset(blob.id()))?);
+                }
+            }
+        }
+        tails.sort_by_key(RecordsTail::blob_id);
+        debug!("storage tail from {:?}, {} blobs", position, tails.len());
+        let entries = stream::iter(tails.into_iter().map(RecordsTail::entries));
+        let events = futures::StreamExt::flatten(entries).map_ok(WriteEvent::from_entry);
+        Ok(events)
+    }
+
+    /// Returns stream of raw records written at `from` position and after it, including
+    /// records written after the call, in order of writing. Chunks of the stream are applied
+    /// to the replica storage with [`Replica::apply`]. Stream fails with
+    /// [`ErrorKind::SubscriberLagged`], if it is consumed slower than records are written,
+    /// shipping can be restarted from [`Replica::position`] then.
+    /// # Errors
+    /// Fails if blob header can't be serialized
+    ///
+    /// [`Replica::apply`]: struct.Replica.html#method.apply
+    /// [`Replica::position`]: struct.Replica.html#method.position
+    /// [`ErrorKind::SubscriberLagged`]: enum.ErrorKind.html#variant.SubscriberLagged
+    pub async fn replication_stream(
+        &self,
+        from: Position,
+    ) -> Result<impl Stream<Item = Result<ReplicationChunk>>> {
+        // subscription goes first, so writes made during the replay are not missed
+        let live = self.subscribe();
+        let replay = self.tail_from(from).await?;
+        let mut last = None;
+        let chunks = replay
+            .chain(live)
+            .try_filter(move |event| {
+                let position = event.position();
+                let is_new = position >= from && last < Some(position);
+                if is_new {
+                    last = Some(position);
+                }
+                future::ready(is_new)
+            })
+            .and_then(|event| async move {
+                let record = event.entry().load_raw().await?;
+                Ok(ReplicationChunk::new(event.position(), record))
+            });
+        Ok(chunks)
+    }
+
+    /// Ships records written at `from` position and after it to the replica via `sender`,
+    /// see [`replication_stream`]. Returns only on errors.
+    /// # Errors
+    /// Fails if records can't be read, replica falls behind or `sender` fails
+    ///
+    /// [`replication_stream`]: Storage::replication_stream
+    pub async fn replicate(
+        &self,
+        from: Position,
+        sender: &mut impl ReplicationSender,
+    ) -> Result<()> {
+        let mut chunks = Box::pin(self.replication_stream(from).await?);
+        while let Some(chunk) = chunks.next().await {
+            sender.send(chunk?).await?;
+        }
+        Ok(())
+    }
+
+    // Writes record received from the primary storage at the same position. Active blob is
+    // replaced, when the first record of the next blob comes. Returns position after the
+    // written record.
+    pub(crate) async fn write_replicated(
+        &self,
+        position: Position,
+        record: Record,
+    ) -> Result<Position> {
+        let mut safe = self.inner.safe.write().await;
+        let active_id = safe.active_blob.as_ref().map(|blob| blob.id());
+        let rotate = match active_id {
+            Some(id) if id > position.blob_id => {
+                let msg = format!("blob {} is already closed on replica", position.blob_id);
+                return Err(Error::replication(msg).into());
+            }
+            Some(id) => id < position.blob_id,
+            None => true,
+        };
+        if rotate {
+            debug!("replica starts blob {}", position.blob_id);
+            let name = self.inner.blob_name(position.blob_id)?;
+            let config = self.inner.config.index();
+            let blob = Blob::open_new(name, self.inner.ioring.clone(), config)
+                .await?
+                .boxed();
+            self.inner.next_blob_id.store(position.blob_id + 1, ORD);
+            if let Some(old_active) = &safe.active_blob {
+                old_active.fsyncdata().await?;
+            }
+            safe.replace_active_blob(blob).await?;
+        }
+        let blob = safe
+            .active_blob
+            .as_mut()
+            .ok_or_else(Error::active_blob_not_set)?;
+        if blob.file_size() != position.offset {
+            let msg = format!(
+                "record at {:?} doesn't follow the end of blob at {}",
+                position,
+                blob.file_size()
+            );
+            return Err(Error::replication(msg).into());
+        }
+        let header = blob.write(record).await?;
+        if let Some(cache) = self.inner.config.read_cache() {
+            cache.invalidate(header.key());
+        }
+        self.inner
+            .change_feed
+            .publish(|| blob.header_to_entry(header));
+        let end = Position::new(blob.id(), blob.file_size());
+        drop(safe);
+        if rotate {
+            self.observer.try_dump_old_blob_indexes().await;
+        }
+        Ok(end)
     }
 
-    async fn read_with_optional_meta(&self, key: &K, meta: Option<&Meta>) -> Result<Vec<u8>> {
+    // position after the last written record
+    pub(crate) async fn end_position(&self) -> Position {
+        let safe = self.inner.safe.read().await;
+        if let Some(blob) = &safe.active_blob {
+            return Position::new(blob.id(), blob.file_size());
+        }
+        let blobs = safe.blobs.read().await;
+        blobs
+            .iter()
+            .map(|blob| Position::new(blob.id(), blob.file_size()))
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Builds [`Digest`] of the storage records, which can be compared with the digest of
+    /// another storage to find differing key ranges. Only indexes are read.
+    /// # Errors
+    /// Fails if index files can't be read
+    pub async fn digest(&self) -> Result<Digest> {
+        let mut digest = Digest::new();
+        let mut detached = Vec::new();
+        {
+            let safe = self.inner.safe.read().await;
+            let blobs = safe.blobs.read().await;
+            // index of the active blob is always in memory
+            for blob in safe.active_blob.as_deref().into_iter().chain(blobs.iter()) {
+                let id = blob.id();
+                match blob.detached_index() {
+                    Some(index) => detached.push((id, blob.name().to_string(), index)),
+                    None => {
+                        blob.for_each_header(|header| digest.push(id, header))
+                            .await?
+                    }
+                }
+            }
+        }
+        // index files are read after blobs are unlocked, so writes and blob closing aren't blocked
+        for (id, name, index) in detached {
+            index
+                .for_each_header(|header| digest.push(id, header))
+                .await
+                .with_context(|| format!("failed to read index of blob {}", name))?;
+        }
+        digest.build_tree();
+        Ok(digest)
+    }
+
+    // the latest written entry is the one from the blob with the greatest id and with the
+    // greatest offset in it
+    fn sort_latest_first(entries: &mut [Entry]) {
+        entries.sort_by_key(|entry| Reverse((entry.blob_id(), entry.blob_offset())));
+    }
+
+    async fn read_with_optional_meta(&self, key: &K, meta: Option<&Meta>) -> Result<Bytes> {
         debug!("storage read with optional meta {:?}, {:?}", key, meta);
+        // records with meta are not cached
+        match self.inner.config.read_cache().filter(|_| meta.is_none()) {
+            Some(cache) => match cache.get(key.as_ref()) {
+                Ok(data) => Ok(data),
+                Err(epoch) => {
+                    let data = self.read_from_blobs(key, meta).await?;
+                    cache.insert(key.as_ref(), data.clone(), epoch);
+                    Ok(data)
+                }
+            },
+            None => self.read_from_blobs(key, meta).await,
+        }
+    }
+
+    async fn read_from_blobs(&self, key: &K, meta: Option<&Meta>) -> Result<Bytes> {
         let safe = self.inner.safe.read().await;
         if let Some(ablob) = safe.active_blob.as_ref() {
             match ablob.read_any(key, meta, true).await {
@@ -385,7 +813,7 @@ impl<K: Key + 'static> Storage<K> {
         Self::get_any_data(&safe, key, meta).await
     }
 
-    async fn get_data_last(safe: &Safe<K>, key: &K, meta: Option<&Meta>) -> Result<Vec<u8>> {
+    async fn get_data_last(safe: &Safe<K>, key: &K, meta: Option<&Meta>) -> Result<Bytes> {
         let blobs = safe.blobs.read().await;
         let possible_blobs = blobs
             .iter_possible_childs_rev(key)
@@ -416,7 +844,7 @@ impl<K: Key + 'static> Storage<K> {
     }
 
     #[allow(dead_code)]
-    async fn get_data_any(safe: &Safe<K>, key: &K, meta: Option<&Meta>) -> Result<Vec<u8>> {
+    async fn get_data_any(safe: &Safe<K>, key: &K, meta: Option<&Meta>) -> Result<Bytes> {
         let blobs = safe.blobs.read().await;
         let stream: FuturesUnordered<_> = blobs
             .iter_possible_childs_rev(key)
@@ -430,17 +858,26 @@ impl<K: Key + 'static> Storage<K> {
             .with_context(|| "no results in closed blobs")
     }
 
-    async fn get_any_data(safe: &Safe<K>, key: &K, meta: Option<&Meta>) -> Result<Vec<u8>> {
+    async fn get_any_data(safe: &Safe<K>, key: &K, meta: Option<&Meta>) -> Result<Bytes> {
         Self::get_data_last(safe, key, meta).await
     }
 
-    /// Stop blob updater and release lock file
+    /// Stop blob updater and release lock file, namespaces of the storage are closed too
     /// # Errors
     /// Fails because of any IO errors
     pub async fn close(self) -> Result<()> {
+        let namespaces = std::mem::take(&mut *self.inner.namespaces.write().await);
+        let mut res = Ok(());
+        for (name, namespace) in namespaces {
+            res = res.and(
+                namespace
+                    .close()
+                    .await
+                    .with_context(|| format!("namespace {} close failed", name)),
+            );
+        }
         let mut safe = self.inner.safe.write().await;
         let active_blob = safe.active_blob.take();
-        let mut res = Ok(());
         if let Some(mut blob) = active_blob {
             res = res.and(
                 blob.dump()
@@ -449,9 +886,95 @@ impl<K: Key + 'static> Storage<K> {
                     .with_context(|| format!("blob {} dump failed", blob.name())),
             )
         }
+        if let Some(group_filter_files) = GroupFilterFiles::new(&self.inner.config, None) {
+            group_filter_files.save(&*safe.blobs.read().await).await;
+        }
+        self.inner.change_feed.close();
         res
     }
 
+    /// Creates namespace `name` with its own key space or opens existing one.
+    /// Namespace is a separate storage, it may have its own key type, blob size limits,
+    /// bloom filter and index settings. Its files are kept in `namespaces/<name>` subdirectory
+    /// of the work dir. Namespace shares observer worker, dump semaphore and io_uring with
+    /// this storage, so work dir, dump semaphore and io_uring set in `builder` are ignored.
+    /// Storage must be initialized.
+    /// # Errors
+    /// Fails if namespace is already opened, if name is empty or contains characters other
+    /// than ascii alphanumerics, `-` and `_`, if required params of `builder` are missed or
+    /// because of IO errors
+    pub async fn create_namespace<N: Key + 'static>(
+        &self,
+        name: &str,
+        builder: Builder,
+    ) -> Result<Storage<N>> {
+        check_name(name)?;
+        if self.observer.sender.is_none() {
+            return Err(Error::uninitialized().into());
+        }
+        let mut namespaces = self.inner.namespaces.write().await;
+        if namespaces.contains_key(name) {
+            let msg = format!("namespace {} already exists", name);
+            return Err(Error::namespace(msg).into());
+        }
+        let work_dir = self.namespace_dir(name)?;
+        let mut storage =
+            builder.build_namespace::<N>(work_dir, self.inner.ioring.clone(), &self.observer)?;
+        storage
+            .init()
+            .await
+            .with_context(|| format!("failed to init namespace {}", name))?;
+        namespaces.insert(name.to_owned(), Box::new(storage.clone()));
+        Ok(storage)
+    }
+
+    /// Returns opened namespace `name`.
+    /// Returns `None` if there is no such namespace or it was created with another key type.
+    pub async fn namespace<N: Key + 'static>(&self, name: &str) -> Option<Storage<N>> {
+        let namespaces = self.inner.namespaces.read().await;
+        namespaces
+            .get(name)?
+            .as_any()
+            .downcast_ref::<Storage<N>>()
+            .cloned()
+    }
+
+    /// Returns names of opened namespaces.
+    pub async fn namespaces(&self) -> Vec<String> {
+        self.inner.namespaces.read().await.keys().cloned().collect()
+    }
+
+    /// Closes namespace `name` and removes all its data.
+    /// Handles of the namespace must not be used after that.
+    /// # Errors
+    /// Fails if there is no opened namespace with such name or because of IO errors
+    pub async fn drop_namespace(&self, name: &str) -> Result<()> {
+        let namespace = self
+            .inner
+            .namespaces
+            .write()
+            .await
+            .remove(name)
+            .ok_or_else(|| Error::namespace(format!("namespace {} not found", name)))?;
+        namespace
+            .close()
+            .await
+            .with_context(|| format!("namespace {} close failed", name))?;
+        let work_dir = self.namespace_dir(name)?;
+        tokio::fs::remove_dir_all(&work_dir)
+            .await
+            .with_context(|| format!("failed to remove {}", work_dir.display()))
+    }
+
+    fn namespace_dir(&self, name: &str) -> Result<PathBuf> {
+        let work_dir = self
+            .inner
+            .config
+            .work_dir()
+            .ok_or_else(|| Error::from(ErrorKind::Uninitialized))?;
+        Ok(work_dir.join(NAMESPACES_DIR).join(name))
+    }
+
     /// `blob_count` returns exact number of closed blobs plus one active, if there is some.
     /// It locks on inner structure, so it much slower than `next_blob_id`.
     /// # Examples
@@ -482,6 +1005,83 @@ impl<K: Key + 'static> Storage<K> {
         }
     }
 
+    /// Returns hit/miss counters and memory usage of b+-tree node cache,
+    /// `None` if cache is disabled in [`Builder`].
+    ///
+    /// [`Builder`]: struct.Builder.html
+    #[must_use]
+    pub fn node_cache_stats(&self) -> Option<NodeCacheStats> {
+        self.inner
+            .config
+            .index()
+            .node_cache
+            .map(|cache| cache.stats())
+    }
+
+    /// Returns hit/miss counters and memory usage of the record read cache,
+    /// `None` if cache is disabled in [`Builder`].
+    ///
+    /// [`Builder`]: struct.Builder.html
+    #[must_use]
+    pub fn read_cache_stats(&self) -> Option<ReadCacheStats> {
+        self.inner.config.read_cache().map(ReadCache::stats)
+    }
+
+    /// Returns counters of key checks by filters of every blob and of every level of blob
+    /// groups hierarchy, with fill ratio and estimated false positive rate of bloom filters.
+    /// Checks are counted on reads and [`check_filters`] calls, keys, which passed filters
+    /// of the blob, but weren't found in its index, are counted as false positives.
+    ///
+    /// [`check_filters`]: Storage::check_filters
+    pub async fn filter_stats(&self) -> FilterStats {
+        let safe = self.inner.safe.read().await;
+        let blobs = safe.blobs.read().await;
+        let mut stats = FilterStats {
+            blobs: blobs.iter().map(Blob::filter_stats).collect(),
+            levels: blobs.level_stats(),
+        };
+        if let Some(blob) = &safe.active_blob {
+            stats.blobs.push(blob.filter_stats());
+        }
+        stats
+    }
+
+    /// Loads offloaded bloom filters of closed blobs back from their index files and
+    /// rebuilds filters of blob groups, which could be offloaded by [`offload_buffer`] too.
+    /// Index files are read without locking blobs, blobs and groups keep their filter stats.
+    /// Returns memory allocated by filters after reload.
+    /// If filter memory limit is set, filters of cold blobs are offloaded again by the
+    /// observer.
+    /// # Errors
+    /// Fails if some index file can't be read.
+    /// [`offload_buffer`]: BloomProvider::offload_buffer
+    pub async fn load_filters(&self) -> Result<usize> {
+        let (offloaded, blobs) = {
+            let safe = self.inner.safe.read().await;
+            let blobs = safe.blobs.read().await;
+            let offloaded: Vec<_> = blobs
+                .iter()
+                .filter(|blob| blob.is_filter_offloaded())
+                .filter_map(|blob| Some((blob.id(), blob.detached_index()?)))
+                .collect();
+            (offloaded, safe.blobs.clone())
+        };
+        let mut filters = HashMap::new();
+        for (id, index) in offloaded {
+            filters.insert(id, index.read_bloom().await?);
+        }
+        let mut blobs = blobs.write().await;
+        for blob in blobs.iter_mut() {
+            if let Some(filter) = filters.remove(&blob.id()) {
+                blob.restore_filter(filter);
+            }
+        }
+        let children = 0..blobs.len();
+        blobs.rebuild_filters(children).await;
+        drop(blobs);
+        Ok(self.filter_memory_allocated().await)
+    }
+
     /// Returns next blob ID. If pearl dir structure wasn't changed from the outside,
     /// returned number is equal to `blobs_count`. But this method doesn't require
     /// lock. So it is much faster than `blobs_count`.
@@ -526,11 +1126,23 @@ impl<K: Key + 'static> Storage<K> {
     async fn init_from_existing(&mut self, files: Vec<DirEntry>, with_active: bool) -> Result<()> {
         trace!("init from existing: {:#?}", files);
         let disk_access_sem = self.observer.get_dump_sem();
+        let group_filter_files =
+            GroupFilterFiles::new(&self.inner.config, self.inner.ioring.clone());
+        let saved_filters = match &group_filter_files {
+            Some(group_filter_files) => group_filter_files.load(&files).await,
+            None => BTreeMap::new(),
+        };
+        // filters of blobs from saved groups are loaded only if group filter is invalid
+        let lazy_filters = saved_filters
+            .values()
+            .flat_map(GroupFilter::blob_ids)
+            .collect();
         let mut blobs = Self::read_blobs(
             &files,
             self.inner.ioring.clone(),
             disk_access_sem,
             &self.inner.config,
+            &lazy_filters,
         )
         .await
         .context("failed to read blobs")?;
@@ -549,17 +1161,65 @@ impl<K: Key + 'static> Storage<K> {
             blob.dump().await?;
         }
 
+        let filters = self
+            .build_filters(blobs, saved_filters, group_filter_files.as_ref())
+            .await?;
+        if let Some(group_filter_files) = &group_filter_files {
+            group_filter_files.save(&filters).await;
+        }
         let mut safe = self.inner.safe.write().await;
         safe.active_blob = active_blob;
-        *safe.blobs.write().await =
-            HierarchicalFilters::from_vec(self.inner.config.bloom_filter_group_size(), 1, blobs)
-                .await;
+        *safe.blobs.write().await = filters;
         self.inner
             .next_blob_id
             .store(safe.max_id().await.map_or(0, |i| i + 1), ORD);
         Ok(())
     }
 
+    // Full groups of blobs with valid saved filters are added with these filters, so filters
+    // of their blobs aren't needed. Filters of other groups are rebuilt from blob filters.
+    async fn build_filters(
+        &self,
+        blobs: Vec<Blob<K>>,
+        mut saved_filters: BTreeMap<usize, GroupFilter>,
+        group_filter_files: Option<&GroupFilterFiles>,
+    ) -> Result<BlobFilters<K>> {
+        let group_size = self.inner.config.bloom_filter_group_size();
+        let mut filters = HierarchicalFilters::new(group_size, 1);
+        let mut blobs = blobs.into_iter();
+        loop {
+            let group: Vec<_> = blobs.by_ref().take(group_size.max(1)).collect();
+            let first_id = match group.first() {
+                Some(blob) => blob.id(),
+                None => break,
+            };
+            let saved = saved_filters.remove(&first_id);
+            let filter = saved
+                .as_ref()
+                .filter(|saved| group.len() == group_size && saved.matches(&group))
+                .and_then(|saved| saved.filter().ok());
+            if let Some(filter) = filter {
+                debug!("saved filter of blobs group {} is used", first_id);
+                filters.push_group(group, filter).await;
+                continue;
+            }
+            if let (Some(_), Some(group_filter_files)) = (saved, group_filter_files) {
+                warn!("saved filter of blobs group {} is outdated", first_id);
+                group_filter_files.remove(first_id).await;
+            }
+            for mut blob in group {
+                blob.reload_filter().await?;
+                filters.push(blob).await;
+            }
+        }
+        if let Some(group_filter_files) = group_filter_files {
+            for first_id in saved_filters.keys() {
+                group_filter_files.remove(*first_id).await;
+            }
+        }
+        Ok(filters)
+    }
+
     async fn pop_active(blobs: &mut Vec<Blob<K>>, config: &Config) -> Result<Box<Blob<K>>> {
         let mut active_blob = blobs
             .pop()
@@ -578,6 +1238,7 @@ impl<K: Key + 'static> Storage<K> {
         ioring: Option<Rio>,
         disk_access_sem: Arc<Semaphore>,
         config: &Config,
+        lazy_filters: &HashSet<usize>,
     ) -> Result<Vec<Blob<K>>> {
         debug!("read working directory content");
         let dir_content = files.iter().map(DirEntry::path);
@@ -596,7 +1257,9 @@ impl<K: Key + 'static> Storage<K> {
             .map(|file| async {
                 let sem = disk_access_sem.clone();
                 let _sem = sem.acquire().await.expect("sem is closed");
-                Blob::from_file(file.clone(), ioring.clone(), config.index())
+                let load_filter = blob::FileName::from_path(&file)
+                    .map_or(true, |name| !lazy_filters.contains(&name.id()));
+                Blob::from_file(file.clone(), ioring.clone(), config.index(), load_filter)
                     .await
                     .map_err(|e| (e, file))
             })
@@ -684,6 +1347,12 @@ impl<K: Key + 'static> Storage<K> {
                 .await
                 .with_context(|| anyhow!(format!("failed to remove file {:?}", index_path)))?;
         }
+        let meta_index_path = path.with_extension(blob::BLOB_META_INDEX_FILE_EXTENSION);
+        if meta_index_path.exists() {
+            tokio::fs::remove_file(&meta_index_path)
+                .await
+                .with_context(|| format!("failed to remove file {:?}", meta_index_path))?;
+        }
         Ok(())
     }
 
@@ -698,6 +1367,10 @@ impl<K: Key + 'static> Storage<K> {
 
     async fn contains_with(&self, key: &K, meta: Option<&Meta>) -> Result<bool> {
         let inner = self.inner.safe.read().await;
+        Self::contains_in(&inner, key, meta).await
+    }
+
+    async fn contains_in(inner: &Safe<K>, key: &K, meta: Option<&Meta>) -> Result<bool> {
         if let Some(active_blob) = &inner.active_blob {
             if active_blob.contains(key, meta).await? {
                 return Ok(true);
@@ -713,6 +1386,38 @@ impl<K: Key + 'static> Storage<K> {
         Ok(false)
     }
 
+    async fn check_version(safe: &Safe<K>, key: &K, expected: &ExpectedVersion) -> Result<()> {
+        let mut entry = Self::latest_entry(safe, key)
+            .await?
+            .ok_or_else(Error::not_found)?;
+        let matches = match expected {
+            ExpectedVersion::Meta(meta) => entry.load_meta().await? == Some(meta),
+            ExpectedVersion::Offset { blob_id, offset } => {
+                *blob_id == entry.blob_id() && *offset == entry.blob_offset()
+            }
+        };
+        if matches {
+            Ok(())
+        } else {
+            Err(Error::record_exists().into())
+        }
+    }
+
+    async fn latest_entry(safe: &Safe<K>, key: &K) -> Result<Option<Entry>> {
+        if let Some(active_blob) = &safe.active_blob {
+            if let Some(entry) = active_blob.latest_entry(key).await? {
+                return Ok(Some(entry));
+            }
+        }
+        let blobs = safe.blobs.read().await;
+        for blob in blobs.iter_possible_childs_rev(key) {
+            if let Some(entry) = blob.1.data.latest_entry(key).await? {
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+
     /// `check_filters` is used to check whether a key is in storage.
     /// Range (min-max test) and bloom filters are used.
     /// If bloom filter opt out and range filter passes, returns `None`.
@@ -806,9 +1511,11 @@ impl<K: Key + 'static> Inner<K> {
     fn new(config: Config, ioring: Option<Rio>) -> Self {
         Self {
             safe: Arc::new(RwLock::new(Safe::new(config.bloom_filter_group_size()))),
+            change_feed: ChangeFeed::new(config.change_feed_capacity()),
             config,
             next_blob_id: Arc::new(AtomicUsize::new(0)),
             ioring,
+            namespaces: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -888,6 +1595,10 @@ impl<K: Key + 'static> Inner<K> {
     // It'll make code a bit more complicated, but blobs will sequentially grow for sure
     pub(crate) fn next_blob_name(&self) -> Result<blob::FileName> {
         let next_id = self.next_blob_id.fetch_add(1, ORD);
+        self.blob_name(next_id)
+    }
+
+    fn blob_name(&self, id: usize) -> Result<blob::FileName> {
         let prefix = self
             .config
             .blob_file_name_prefix()
@@ -906,7 +1617,7 @@ impl<K: Key + 'static> Inner<K> {
             .to_owned();
         Ok(blob::FileName::new(
             prefix,
-            next_id,
+            id,
             BLOB_FILE_EXTENSION.to_owned(),
             dir,
         ))
@@ -930,7 +1641,12 @@ impl<K: Key + 'static> Inner<K> {
     }
 
     pub(crate) async fn try_dump_old_blob_indexes(&self, sem: Arc<Semaphore>) {
-        self.safe.write().await.try_dump_old_blob_indexes(sem).await;
+        let group_filter_files = GroupFilterFiles::new(&self.config, self.ioring.clone());
+        self.safe
+            .write()
+            .await
+            .try_dump_old_blob_indexes(sem, group_filter_files)
+            .await;
     }
 }
 
@@ -985,21 +1701,37 @@ impl<K: Key + 'static> Safe<K> {
         Ok(())
     }
 
-    pub(crate) async fn try_dump_old_blob_indexes(&mut self, sem: Arc<Semaphore>) {
+    pub(crate) async fn try_dump_old_blob_indexes(
+        &mut self,
+        sem: Arc<Semaphore>,
+        group_filter_files: Option<GroupFilterFiles>,
+    ) {
         let blobs = self.blobs.clone();
         tokio::spawn(async move {
             trace!("acquire blobs write to dump old blobs");
             let mut write_blobs = blobs.write().await;
             trace!("dump old blobs");
-            for blob in write_blobs.iter_mut() {
+            let mut dumped = Vec::new();
+            for id in 0..write_blobs.len() {
+                let blob = match write_blobs.get_child_mut(id) {
+                    Some(child) => &mut child.data,
+                    None => continue,
+                };
                 trace!("dumping old blob");
                 let _ = sem.acquire().await;
                 trace!("acquired sem for dumping old blobs");
-                if let Err(e) = blob.dump().await {
-                    error!("Error dumping blob ({}): {}", blob.name(), e);
+                match blob.dump().await {
+                    Ok(0) => {}
+                    Ok(_) => dumped.push(id),
+                    Err(e) => error!("Error dumping blob ({}): {}", blob.name(), e),
                 }
                 trace!("finished dumping old blob");
             }
+            // filters of blobs are rebuilt on dump, so they are merged into group filters again
+            write_blobs.rebuild_filters(dumped).await;
+            if let Some(group_filter_files) = group_filter_files {
+                group_filter_files.save(&write_blobs).await;
+            }
         });
     }
 }
@@ -1009,12 +1741,29 @@ pub trait Key: AsRef<[u8]> + Debug + Clone + Send + Sync + Ord + From<Vec<u8>> +
     /// Key must have fixed length
     const LEN: u16;
 
+    /// Must be `true` only if `Ord` of keys matches lexicographic order of their bytes.
+    /// Then [`Storage::scan_prefix`] reads only the part of sorted index with the prefix and
+    /// skips blobs by their key ranges, otherwise all keys of blobs are checked.
+    const BYTE_ORDERED: bool = false;
+
     /// Convert `Self` into `Vec<u8>`
     fn to_vec(&self) -> Vec<u8> {
         self.as_ref().to_vec()
     }
 }
 
+#[async_trait::async_trait]
+impl<K: Key + 'static> Namespace for Storage<K> {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.observer.detach();
+        Storage::close(self.clone()).await
+    }
+}
+
 #[async_trait::async_trait]
 impl<K: Key + 'static> BloomProvider<K> for Storage<K> {
     type Filter = <Blob<K> as BloomProvider<K>>::Filter;
@@ -1051,8 +1800,7 @@ impl<K: Key + 'static> BloomProvider<K> for Storage<K> {
             if let Some(active_filter) = inner
                 .active_blob
                 .as_ref()
-                .map(|b| b.get_filter_fast())
-                .flatten()
+                .and_then(|blob| blob.get_filter_fast())
             {
                 if !filter.checked_add_assign(active_filter) {
                     return None;
@@ -0,0 +1,367 @@
+use super::prelude::*;
+use ring::digest::{Context, SHA256};
+
+// keys are split into ranges by this number of leading bits
+const RANGE_PREFIX_BITS: u32 = 12;
+const RANGES_COUNT: usize = 1 << RANGE_PREFIX_BITS;
+const PREFIX_LIMIT: u32 = 1 << 16;
+
+/// Summary of the storage records, which allows to check cheaply, if two storages hold the
+/// same records, and to find key ranges, which differ.
+///
+/// Digest is built from record headers: key, data checksum and creation time, so only
+/// indexes are read. Hashes of key ranges don't depend on how records are distributed
+/// between blobs, hashes of blobs do.
+///
+/// Keys are split into ranges by two bytes after the prefix, which is common for all keys,
+/// so keys with the same leading bytes don't fall into a single range.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Digest {
+    // levels of the hash tree: the first one holds hashes of key ranges,
+    // the last one holds the root hash
+    levels: Vec<Vec<u128>>,
+    blobs: BTreeMap<usize, BlobDigest>,
+    // common prefix of all keys, `None` if there are no records
+    prefix: Option<Vec<u8>>,
+}
+
+/// Summary of the blob records, part of the storage [`Digest`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobDigest {
+    records_count: usize,
+    hash: u128,
+}
+
+/// Range of keys with the common prefix, which two bytes after the prefix are in
+/// `start..end`. Keys shorter than that are compared as padded with zeros.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyRange {
+    prefix: Vec<u8>,
+    start: u32,
+    end: u32,
+}
+
+impl Digest {
+    pub(crate) fn new() -> Self {
+        Self {
+            levels: vec![vec![0; RANGES_COUNT]],
+            blobs: BTreeMap::new(),
+            prefix: None,
+        }
+    }
+
+    // hashes of records are summed, so the result doesn't depend on the order of records
+    pub(crate) fn push(&mut self, blob_id: usize, header: &RecordHeader) {
+        let key = header.key();
+        let prefix = self.prefix.get_or_insert_with(|| key.to_vec());
+        let common = common_prefix_len(prefix, key);
+        if common < prefix.len() {
+            self.shrink_prefix(common);
+        }
+        let hash = record_hash(header);
+        let range = &mut self.levels[0][range_index(&key[common..])];
+        *range = range.wrapping_add(hash);
+        let blob = self.blobs.entry(blob_id).or_default();
+        blob.records_count += 1;
+        blob.hash = blob.hash.wrapping_add(hash);
+    }
+
+    pub(crate) fn build_tree(&mut self) {
+        self.levels.truncate(1);
+        while self.levels[self.levels.len() - 1].len() > 1 {
+            let level = self.levels[self.levels.len() - 1]
+                .chunks(2)
+                .map(|pair| hash_pair(pair[0], pair[1]))
+                .collect();
+            self.levels.push(level);
+        }
+    }
+
+    /// Returns root hash, storages with the same records have equal root hashes.
+    #[must_use]
+    pub fn root(&self) -> u128 {
+        self.levels[self.levels.len() - 1][0]
+    }
+
+    /// Returns digests of blobs by their ids.
+    #[must_use]
+    pub fn blobs(&self) -> &BTreeMap<usize, BlobDigest> {
+        &self.blobs
+    }
+
+    /// Returns ranges of keys, records of which differ in two digests. Adjacent ranges are
+    /// merged.
+    #[must_use]
+    pub fn diff(&self, other: &Self) -> Vec<KeyRange> {
+        // ranges of both digests are compared after the prefix, which is common for both
+        let prefix = match (&self.prefix, &other.prefix) {
+            (Some(prefix), Some(other_prefix)) => {
+                prefix[..common_prefix_len(prefix, other_prefix)].to_vec()
+            }
+            (Some(prefix), None) | (None, Some(prefix)) => prefix.clone(),
+            (None, None) => Vec::new(),
+        };
+        let (this, other) = (
+            self.with_prefix_len(prefix.len()),
+            other.with_prefix_len(prefix.len()),
+        );
+        let mut ranges: Vec<KeyRange> = Vec::new();
+        let top = this.levels.len() - 1;
+        this.diff_node(&other, top, 0, &mut |index| {
+            let range = KeyRange::from_index(&prefix, index);
+            match ranges.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => ranges.push(range),
+            }
+        });
+        ranges
+    }
+
+    /// Returns ids of blobs, which differ in two digests or exist only in one of them.
+    #[must_use]
+    pub fn diff_blobs(&self, other: &Self) -> Vec<usize> {
+        let mut ids: Vec<_> = self
+            .blobs
+            .iter()
+            .filter(|(id, blob)| other.blobs.get(id) != Some(blob))
+            .map(|(id, _)| *id)
+            .chain(
+                other
+                    .blobs
+                    .keys()
+                    .filter(|id| !self.blobs.contains_key(id))
+                    .copied(),
+            )
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    fn with_prefix_len(&self, len: usize) -> Self {
+        let mut digest = self.clone();
+        if matches!(&digest.prefix, Some(prefix) if len < prefix.len()) {
+            digest.shrink_prefix(len);
+            digest.build_tree();
+        }
+        digest
+    }
+
+    // keys of each range share the prefix and the first byte after it, so two bytes after
+    // the shorter prefix are known for every range and hashes of ranges are moved without keys
+    fn shrink_prefix(&mut self, len: usize) {
+        let prefix = match self.prefix.as_mut() {
+            Some(prefix) => prefix,
+            None => return,
+        };
+        let mut ranges = vec![0_u128; RANGES_COUNT];
+        for (index, hash) in self.levels[0]
+            .iter()
+            .enumerate()
+            .filter(|(_, hash)| **hash != 0)
+        {
+            let mut key = prefix.clone();
+            key.extend_from_slice(&range_start(index).to_be_bytes());
+            let range = &mut ranges[range_index(&key[len..])];
+            *range = range.wrapping_add(*hash);
+        }
+        prefix.truncate(len);
+        self.levels = vec![ranges];
+    }
+
+    // nodes are visited from left to right, so differing ranges are found in key order
+    fn diff_node(&self, other: &Self, level: usize, index: usize, f: &mut impl FnMut(usize)) {
+        if self.levels[level][index] == other.levels[level][index] {
+            return;
+        }
+        if level == 0 {
+            f(index);
+        } else {
+            self.diff_node(other, level - 1, index * 2, f);
+            self.diff_node(other, level - 1, index * 2 + 1, f);
+        }
+    }
+}
+
+impl BlobDigest {
+    /// Returns the number of records in the blob.
+    #[must_use]
+    pub fn records_count(&self) -> usize {
+        self.records_count
+    }
+
+    /// Returns hash of the blob records.
+    #[must_use]
+    pub fn hash(&self) -> u128 {
+        self.hash
+    }
+}
+
+impl KeyRange {
+    fn from_index(prefix: &[u8], index: usize) -> Self {
+        let start = u32::from(range_start(index));
+        Self {
+            prefix: prefix.to_vec(),
+            start,
+            end: start + PREFIX_LIMIT / RANGES_COUNT as u32,
+        }
+    }
+
+    /// Returns the least key in the range.
+    #[must_use]
+    pub fn start(&self) -> Vec<u8> {
+        self.key_at(self.start as u16)
+    }
+
+    /// Returns the least key after the range, `None` if there are no such keys.
+    #[must_use]
+    pub fn end(&self) -> Option<Vec<u8>> {
+        if self.end < PREFIX_LIMIT {
+            Some(self.key_at(self.end as u16))
+        } else {
+            // the least key without the prefix
+            let len = self.prefix.iter().rposition(|byte| *byte < u8::MAX)?;
+            let mut key = self.prefix[..=len].to_vec();
+            key[len] += 1;
+            Some(key)
+        }
+    }
+
+    /// Checks if the range contains `key`.
+    #[must_use]
+    pub fn contains(&self, key: &[u8]) -> bool {
+        key.starts_with(&self.prefix) && {
+            let bytes = u32::from(key_prefix(&key[self.prefix.len()..]));
+            self.start <= bytes && bytes < self.end
+        }
+    }
+
+    // trailing zeros are dropped, as keys are padded with them
+    fn key_at(&self, bytes: u16) -> Vec<u8> {
+        let bytes = bytes.to_be_bytes();
+        let len = bytes
+            .iter()
+            .rposition(|byte| *byte != 0)
+            .map_or(0, |i| i + 1);
+        [&self.prefix, &bytes[..len]].concat()
+    }
+}
+
+fn common_prefix_len(left: &[u8], right: &[u8]) -> usize {
+    left.iter().zip(right).take_while(|(l, r)| l == r).count()
+}
+
+fn range_start(index: usize) -> u16 {
+    (index << (16 - RANGE_PREFIX_BITS)) as u16
+}
+
+fn key_prefix(key: &[u8]) -> u16 {
+    let first = key.first().copied().unwrap_or_default();
+    let second = key.get(1).copied().unwrap_or_default();
+    u16::from_be_bytes([first, second])
+}
+
+fn range_index(key: &[u8]) -> usize {
+    usize::from(key_prefix(key) >> (16 - RANGE_PREFIX_BITS))
+}
+
+fn record_hash(header: &RecordHeader) -> u128 {
+    let mut context = Context::new(&SHA256);
+    context.update(header.key());
+    context.update(&header.data_checksum().to_le_bytes());
+    context.update(&header.created().to_le_bytes());
+    truncate(context.finish().as_ref())
+}
+
+fn hash_pair(left: u128, right: u128) -> u128 {
+    let mut context = Context::new(&SHA256);
+    context.update(&left.to_le_bytes());
+    context.update(&right.to_le_bytes());
+    truncate(context.finish().as_ref())
+}
+
+fn truncate(hash: &[u8]) -> u128 {
+    let mut buf = [0; 16];
+    buf.copy_from_slice(&hash[..16]);
+    u128::from_le_bytes(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(key: &[u8]) -> RecordHeader {
+        RecordHeader::new(key.to_vec(), 0, 1, 1)
+    }
+
+    #[test]
+    fn digest_diff() {
+        let headers: Vec<_> = (0..100_u16)
+            .map(|i| header(&(i * 300).to_be_bytes()))
+            .collect();
+        let (mut first, mut second) = (Digest::new(), Digest::new());
+        for (i, header) in headers.iter().enumerate() {
+            first.push(0, header);
+            // the same records in other blobs and order
+            second.push(i % 3, &headers[headers.len() - 1 - i]);
+        }
+        first.build_tree();
+        second.build_tree();
+        assert_eq!(first.root(), second.root());
+        assert!(first.diff(&second).is_empty());
+        assert_eq!(first.diff_blobs(&second), [0, 1, 2]);
+        assert_eq!(first.blobs()[&0].records_count(), 100);
+
+        // keys with prefixes 0x0000 and 0x0010 are in adjacent ranges
+        second.push(3, &header(&[0, 0, 1]));
+        second.push(3, &header(&[0, 0x10]));
+        second.push(3, &header(&[0xff, 0xff]));
+        second.build_tree();
+        assert_ne!(first.root(), second.root());
+        let diff = first.diff(&second);
+        assert_eq!(diff.len(), 2);
+        assert_eq!(diff[0].start(), []);
+        assert_eq!(diff[0].end(), Some(vec![0, 0x20]));
+        assert!(diff[0].contains(&[0, 0x1f, 0xff]));
+        assert!(!diff[0].contains(&[0, 0x20]));
+        assert_eq!(diff[1].end(), None);
+        assert!(diff[1].contains(&[0xff, 0xf0]));
+        assert!(!diff[1].contains(&[0xff]));
+        assert_eq!(first.diff_blobs(&second), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn digest_common_prefix() {
+        let key = |i: u16| [&[7, 7, 7][..], &i.to_be_bytes()].concat();
+        let mut first = Digest::new();
+        for i in 0..100 {
+            first.push(0, &header(&key(i * 300)));
+        }
+        first.build_tree();
+        let mut second = first.clone();
+        second.push(1, &header(&key(1)));
+        second.build_tree();
+        // keys differ only after the common prefix, but still fall into several ranges
+        let diff = first.diff(&second);
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].start(), [7, 7, 7]);
+        assert_eq!(diff[0].end(), Some(vec![7, 7, 7, 0, 0x10]));
+        assert!(diff[0].contains(&key(1)));
+        assert!(!diff[0].contains(&key(300)));
+
+        // prefix of the other digest is shorter, ranges are compared after the common one
+        let mut third = Digest::new();
+        third.push(0, &header(&[7, 8]));
+        for i in 0..100 {
+            third.push(0, &header(&key(i * 300)));
+        }
+        third.build_tree();
+        let diff = first.diff(&third);
+        assert_eq!(diff.len(), 1);
+        assert!(diff[0].contains(&[7, 8]));
+        assert!(!diff[0].contains(&key(0)));
+        assert_eq!(diff[0].end(), Some(vec![7, 8, 0x10]));
+        first.push(0, &header(&[7, 8]));
+        first.build_tree();
+        assert_eq!(first, third);
+    }
+}
@@ -0,0 +1,130 @@
+use super::prelude::*;
+use std::cmp::Reverse;
+
+// bloom filter of the closed blob with dumped index, which can be offloaded and reloaded
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FilterState {
+    blob_id: usize,
+    heat: u64,
+    memory: usize,
+    offloaded: bool,
+}
+
+// Keeps in memory the hottest filters, which fit into `budget`. On equal heat loaded filters
+// and then filters of the newer blobs are preferred. Offloaded filters, which weren't looked up
+// recently, stay offloaded. Returns ids of blobs to offload and to reload filters.
+fn plan(mut states: Vec<FilterState>, budget: usize) -> (Vec<usize>, Vec<usize>) {
+    states.sort_by_key(|state| (Reverse(state.heat), state.offloaded, Reverse(state.blob_id)));
+    let mut used = 0;
+    let mut offload = Vec::new();
+    let mut reload = Vec::new();
+    for state in states {
+        if state.offloaded && state.heat == 0 {
+            continue;
+        }
+        if used + state.memory <= budget {
+            used += state.memory;
+            if state.offloaded {
+                reload.push(state.blob_id);
+            }
+        } else if !state.offloaded {
+            offload.push(state.blob_id);
+        }
+    }
+    (offload, reload)
+}
+
+impl<K: Key + 'static> Inner<K> {
+    /// Offloads filters of the blobs with the least lookups while filters memory exceeds
+    /// `limit` and reloads filters of the blobs, which are looked up again, if they fit.
+    /// Filters of the active blob, blobs with in-memory index and group filters are
+    /// counted, but never offloaded. Filters are read from index files without locking blobs,
+    /// filters, which can't be read, stay offloaded.
+    pub(crate) async fn limit_filter_memory(&self, limit: usize) -> Result<()> {
+        let (offload, reload, blobs) = {
+            let safe = self.safe.read().await;
+            let blobs = safe.blobs.read().await;
+            let mut fixed = safe
+                .active_blob
+                .as_ref()
+                .map_or(0, |blob| blob.filter_memory_allocated())
+                + blobs.filter_memory_allocated().await;
+            let mut states = Vec::new();
+            for blob in blobs.iter() {
+                let heat = blob.update_filter_heat();
+                if blob.is_filter_offloadable() && blob.filter_memory_required() > 0 {
+                    fixed -= blob.filter_memory_allocated();
+                    states.push(FilterState {
+                        blob_id: blob.id(),
+                        heat,
+                        memory: blob.filter_memory_required(),
+                        offloaded: blob.is_filter_offloaded(),
+                    });
+                }
+            }
+            let (offload, reload) = plan(states, limit.saturating_sub(fixed));
+            let reload: Vec<_> = blobs
+                .iter()
+                .filter(|blob| reload.contains(&blob.id()))
+                .filter_map(|blob| Some((blob.id(), blob.detached_index()?)))
+                .collect();
+            (offload, reload, safe.blobs.clone())
+        };
+        if offload.is_empty() && reload.is_empty() {
+            return Ok(());
+        }
+        debug!(
+            "filter memory limit: offload filters of blobs {:?}, reload filters of blobs {:?}",
+            offload,
+            reload.iter().map(|(id, _)| id).collect::<Vec<_>>()
+        );
+        let mut filters = HashMap::new();
+        for (id, index) in reload {
+            match index.read_bloom().await {
+                Ok(filter) => {
+                    filters.insert(id, filter);
+                }
+                Err(e) => error!("failed to reload filter of blob {}: {:#}", id, e),
+            }
+        }
+        let mut blobs = blobs.write().await;
+        for blob in blobs.iter_mut() {
+            if offload.contains(&blob.id()) {
+                blob.offload_filter();
+            } else if let Some(filter) = filters.remove(&blob.id()) {
+                blob.restore_filter(filter);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{plan, FilterState};
+
+    fn state(blob_id: usize, heat: u64, offloaded: bool) -> FilterState {
+        FilterState {
+            blob_id,
+            heat,
+            memory: 10,
+            offloaded,
+        }
+    }
+
+    #[test]
+    fn filter_memory_plan() {
+        // all filters are cold, newer ones are kept
+        let states = vec![state(0, 0, false), state(1, 0, false), state(2, 0, false)];
+        assert_eq!(plan(states, 25), (vec![0], vec![]));
+        // hot offloaded filter replaces the coldest loaded one
+        let states = vec![state(0, 5, true), state(1, 1, false), state(2, 0, false)];
+        assert_eq!(plan(states, 20), (vec![2], vec![0]));
+        // cold offloaded filter isn't reloaded even if it fits
+        let states = vec![state(0, 0, true), state(1, 3, false)];
+        assert_eq!(plan(states, 100), (vec![], vec![]));
+        // loaded filter wins on equal heat
+        let states = vec![state(0, 2, true), state(1, 2, false)];
+        assert_eq!(plan(states, 10), (vec![], vec![]));
+    }
+}
@@ -0,0 +1,183 @@
+use super::prelude::*;
+use tokio::fs::remove_file;
+
+const GROUP_FILTER_FILE_EXTENSION: &str = "group_filter";
+// files start with magic and format version, files of other versions are removed and rebuilt
+const GROUP_FILTER_MAGIC: u64 = 0x7065_6172_6c67_6670;
+const GROUP_FILTER_VERSION: u32 = 1;
+
+/// Filter of a full group of closed blobs, which is saved next to the blobs, so it isn't
+/// rebuilt from filters of the blobs at start. Saved filter is valid while the group
+/// consists of the same blobs with the same indexes.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GroupFilter {
+    // blob ids with hashes of their index records
+    members: Vec<(usize, Vec<u8>)>,
+    range: Vec<u8>,
+    bloom: Option<Vec<u8>>,
+    prefix: Option<Vec<u8>>,
+}
+
+impl GroupFilter {
+    /// Returns `None` if some blob index isn't dumped yet or group filter is offloaded.
+    fn from_group<K: Key + 'static>(
+        blobs: &[&Blob<K>],
+        filter: &CombinedFilter<K>,
+    ) -> Option<Self> {
+        let members = blobs
+            .iter()
+            .map(|blob| Some((blob.id(), blob.index_records_hash()?.to_vec())))
+            .collect::<Option<_>>()?;
+        let range = filter.range().to_raw().ok()?;
+        let bloom = match filter.bloom() {
+            Some(bloom) => Some(bloom.to_raw().ok()?),
+            None => None,
+        };
+        let prefix = match filter.prefix() {
+            Some(prefix) => Some(prefix.to_raw().ok()?),
+            None => None,
+        };
+        Some(Self {
+            members,
+            range,
+            bloom,
+            prefix,
+        })
+    }
+
+    fn first_id(&self) -> Option<usize> {
+        self.members.first().map(|(id, _)| *id)
+    }
+
+    pub(crate) fn blob_ids(&self) -> impl Iterator<Item = usize> + '_ {
+        self.members.iter().map(|(id, _)| *id)
+    }
+
+    pub(crate) fn matches<K: Key + 'static>(&self, blobs: &[Blob<K>]) -> bool {
+        self.members.len() == blobs.len()
+            && self.members.iter().zip(blobs).all(|((id, hash), blob)| {
+                *id == blob.id() && blob.index_records_hash() == Some(hash.as_slice())
+            })
+    }
+
+    pub(crate) fn filter<K: Key>(&self) -> Result<CombinedFilter<K>> {
+        let bloom = self.bloom.as_deref().map(Bloom::from_raw).transpose()?;
+        let prefix = self
+            .prefix
+            .as_deref()
+            .map(PrefixFilter::from_raw)
+            .transpose()?;
+        Ok(CombinedFilter::new(
+            RangeFilter::from_raw(&self.range)?,
+            bloom,
+            prefix,
+        ))
+    }
+}
+
+/// Files of saved group filters in the work dir, named by the first blob of the group.
+#[derive(Debug, Clone)]
+pub(crate) struct GroupFilterFiles {
+    prefix: String,
+    dir: PathBuf,
+    ioring: Option<Rio>,
+}
+
+impl GroupFilterFiles {
+    /// Returns `None` if group filters aren't saved.
+    pub(crate) fn new(config: &Config, ioring: Option<Rio>) -> Option<Self> {
+        if !config.save_group_filters() {
+            return None;
+        }
+        Some(Self {
+            prefix: config.blob_file_name_prefix()?.to_owned(),
+            dir: config.work_dir()?.to_owned(),
+            ioring,
+        })
+    }
+
+    fn name(&self, first_id: usize) -> blob::FileName {
+        blob::FileName::new(
+            self.prefix.clone(),
+            first_id,
+            GROUP_FILTER_FILE_EXTENSION.to_owned(),
+            self.dir.clone(),
+        )
+    }
+
+    /// Reads saved group filters from work dir `files`, unreadable files are removed.
+    /// Returns filters by the first blob id of the group.
+    pub(crate) async fn load(&self, files: &[DirEntry]) -> BTreeMap<usize, GroupFilter> {
+        let mut filters = BTreeMap::new();
+        let paths = files.iter().map(DirEntry::path).filter(|path| {
+            path.extension().and_then(|ext| ext.to_str()) == Some(GROUP_FILTER_FILE_EXTENSION)
+        });
+        for path in paths {
+            match self.read(&path).await {
+                Ok((id, filter)) => {
+                    filters.insert(id, filter);
+                }
+                Err(e) => {
+                    warn!("failed to read group filter {}: {:#}", path.display(), e);
+                    if let Err(e) = remove_file(&path).await {
+                        warn!("failed to remove group filter {}: {}", path.display(), e);
+                    }
+                }
+            }
+        }
+        filters
+    }
+
+    async fn read(&self, path: &Path) -> Result<(usize, GroupFilter)> {
+        let id = blob::FileName::from_path(path)?.id();
+        let file = blob::File::open(path, self.ioring.clone()).await?;
+        let buf = file.read_all().await?;
+        let (magic, version): (u64, u32) = deserialize(&buf)?;
+        if magic != GROUP_FILTER_MAGIC || version != GROUP_FILTER_VERSION {
+            return Err(anyhow::anyhow!(
+                "unsupported group filter format version {}",
+                version
+            ));
+        }
+        let (_, _, filter): (u64, u32, GroupFilter) = deserialize(&buf)?;
+        if filter.first_id() == Some(id) {
+            Ok((id, filter))
+        } else {
+            Err(anyhow::anyhow!(
+                "group filter file name doesn't match its blobs"
+            ))
+        }
+    }
+
+    /// Removes saved filter of the group, which starts with blob `first_id`.
+    pub(crate) async fn remove(&self, first_id: usize) {
+        let path = self.name(first_id).to_path();
+        if let Err(e) = remove_file(&path).await {
+            warn!("failed to remove group filter {}: {}", path.display(), e);
+        }
+    }
+
+    /// Saves filters of full groups, which aren't saved yet.
+    pub(crate) async fn save<K: Key + 'static>(&self, blobs: &BlobFilters<K>) {
+        for (group, filter) in blobs.groups() {
+            let name = self.name(group[0].id());
+            if name.to_path().exists() {
+                continue;
+            }
+            let filter = match filter.and_then(|filter| GroupFilter::from_group(&group, filter)) {
+                Some(filter) => filter,
+                None => continue,
+            };
+            if let Err(e) = self.write(&name, &filter).await {
+                warn!("failed to save group filter {}: {:#}", name, e);
+            }
+        }
+    }
+
+    async fn write(&self, name: &blob::FileName, filter: &GroupFilter) -> Result<()> {
+        let buf = serialize(&(GROUP_FILTER_MAGIC, GROUP_FILTER_VERSION, filter))?;
+        // partially written filter isn't read
+        blob::File::write_atomically(&name.to_path(), &buf, self.ioring.clone()).await?;
+        Ok(())
+    }
+}
@@ -1,23 +1,52 @@
 mod builder;
+mod change_feed;
 mod config;
 mod core;
+mod digest;
+mod filter_memory;
+mod group_filters;
+mod namespace;
 mod observer;
 mod observer_worker;
+mod read_cache;
+mod read_policy;
+mod replication;
+mod write_condition;
 
 pub use self::{
     builder::Builder,
+    change_feed::{Position, WriteEvent},
     core::{Key, Storage},
+    digest::{BlobDigest, Digest, KeyRange},
     observer::ActiveBlobPred,
     observer::ActiveBlobStat,
+    read_cache::{ReadCacheAdmission, ReadCacheStats},
+    read_policy::ReadPolicy,
+    replication::{
+        replication_channel, ChannelReceiver, ChannelSender, Replica, ReplicationChunk,
+        ReplicationReceiver, ReplicationSender,
+    },
+    write_condition::ExpectedVersion,
 };
 
 mod prelude {
     pub(crate) use {
         super::{
-            config::Config, core::Inner, observer::Msg, observer::Observer,
-            observer::OperationType, observer_worker::ObserverWorker, ActiveBlobPred,
-            ActiveBlobStat,
+            change_feed::ChangeFeed,
+            config::Config,
+            core::{BlobFilters, Inner},
+            namespace::{check_name, Namespace, Namespaces, NAMESPACES_DIR},
+            observer::Msg,
+            observer::Observer,
+            observer::ObserverTarget,
+            observer::ObserverTargets,
+            observer::OperationType,
+            observer_worker::ObserverWorker,
+            read_cache::ReadCache,
+            write_condition::WriteCondition,
+            ActiveBlobPred, ActiveBlobStat,
         },
+        crate::filter::{FilterTrait, PrefixFilter, RangeFilter},
         crate::prelude::*,
     };
 }
@@ -0,0 +1,28 @@
+use super::prelude::*;
+use std::any::Any;
+
+/// Namespaces keep their files in subdirectories of this directory inside the work dir.
+pub(crate) const NAMESPACES_DIR: &str = "namespaces";
+
+/// Storage of a namespace with erased key type.
+#[async_trait::async_trait]
+pub(crate) trait Namespace: Debug + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    /// Dumps active blob and stops its updates.
+    async fn close(&self) -> Result<()>;
+}
+
+pub(crate) type Namespaces = Arc<RwLock<HashMap<String, Box<dyn Namespace>>>>;
+
+pub(crate) fn check_name(name: &str) -> Result<()> {
+    let is_valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if is_valid {
+        Ok(())
+    } else {
+        let msg = format!("invalid namespace name {:?}", name);
+        Err(Error::namespace(msg).into())
+    }
+}
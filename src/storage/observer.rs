@@ -1,4 +1,5 @@
 use super::prelude::*;
+use std::sync::Mutex as StdMutex;
 use tokio::sync::{
     mpsc::{channel, Sender},
     Semaphore,
@@ -36,81 +37,133 @@ pub type ActiveBlobPred = fn(Option<ActiveBlobStat>) -> bool;
 pub(crate) struct Msg {
     pub(crate) optype: OperationType,
     pub(crate) predicate: Option<ActiveBlobPred>,
+    pub(crate) target: Arc<dyn ObserverTarget>,
 }
 
 impl Msg {
-    pub(crate) fn new(optype: OperationType, predicate: Option<ActiveBlobPred>) -> Self {
-        Self { optype, predicate }
+    pub(crate) fn new(
+        optype: OperationType,
+        predicate: Option<ActiveBlobPred>,
+        target: Arc<dyn ObserverTarget>,
+    ) -> Self {
+        Self {
+            optype,
+            predicate,
+            target,
+        }
     }
 }
 
+/// Storage, which active blob is maintained by observer worker. Allows one worker to serve
+/// storages with different key types.
+#[async_trait::async_trait]
+pub(crate) trait ObserverTarget: Debug + Send + Sync {
+    async fn active_blob_stat(&self) -> Option<ActiveBlobStat>;
+    async fn update_active_blob(&self) -> Result<()>;
+    /// Replaces active blob if it exceeds limits and dumps indexes of closed blobs.
+    async fn try_update(&self, dump_sem: Arc<Semaphore>) -> Result<()>;
+    async fn close_active_blob(&self) -> Result<()>;
+    async fn create_active_blob(&self) -> Result<()>;
+    async fn restore_active_blob(&self) -> Result<()>;
+    async fn try_dump_old_blob_indexes(&self, dump_sem: Arc<Semaphore>);
+}
+
+pub(crate) type ObserverTargets = Arc<StdMutex<Vec<Arc<dyn ObserverTarget>>>>;
+
 #[derive(Debug, Clone)]
-pub(crate) struct Observer<K: Key> {
-    inner: Option<Inner<K>>,
+pub(crate) struct Observer {
+    target: Arc<dyn ObserverTarget>,
+    // all storages served by the worker: the one, which launched it, and its namespaces
+    targets: ObserverTargets,
+    update_interval: Duration,
     pub sender: Option<Sender<Msg>>,
     dump_sem: Arc<Semaphore>,
     async_oplock: Arc<Mutex<()>>,
 }
 
-impl<K: Key + 'static> Observer<K> {
-    pub(crate) fn new(inner: Inner<K>, dump_sem: Arc<Semaphore>) -> Self {
+impl Observer {
+    pub(crate) fn new<K: Key + 'static>(inner: Inner<K>, dump_sem: Arc<Semaphore>) -> Self {
+        let update_interval = Duration::from_millis(inner.config.update_interval_ms());
         Self {
-            inner: Some(inner),
+            target: Arc::new(inner),
+            targets: Arc::default(),
+            update_interval,
             sender: None,
             dump_sem,
             async_oplock: Arc::new(Mutex::new(())),
         }
     }
 
+    /// Creates observer for `inner`, which shares worker with `self`.
+    pub(crate) fn attach<K: Key + 'static>(&self, inner: Inner<K>) -> Self {
+        Self {
+            target: Arc::new(inner),
+            ..self.clone()
+        }
+    }
+
+    /// Stops updates of the active blob of the storage.
+    pub(crate) fn detach(&self) {
+        let target = Arc::as_ptr(&self.target) as *const ();
+        self.lock_targets()
+            .retain(|t| Arc::as_ptr(t) as *const () != target);
+    }
+
     pub(crate) fn is_pending(&self) -> bool {
         self.async_oplock.try_lock().is_none()
     }
 
     pub(crate) fn run(&mut self) {
-        if let Some(inner) = self.inner.take() {
+        if self.sender.is_none() {
             let (sender, receiver) = channel(1024);
             self.sender = Some(sender);
             let worker = ObserverWorker::new(
                 receiver,
-                inner,
+                self.targets.clone(),
+                self.update_interval,
                 self.dump_sem.clone(),
                 self.async_oplock.clone(),
             );
             tokio::spawn(worker.run());
         }
+        let target = Arc::as_ptr(&self.target) as *const ();
+        let mut targets = self.lock_targets();
+        if targets
+            .iter()
+            .all(|t| Arc::as_ptr(t) as *const () != target)
+        {
+            targets.push(self.target.clone());
+        }
+    }
+
+    fn lock_targets(&self) -> std::sync::MutexGuard<'_, Vec<Arc<dyn ObserverTarget>>> {
+        self.targets.lock().expect("observer targets lock poisoned")
     }
 
     pub(crate) async fn force_update_active_blob(&self, predicate: ActiveBlobPred) {
-        self.send_msg(Msg::new(
-            OperationType::ForceUpdateActiveBlob,
-            Some(predicate),
-        ))
-        .await
+        self.send_msg(OperationType::ForceUpdateActiveBlob, Some(predicate))
+            .await
     }
 
     pub(crate) async fn restore_active_blob(&self) {
-        self.send_msg(Msg::new(OperationType::RestoreActiveBlob, None))
-            .await
+        self.send_msg(OperationType::RestoreActiveBlob, None).await
     }
 
     pub(crate) async fn close_active_blob(&self) {
-        self.send_msg(Msg::new(OperationType::CloseActiveBlob, None))
-            .await
+        self.send_msg(OperationType::CloseActiveBlob, None).await
     }
 
     pub(crate) async fn create_active_blob(&self) {
-        self.send_msg(Msg::new(OperationType::CreateActiveBlob, None))
-            .await
+        self.send_msg(OperationType::CreateActiveBlob, None).await
     }
 
     pub(crate) async fn try_dump_old_blob_indexes(&self) {
-        self.send_msg(Msg::new(OperationType::TryDumpBlobIndexes, None))
-            .await
+        self.send_msg(OperationType::TryDumpBlobIndexes, None).await
     }
 
-    async fn send_msg(&self, msg: Msg) {
+    async fn send_msg(&self, optype: OperationType, predicate: Option<ActiveBlobPred>) {
         if let Some(sender) = &self.sender {
-            let optype = msg.optype.clone();
+            let msg = Msg::new(optype.clone(), predicate, self.target.clone());
             if let Err(e) = sender.send(msg).await {
                 error!(
                     "Can't send message to worker:\nOperation: {:?}\nReason: {:?}",
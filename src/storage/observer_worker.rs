@@ -1,24 +1,24 @@
 use super::prelude::*;
 use tokio::{sync::mpsc::Receiver, sync::Semaphore, time::timeout};
 
-pub(crate) struct ObserverWorker<K: Key> {
-    inner: Inner<K>,
+pub(crate) struct ObserverWorker {
+    targets: ObserverTargets,
     receiver: Receiver<Msg>,
     dump_sem: Arc<Semaphore>,
     update_interval: Duration,
     async_oplock: Arc<Mutex<()>>,
 }
 
-impl<K: Key + 'static> ObserverWorker<K> {
+impl ObserverWorker {
     pub(crate) fn new(
         receiver: Receiver<Msg>,
-        inner: Inner<K>,
+        targets: ObserverTargets,
+        update_interval: Duration,
         dump_sem: Arc<Semaphore>,
         async_oplock: Arc<Mutex<()>>,
     ) -> Self {
-        let update_interval = Duration::from_millis(inner.config.update_interval_ms());
         Self {
-            inner,
+            targets,
             receiver,
             dump_sem,
             update_interval,
@@ -52,32 +52,34 @@ impl<K: Key + 'static> ObserverWorker<K> {
             Err(_) => {}
         }
         trace!("check active blob");
-        self.try_update().await
+        self.try_update().await;
+        Ok(())
     }
 
     async fn process_msg(&mut self, msg: Msg) -> Result<()> {
-        if !self.predicate_wrapper(&msg.predicate).await {
+        let target = msg.target;
+        if !Self::predicate_wrapper(&*target, &msg.predicate).await {
             return Ok(());
         }
         let _lock = self.async_oplock.lock().await;
-        if !self.predicate_wrapper(&msg.predicate).await {
+        if !Self::predicate_wrapper(&*target, &msg.predicate).await {
             return Ok(());
         }
         match msg.optype {
             OperationType::ForceUpdateActiveBlob => {
-                update_active_blob(self.inner.clone()).await?;
+                target.update_active_blob().await?;
             }
             OperationType::CloseActiveBlob => {
-                self.inner.close_active_blob().await?;
+                target.close_active_blob().await?;
             }
             OperationType::CreateActiveBlob => {
-                self.inner.create_active_blob().await?;
+                target.create_active_blob().await?;
             }
             OperationType::RestoreActiveBlob => {
-                self.inner.restore_active_blob().await?;
+                target.restore_active_blob().await?;
             }
             OperationType::TryDumpBlobIndexes => {
-                self.inner
+                target
                     .try_dump_old_blob_indexes(self.dump_sem.clone())
                     .await;
             }
@@ -85,26 +87,78 @@ impl<K: Key + 'static> ObserverWorker<K> {
         Ok(())
     }
 
-    async fn predicate_wrapper(&self, predicate: &Option<ActiveBlobPred>) -> bool {
+    async fn predicate_wrapper(
+        target: &dyn ObserverTarget,
+        predicate: &Option<ActiveBlobPred>,
+    ) -> bool {
         if let Some(predicate) = predicate {
-            predicate(self.inner.active_blob_stat().await)
+            predicate(target.active_blob_stat().await)
         } else {
             true
         }
     }
 
-    async fn try_update(&self) -> Result<()> {
+    // failure of one namespace doesn't stop updates of the others
+    async fn try_update(&self) {
         trace!("try update active blob");
-        let inner_cloned = self.inner.clone();
-        if let Some(inner) = active_blob_check(inner_cloned).await? {
+        let targets = self
+            .targets
+            .lock()
+            .expect("observer targets lock poisoned")
+            .clone();
+        for target in targets {
+            if let Err(e) = target.try_update(self.dump_sem.clone()).await {
+                error!("failed to update active blob: {:#}", e);
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<K: Key + 'static> ObserverTarget for Inner<K> {
+    async fn active_blob_stat(&self) -> Option<ActiveBlobStat> {
+        Inner::active_blob_stat(self).await
+    }
+
+    async fn update_active_blob(&self) -> Result<()> {
+        update_active_blob(self.clone()).await
+    }
+
+    async fn try_update(&self, dump_sem: Arc<Semaphore>) -> Result<()> {
+        if let Some(inner) = active_blob_check(self.clone()).await? {
             update_active_blob(inner.clone()).await?;
-            inner.try_dump_old_blob_indexes(self.dump_sem.clone()).await;
+            inner.try_dump_old_blob_indexes(dump_sem).await;
+        }
+        if let Some(limit) = self.config.filter_memory_limit() {
+            if let Err(e) = self.limit_filter_memory(limit).await {
+                error!("failed to limit filter memory: {:#}", e);
+            }
         }
         Ok(())
     }
+
+    async fn close_active_blob(&self) -> Result<()> {
+        Inner::close_active_blob(self).await
+    }
+
+    async fn create_active_blob(&self) -> Result<()> {
+        Inner::create_active_blob(self).await
+    }
+
+    async fn restore_active_blob(&self) -> Result<()> {
+        Inner::restore_active_blob(self).await
+    }
+
+    async fn try_dump_old_blob_indexes(&self, dump_sem: Arc<Semaphore>) {
+        Inner::try_dump_old_blob_indexes(self, dump_sem).await
+    }
 }
 
 async fn active_blob_check<K: Key + 'static>(inner: Inner<K>) -> Result<Option<Inner<K>>> {
+    // blobs of replica are rotated together with blobs of the primary storage
+    if inner.config.replica() {
+        return Ok(None);
+    }
     let (active_size, active_count) = {
         trace!("await for lock");
         let safe_locked = inner.safe.read().await;
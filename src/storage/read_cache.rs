@@ -0,0 +1,292 @@
+use super::prelude::*;
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    hash::{Hash, Hasher},
+    sync::{atomic::AtomicU64, Mutex},
+};
+
+// one value can't take more than this part of the cache, so single large record
+// doesn't flush all hot ones
+const MAX_VALUE_SHARE: usize = 8;
+// doorkeeper is reset after it remembers this amount of keys
+const DOORKEEPER_CAPACITY: usize = 1 << 16;
+// keys are spread by hash over this amount of invalidation epochs, so writes of
+// other keys rarely prevent caching of the value being read
+const EPOCH_SLOTS: usize = 1 << 10;
+
+/// Defines which records are put into the read cache after a miss.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadCacheAdmission {
+    /// Every read record is cached.
+    All,
+    /// Record is cached only on the second miss within a recent window, so keys which
+    /// are read once don't push hot keys out of the cache.
+    #[default]
+    Frequent,
+}
+
+/// Statistics of record read cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadCacheStats {
+    /// Number of reads served from the cache.
+    pub hits: u64,
+    /// Number of reads which went to blobs.
+    pub misses: u64,
+    /// Number of records currently in the cache.
+    pub records: usize,
+    /// Size of cached values in bytes.
+    pub memory: usize,
+}
+
+impl ReadCacheStats {
+    /// Share of reads served from the cache, `0.0` if there were no reads.
+    #[must_use]
+    pub fn hit_ratio(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// Storage-level LRU cache of record values, read without meta.
+#[derive(Clone)]
+pub(crate) struct ReadCache {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    memory_limit: usize,
+    admission: ReadCacheAdmission,
+    state: Mutex<State>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+struct State {
+    values: HashMap<Vec<u8>, (u64, Bytes)>,
+    // access tick -> key, the first one is the least recently used
+    lru: BTreeMap<u64, Vec<u8>>,
+    tick: u64,
+    memory: usize,
+    // hashes of keys, which missed once, used by `Frequent` admission
+    doorkeeper: HashSet<u64>,
+    // epoch of the key hash slot is incremented on invalidation of any key of the slot,
+    // values of the slot keys read before it may be outdated
+    epochs: Vec<u64>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            values: HashMap::new(),
+            lru: BTreeMap::new(),
+            tick: 0,
+            memory: 0,
+            doorkeeper: HashSet::new(),
+            epochs: vec![0; EPOCH_SLOTS],
+        }
+    }
+}
+
+impl ReadCache {
+    pub(crate) fn new(memory_limit: usize, admission: ReadCacheAdmission) -> Self {
+        let inner = Inner {
+            memory_limit,
+            admission,
+            state: Mutex::new(State::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        };
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Returns cached value or epoch, which must be passed to `insert` with the value read
+    /// from blobs.
+    pub(crate) fn get(&self, key: &[u8]) -> std::result::Result<Bytes, u64> {
+        let mut guard = self.lock();
+        let state = &mut *guard;
+        state.tick += 1;
+        let tick = state.tick;
+        let res = match state.values.get_mut(key) {
+            Some((last_access, value)) => {
+                let prev_access = std::mem::replace(last_access, tick);
+                let value = value.clone();
+                let key = state.lru.remove(&prev_access).expect("key must be in lru");
+                state.lru.insert(tick, key);
+                Ok(value)
+            }
+            None => Err(state.epochs[epoch_slot(key)]),
+        };
+        drop(guard);
+        if res.is_ok() {
+            self.inner.hits.fetch_add(1, ORD);
+        } else {
+            self.inner.misses.fetch_add(1, ORD);
+        }
+        res
+    }
+
+    /// Caches value if it passes admission policy and there were no writes of the keys
+    /// sharing epoch with it since `epoch`.
+    pub(crate) fn insert(&self, key: &[u8], value: Bytes, epoch: u64) {
+        if value.len() > self.inner.memory_limit / MAX_VALUE_SHARE {
+            return;
+        }
+        let mut state = self.lock();
+        if state.epochs[epoch_slot(key)] != epoch
+            || state.values.contains_key(key)
+            || !self.admit(&mut state, key)
+        {
+            return;
+        }
+        while state.memory + value.len() > self.inner.memory_limit {
+            let (_, lru_key) = match state.lru.pop_first() {
+                Some(entry) => entry,
+                None => break,
+            };
+            if let Some((_, evicted)) = state.values.remove(&lru_key) {
+                state.memory -= evicted.len();
+            }
+        }
+        state.tick += 1;
+        let tick = state.tick;
+        state.memory += value.len();
+        state.lru.insert(tick, key.to_vec());
+        state.values.insert(key.to_vec(), (tick, value));
+    }
+
+    /// Drops cached value of the key, must be called after any write with this key.
+    pub(crate) fn invalidate(&self, key: &[u8]) {
+        let mut state = self.lock();
+        state.epochs[epoch_slot(key)] += 1;
+        if let Some((tick, value)) = state.values.remove(key) {
+            state.lru.remove(&tick);
+            state.memory -= value.len();
+        }
+    }
+
+    pub(crate) fn stats(&self) -> ReadCacheStats {
+        let state = self.lock();
+        ReadCacheStats {
+            hits: self.inner.hits.load(ORD),
+            misses: self.inner.misses.load(ORD),
+            records: state.values.len(),
+            memory: state.memory,
+        }
+    }
+
+    fn admit(&self, state: &mut State, key: &[u8]) -> bool {
+        match self.inner.admission {
+            ReadCacheAdmission::All => true,
+            ReadCacheAdmission::Frequent => {
+                let hash = key_hash(key);
+                if state.doorkeeper.remove(&hash) {
+                    true
+                } else {
+                    if state.doorkeeper.len() >= DOORKEEPER_CAPACITY {
+                        state.doorkeeper.clear();
+                    }
+                    state.doorkeeper.insert(hash);
+                    false
+                }
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.inner.state.lock().expect("read cache lock poisoned")
+    }
+}
+
+fn key_hash(key: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn epoch_slot(key: &[u8]) -> usize {
+    key_hash(key) as usize % EPOCH_SLOTS
+}
+
+impl Debug for ReadCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("ReadCache")
+            .field("memory_limit", &self.inner.memory_limit)
+            .field("admission", &self.inner.admission)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lru_eviction() {
+        let cache = ReadCache::new(64, ReadCacheAdmission::All);
+        let value = Bytes::from(vec![0; 8]);
+        for key in 0..8_u8 {
+            let epoch = cache.get(&[key]).unwrap_err();
+            cache.insert(&[key], value.clone(), epoch);
+        }
+        assert!(cache.get(&[0]).is_ok());
+        let epoch = cache.get(&[8]).unwrap_err();
+        cache.insert(&[8], value, epoch);
+        // key 1 is the least recently used one
+        assert!(cache.get(&[1]).is_err());
+        assert!(cache.get(&[0]).is_ok());
+        assert!(cache.get(&[8]).is_ok());
+        let stats = cache.stats();
+        assert_eq!(8, stats.records);
+        assert_eq!(64, stats.memory);
+        assert_eq!(3, stats.hits);
+        assert_eq!(10, stats.misses);
+    }
+
+    #[test]
+    fn frequent_admission() {
+        let cache = ReadCache::new(1024, ReadCacheAdmission::Frequent);
+        let value = Bytes::from_static(b"value");
+        let epoch = cache.get(b"key").unwrap_err();
+        cache.insert(b"key", value.clone(), epoch);
+        let epoch = cache.get(b"key").unwrap_err();
+        cache.insert(b"key", value.clone(), epoch);
+        assert_eq!(Ok(value), cache.get(b"key"));
+        // too large for the cache
+        let epoch = cache.get(b"large").unwrap_err();
+        cache.insert(b"large", vec![0; 512].into(), epoch);
+        let epoch = cache.get(b"large").unwrap_err();
+        cache.insert(b"large", vec![0; 512].into(), epoch);
+        assert!(cache.get(b"large").is_err());
+    }
+
+    #[test]
+    fn invalidation() {
+        let cache = ReadCache::new(1024, ReadCacheAdmission::All);
+        let epoch = cache.get(b"key").unwrap_err();
+        cache.insert(b"key", Bytes::from_static(b"old"), epoch);
+        cache.invalidate(b"key");
+        assert!(cache.get(b"key").is_err());
+        assert_eq!(0, cache.stats().memory);
+        // value was read before concurrent write, so it's not cached
+        let epoch = cache.get(b"key").unwrap_err();
+        cache.invalidate(b"key");
+        cache.insert(b"key", Bytes::from_static(b"old"), epoch);
+        assert!(cache.get(b"key").is_err());
+        // writes of other keys don't prevent caching
+        let epoch = cache.get(b"key").unwrap_err();
+        let other = (0_u32..)
+            .map(u32::to_be_bytes)
+            .find(|other| epoch_slot(other) != epoch_slot(b"key"))
+            .unwrap();
+        cache.invalidate(&other);
+        cache.insert(b"key", Bytes::from_static(b"new"), epoch);
+        assert_eq!(Ok(Bytes::from_static(b"new")), cache.get(b"key"));
+    }
+}
@@ -0,0 +1,14 @@
+/// Defines which versions of the record are returned, when the key was written more than once.
+///
+/// Versions are ordered by blob id, then by offset in the blob, then by creation time, so the
+/// latest version is the one, which was written last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadPolicy {
+    /// The latest written version only.
+    #[default]
+    Latest,
+    /// The first written version only.
+    Oldest,
+    /// All versions, the latest goes first.
+    All,
+}
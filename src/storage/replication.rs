@@ -0,0 +1,233 @@
+use super::prelude::*;
+use tokio::sync::mpsc;
+
+const POSITION_FILE_NAME: &str = "replica.position";
+// applied position is saved once per this amount of records and when replica moves to the
+// next blob, so saved position may be behind the storage after restart
+const POSITION_SAVE_INTERVAL: usize = 100;
+
+/// Record shipped from the primary storage to replica together with its position.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicationChunk {
+    position: Position,
+    record: Bytes,
+}
+
+impl ReplicationChunk {
+    pub(crate) fn new(position: Position, record: Bytes) -> Self {
+        Self { position, record }
+    }
+
+    /// Returns position of the record in the primary storage.
+    #[must_use]
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Returns the whole record as it is stored in the blob.
+    #[must_use]
+    pub fn record(&self) -> &Bytes {
+        &self.record
+    }
+}
+
+/// Sending half of replication transport, it is used on the primary storage side.
+#[async_trait::async_trait]
+pub trait ReplicationSender: Send {
+    /// Delivers chunk to replica.
+    async fn send(&mut self, chunk: ReplicationChunk) -> Result<()>;
+}
+
+/// Receiving half of replication transport, it is used on the replica side.
+#[async_trait::async_trait]
+pub trait ReplicationReceiver: Send {
+    /// Returns the next chunk or `None`, if primary storage stopped shipping.
+    async fn recv(&mut self) -> Result<Option<ReplicationChunk>>;
+}
+
+/// Creates in-process replication transport, which buffers at most `buffer` chunks.
+#[must_use]
+pub fn replication_channel(buffer: usize) -> (ChannelSender, ChannelReceiver) {
+    let (sender, receiver) = mpsc::channel(buffer);
+    (ChannelSender(sender), ChannelReceiver(receiver))
+}
+
+/// Sending half of in-process replication transport, see [`replication_channel`].
+#[derive(Debug, Clone)]
+pub struct ChannelSender(mpsc::Sender<ReplicationChunk>);
+
+#[async_trait::async_trait]
+impl ReplicationSender for ChannelSender {
+    async fn send(&mut self, chunk: ReplicationChunk) -> Result<()> {
+        self.0
+            .send(chunk)
+            .await
+            .map_err(|_| Error::replication("replica is disconnected".to_owned()).into())
+    }
+}
+
+/// Receiving half of in-process replication transport, see [`replication_channel`].
+#[derive(Debug)]
+pub struct ChannelReceiver(mpsc::Receiver<ReplicationChunk>);
+
+#[async_trait::async_trait]
+impl ReplicationReceiver for ChannelReceiver {
+    async fn recv(&mut self) -> Result<Option<ReplicationChunk>> {
+        Ok(self.0.recv().await)
+    }
+}
+
+/// Applies records shipped from the primary storage to the replica storage, keeping blob ids
+/// and offsets of the records, so positions of the primary storage are valid for the replica.
+///
+/// Applied position is saved to `replica.position` file in the work dir once per 100 records
+/// and when replica moves to the next blob. On start replica resumes from the saved position,
+/// records applied after it are found in the storage and skipped, when they are shipped again.
+#[derive(Debug)]
+pub struct Replica<K: Key> {
+    storage: Storage<K>,
+    state: Mutex<State>,
+    position_path: PathBuf,
+}
+
+#[derive(Debug)]
+struct State {
+    // position after the last applied record
+    position: Position,
+    // end of the storage at start, records before it are already applied
+    stored: Position,
+    // number of applied records after the saved position
+    unsaved: usize,
+}
+
+impl<K: Key + 'static> Replica<K> {
+    /// Creates replica, which applies records to initialized `storage`, built with
+    /// [`Builder::replica`].
+    /// # Errors
+    /// Fails if storage is not a replica or position file can't be read
+    ///
+    /// [`Builder::replica`]: struct.Builder.html#method.replica
+    pub async fn new(storage: Storage<K>) -> Result<Self> {
+        let config = &storage.inner.config;
+        if !config.replica() {
+            let msg = "storage is not built as a replica".to_owned();
+            return Err(Error::replication(msg).into());
+        }
+        let position_path = config
+            .work_dir()
+            .ok_or_else(Error::uninitialized)?
+            .join(POSITION_FILE_NAME);
+        let end = storage.end_position().await;
+        let position = match Self::load_position(&position_path).await? {
+            Some(saved) if saved <= end => saved,
+            Some(saved) => {
+                // records written before a crash are kept, they were validated on write
+                warn!("replica position {:?} is ahead of storage {:?}", saved, end);
+                end
+            }
+            None => end,
+        };
+        debug!("replica starts at {:?}", position);
+        let state = State {
+            position,
+            stored: end,
+            unsaved: 0,
+        };
+        Ok(Self {
+            storage,
+            state: Mutex::new(state),
+            position_path,
+        })
+    }
+
+    /// Returns position after the last applied record, shipping should be started from it.
+    pub async fn position(&self) -> Position {
+        self.state.lock().await.position
+    }
+
+    /// Returns replica storage, it can be used only for reads.
+    #[must_use]
+    pub fn storage(&self) -> &Storage<K> {
+        &self.storage
+    }
+
+    /// Applies record shipped from the primary storage. Already applied records are skipped,
+    /// so shipping may be restarted from an earlier position.
+    /// # Errors
+    /// Fails if record is corrupted, if it doesn't follow the last applied record or because
+    /// of IO errors
+    pub async fn apply(&self, chunk: ReplicationChunk) -> Result<()> {
+        let ReplicationChunk {
+            position: record_position,
+            record,
+        } = chunk;
+        let mut state = self.state.lock().await;
+        if record_position < state.position {
+            debug!("record at {:?} is already applied", record_position);
+            return Ok(());
+        }
+        if record_position < state.stored {
+            // record was applied before restart, but position wasn't saved
+            debug!("record at {:?} is already stored", record_position);
+            let end = record_position.offset + record.len() as u64;
+            state.position = Position::new(record_position.blob_id, end);
+            return Ok(());
+        }
+        let record = Record::from_raw(record)
+            .with_context(|| format!("invalid record at {:?}", record_position))?;
+        let previous = state.position;
+        state.position = self
+            .storage
+            .write_replicated(record_position, record)
+            .await?;
+        state.unsaved += 1;
+        if previous.blob_id != record_position.blob_id && state.unsaved > 1 {
+            // previous blob is synced, when replica moves to the next one
+            self.save_position(previous).await?;
+            state.unsaved = 1;
+        } else if state.unsaved >= POSITION_SAVE_INTERVAL {
+            self.save_position(state.position).await?;
+            state.unsaved = 0;
+        }
+        Ok(())
+    }
+
+    /// Applies chunks from `receiver`, until primary storage stops shipping, then saves
+    /// applied position.
+    /// # Errors
+    /// Fails if `receiver` fails or chunk can't be applied, see [`apply`]
+    ///
+    /// [`apply`]: Replica::apply
+    pub async fn run(&self, receiver: &mut impl ReplicationReceiver) -> Result<()> {
+        while let Some(chunk) = receiver.recv().await? {
+            self.apply(chunk).await?;
+        }
+        let mut state = self.state.lock().await;
+        if state.unsaved > 0 {
+            self.save_position(state.position).await?;
+            state.unsaved = 0;
+        }
+        Ok(())
+    }
+
+    async fn load_position(path: &Path) -> Result<Option<Position>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let buf = tokio::fs::read(path)
+            .await
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        Ok(Some(deserialize(&buf)?))
+    }
+
+    async fn save_position(&self, position: Position) -> Result<()> {
+        let path = &self.position_path;
+        blob::File::write_atomically(
+            path,
+            &serialize(&position)?,
+            self.storage.inner.ioring.clone(),
+        )
+        .await
+        .with_context(|| format!("failed to save {}", path.display()))
+    }
+}
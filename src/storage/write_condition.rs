@@ -0,0 +1,33 @@
+use super::prelude::*;
+
+/// Version of the latest record with the key, which is expected by
+/// [`Storage::write_if_version`].
+///
+/// [`Storage::write_if_version`]: struct.Storage.html#method.write_if_version
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpectedVersion {
+    /// The latest record has this metadata.
+    Meta(Meta),
+    /// The latest record is placed at `offset` in the blob with id `blob_id`, see
+    /// [`Entry::blob_id`] and [`Entry::blob_offset`].
+    ///
+    /// [`Entry::blob_id`]: struct.Entry.html#method.blob_id
+    /// [`Entry::blob_offset`]: struct.Entry.html#method.blob_offset
+    Offset {
+        /// Id of the blob.
+        blob_id: usize,
+        /// Offset of the record in the blob.
+        offset: u64,
+    },
+}
+
+/// Condition, which is checked under the active blob lock before the record is written.
+#[derive(Debug)]
+pub(crate) enum WriteCondition {
+    /// Record is skipped if duplicates are not allowed and the same record exists.
+    Any,
+    /// Write fails, if any record with the key exists.
+    Absent,
+    /// Write fails, if the latest record with the key doesn't match expected version.
+    Version(ExpectedVersion),
+}
@@ -32,6 +32,7 @@ impl AsRef<KeyTest> for KeyTest {
 
 impl Key for KeyTest {
     const LEN: u16 = 4;
+    const BYTE_ORDERED: bool = true;
 }
 
 impl Default for KeyTest {
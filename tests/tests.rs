@@ -7,11 +7,16 @@ use futures::{
     stream::{futures_unordered::FuturesUnordered, StreamExt, TryStreamExt},
     TryFutureExt,
 };
-use pearl::{BloomProvider, Builder, Meta, Storage};
+use pearl::{
+    blocking, bytes::Bytes, replication_channel, BloomConfig, BloomProvider, Builder,
+    ExpectedVersion, FilterCheckStats, FilterKind, IndexKind, Meta, Position, ReadCacheAdmission,
+    ReadPolicy, Replica, Storage,
+};
 use rand::{seq::SliceRandom, Rng};
 use std::{
     fs,
     hash::Hasher,
+    sync::Arc,
     time::{Duration, Instant},
 };
 use tokio::time::sleep;
@@ -79,6 +84,21 @@ async fn test_storage_read_write() {
     let data = b"test data string";
     write_one(&storage, 1234, data, None).await.unwrap();
     let new_data = storage.read(KeyTest::new(key)).await.unwrap();
+    assert_eq!(new_data, &data[..]);
+    common::clean(storage, path).await.unwrap();
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+}
+
+#[tokio::test]
+async fn test_storage_read_write_bytes() {
+    let now = Instant::now();
+    let path = common::init("read_write_bytes");
+    let storage = common::default_test_storage_in(&path).await.unwrap();
+    let key = KeyTest::new(1234);
+    let data = Bytes::from_static(b"test data bytes");
+    storage.write(&key, data.clone()).await.unwrap();
+    storage.try_close_active_blob().await.unwrap();
+    let new_data = storage.read(&key).await.unwrap();
     assert_eq!(new_data, data);
     common::clean(storage, path).await.unwrap();
     warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
@@ -226,6 +246,247 @@ async fn test_on_disk_index() -> Result<()> {
     common::clean(storage, path).await
 }
 
+#[tokio::test]
+async fn test_on_disk_hash_index() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("hash_index");
+    let builder = || {
+        Builder::new()
+            .work_dir(&path)
+            .blob_file_name_prefix("test")
+            .max_blob_size(10_000)
+            .max_data_in_blob(1_000)
+            .set_filter_config(Default::default())
+            .set_index_kind(IndexKind::Hash)
+            .allow_duplicates()
+    };
+    let mut storage = builder().build().unwrap();
+    storage.init().await.unwrap();
+    let records = common::generate_records(30, 1_000);
+    for (key, data) in &records {
+        sleep(Duration::from_millis(32)).await;
+        write_one(&storage, *key, data, None).await.unwrap();
+    }
+    let duplicated_key = records[0].0;
+    write_one(&storage, duplicated_key, b"duplicate", None)
+        .await
+        .unwrap();
+    storage.close().await.unwrap();
+
+    let mut storage = builder().build().unwrap();
+    storage.init().await.unwrap();
+    assert!(storage.blobs_count().await > 1);
+    for (key, data) in records.iter().skip(1) {
+        let read = storage.read(KeyTest::new(*key)).await.unwrap();
+        assert_eq!(&read, data);
+    }
+    let entries = storage.read_all(&KeyTest::new(duplicated_key)).await?;
+    assert_eq!(entries.len(), 2);
+    assert!(!storage.contains(KeyTest::new(u32::MAX)).await?);
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    common::clean(storage, path).await
+}
+
+#[tokio::test]
+async fn test_index_kind_from_file() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("index_kind_from_file");
+    let builder = |kind| {
+        Builder::new()
+            .work_dir(&path)
+            .blob_file_name_prefix("test")
+            .max_blob_size(10_000)
+            .max_data_in_blob(1_000)
+            .set_filter_config(Default::default())
+            .set_index_kind(kind)
+            .allow_duplicates()
+    };
+    let mut storage = builder(IndexKind::Simple).build().unwrap();
+    storage.init().await.unwrap();
+    let records = common::generate_records(30, 1_000);
+    for (key, data) in &records {
+        sleep(Duration::from_millis(32)).await;
+        write_one(&storage, *key, data, None).await.unwrap();
+    }
+    let duplicated_key = records[0].0;
+    write_one(&storage, duplicated_key, b"duplicate", None)
+        .await
+        .unwrap();
+    storage.close().await.unwrap();
+    let index_path = path.join("test.0.index");
+    let modified = fs::metadata(&index_path)?.modified()?;
+
+    // index files describe their layout themselves, so they are not regenerated
+    let mut storage = builder(IndexKind::Hash).build().unwrap();
+    storage.init().await.unwrap();
+    assert_eq!(modified, fs::metadata(&index_path)?.modified()?);
+    for (key, data) in records.iter().skip(1) {
+        let read = storage.read(KeyTest::new(*key)).await.unwrap();
+        assert_eq!(&read, data);
+    }
+    let entries = storage.read_all(&KeyTest::new(duplicated_key)).await?;
+    assert_eq!(entries.len(), 2);
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    common::clean(storage, path).await
+}
+
+#[tokio::test]
+async fn test_node_cache() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("node_cache");
+    let mut storage = Builder::new()
+        .work_dir(&path)
+        .blob_file_name_prefix("test")
+        .max_blob_size(10_000)
+        .max_data_in_blob(1_000)
+        .node_cache_memory_limit(1_000_000)
+        .build()
+        .unwrap();
+    storage.init().await.unwrap();
+    let records = common::generate_records(30, 1_000);
+    for (key, data) in &records {
+        sleep(Duration::from_millis(32)).await;
+        write_one(&storage, *key, data, None).await.unwrap();
+    }
+    storage.try_close_active_blob().await?;
+    let before = storage.node_cache_stats().unwrap();
+    for _ in 0..2 {
+        for (key, data) in &records {
+            let read = storage.read(KeyTest::new(*key)).await?;
+            assert_eq!(&read, data);
+        }
+    }
+    let stats = storage.node_cache_stats().unwrap();
+    assert!(stats.hits - before.hits >= records.len() as u64);
+    assert!(stats.nodes > 0);
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    common::clean(storage, path).await
+}
+
+#[tokio::test]
+async fn test_read_cache() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("read_cache");
+    let mut storage = Builder::new()
+        .work_dir(&path)
+        .blob_file_name_prefix("test")
+        .max_blob_size(10_000)
+        .max_data_in_blob(1_000)
+        .read_cache(1_000_000, ReadCacheAdmission::All)
+        .allow_duplicates()
+        .build()
+        .unwrap();
+    storage.init().await.unwrap();
+    let key = KeyTest::new(1);
+    storage.write(&key, Bytes::from_static(b"old")).await?;
+    storage.try_close_active_blob().await?;
+    for _ in 0..3 {
+        assert_eq!(storage.read(&key).await?, &b"old"[..]);
+    }
+    let stats = storage.read_cache_stats().unwrap();
+    assert_eq!(stats.misses, 1);
+    assert_eq!(stats.hits, 2);
+    assert_eq!(stats.records, 1);
+    // write drops cached value, so the next read returns the new one
+    storage.write(&key, Bytes::from_static(b"new")).await?;
+    assert_eq!(storage.read_cache_stats().unwrap().records, 0);
+    assert_eq!(storage.read(&key).await?, &b"new"[..]);
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    common::clean(storage, path).await
+}
+
+#[tokio::test]
+async fn test_read_policy() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("read_policy");
+    let builder = || {
+        Builder::new()
+            .work_dir(&path)
+            .blob_file_name_prefix("test")
+            .max_blob_size(10_000)
+            .max_data_in_blob(1_000)
+            .set_filter_config(Default::default())
+            .set_index_kind(IndexKind::Simple)
+            .allow_duplicates()
+    };
+    let mut storage = builder().build().unwrap();
+    storage.init().await.unwrap();
+    let key = KeyTest::new(1);
+    let versions = [&b"v1"[..], b"v2", b"v3", b"v4", b"v5"];
+    for (i, data) in versions.iter().enumerate() {
+        storage.write(&key, data.to_vec()).await?;
+        if i == 2 {
+            storage.try_close_active_blob().await?;
+        }
+    }
+    let (key, versions) = (&key, &versions);
+    let check = |storage: Storage<KeyTest>| async move {
+        assert_eq!(storage.read(&key).await?, b"v5"[..]);
+        let oldest = storage.read_entries(&key, ReadPolicy::Oldest).await?;
+        assert_eq!(oldest.len(), 1);
+        assert_eq!(oldest[0].load_data().await?, b"v1"[..]);
+        let latest = storage.read_entries(&key, ReadPolicy::Latest).await?;
+        assert_eq!(latest[0].load_data().await?, b"v5"[..]);
+        let mut all = Vec::new();
+        for entry in storage.read_entries(&key, ReadPolicy::All).await? {
+            all.push(entry.load_data().await?);
+        }
+        assert!(all.iter().eq(versions.iter().rev()));
+        Ok::<_, anyhow::Error>(storage)
+    };
+    let storage = check(storage).await?;
+    storage.close().await.unwrap();
+    // the same order after the latest versions are moved from memory to index files
+    let mut storage = builder().build().unwrap();
+    storage.init().await.unwrap();
+    let storage = check(storage).await?;
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    common::clean(storage, path).await
+}
+
+#[tokio::test]
+async fn test_mmap_read() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("mmap_read");
+    let builder = || {
+        Builder::new()
+            .work_dir(&path)
+            .blob_file_name_prefix("test")
+            .max_blob_size(10_000)
+            .max_data_in_blob(1_000)
+            .set_filter_config(Default::default())
+            .enable_mmap()
+            .allow_duplicates()
+    };
+    let mut storage = builder().build().unwrap();
+    storage.init().await.unwrap();
+    let records = common::generate_records(30, 1_000);
+    for (key, data) in &records {
+        sleep(Duration::from_millis(32)).await;
+        write_one(&storage, *key, data, None).await.unwrap();
+    }
+    assert!(storage.blobs_count().await > 1);
+    for (key, data) in &records {
+        let entries = storage.read_all(&KeyTest::new(*key)).await?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(&entries[0].load_data().await?, data);
+    }
+    storage.close().await.unwrap();
+
+    let mut storage = builder().build().unwrap();
+    storage.init().await.unwrap();
+    // closed blobs are mapped on open
+    let maps = fs::read_to_string("/proc/self/maps")?;
+    let blob = fs::canonicalize(path.join("test.0.blob"))?;
+    assert!(maps.contains(blob.to_str().unwrap()));
+    for (key, data) in &records {
+        let read = storage.read(KeyTest::new(*key)).await?;
+        assert_eq!(&read, data);
+    }
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    common::clean(storage, path).await
+}
+
 #[tokio::test]
 async fn test_work_dir_lock() {
     use nix::sys::wait::{waitpid, WaitStatus};
@@ -408,16 +669,1054 @@ async fn test_read_with() {
     write_one(&storage, key, data, Some("2.0")).await.unwrap();
     debug!("second data written");
     let key = KeyTest::new(key);
-    let data_read_with = storage.read_with(&key, &meta_with("2.0")).await.unwrap();
+    let data_read_with = storage.read_with(&key, &meta_with("1.0")).await.unwrap();
     debug!("read with finished");
     let data_read = storage.read(&key).await.unwrap();
     debug!("read finished");
     assert_ne!(data_read_with, data_read);
-    assert_eq!(data_read_with, data);
+    // read returns the latest version
+    assert_eq!(data_read, &data[..]);
     common::clean(storage, path).await.expect("clean failed");
     warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
 }
 
+#[tokio::test]
+async fn test_write_if_absent() -> Result<()> {
+    use pearl::error::AsPearlError;
+
+    let now = Instant::now();
+    let path = common::init("write_if_absent");
+    let storage = common::create_test_storage(&path, 1_000_000).await.unwrap();
+    let key = KeyTest::new(1);
+    let results = (0..16_u8)
+        .map(|i| {
+            let storage = storage.clone();
+            let key = key.clone();
+            tokio::spawn(async move { storage.write_if_absent(key, vec![i; 16]).await })
+        })
+        .collect::<FuturesUnordered<_>>()
+        .try_collect::<Vec<_>>()
+        .await?;
+    let (written, failed): (Vec<_>, Vec<_>) = results.into_iter().partition(Result::is_ok);
+    assert_eq!(written.len(), 1);
+    for err in failed.into_iter().filter_map(Result::err) {
+        let kind = err.as_pearl_error().map(pearl::Error::kind);
+        assert!(matches!(kind, Some(pearl::ErrorKind::RecordExists)));
+    }
+    assert_eq!(storage.read_all(&key).await?.len(), 1);
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    common::clean(storage, path).await
+}
+
+#[tokio::test]
+async fn test_write_if_version() -> Result<()> {
+    use pearl::error::AsPearlError;
+
+    let now = Instant::now();
+    let path = common::init("write_if_version");
+    let storage = common::create_test_storage(&path, 1_000_000).await.unwrap();
+    let key = KeyTest::new(1);
+    let kind = |res: Result<()>| {
+        res.expect_err("conflicting write succeeded")
+            .as_pearl_error()
+            .map(|e| e.kind().clone())
+    };
+    let expected = ExpectedVersion::Meta(meta_with("1"));
+    let res = storage
+        .write_with_if_version(&key, b"v2".to_vec(), meta_with("2"), expected.clone())
+        .await;
+    assert!(matches!(kind(res), Some(pearl::ErrorKind::RecordNotFound)));
+    write_one(&storage, 1, b"v1", Some("1")).await?;
+    storage
+        .write_with_if_version(&key, b"v2".to_vec(), meta_with("2"), expected.clone())
+        .await?;
+    // the latest version is "2" now
+    let res = storage
+        .write_with_if_version(&key, b"v3".to_vec(), meta_with("3"), expected)
+        .await;
+    assert!(matches!(kind(res), Some(pearl::ErrorKind::RecordExists)));
+    let expected = ExpectedVersion::Offset {
+        blob_id: 0,
+        offset: 0,
+    };
+    let res = storage
+        .write_if_version(&key, b"v3".to_vec(), expected)
+        .await;
+    assert!(matches!(kind(res), Some(pearl::ErrorKind::RecordExists)));
+    let latest = &storage.read_entries(&key, ReadPolicy::Latest).await?[0];
+    let expected = ExpectedVersion::Offset {
+        blob_id: latest.blob_id(),
+        offset: latest.blob_offset(),
+    };
+    storage
+        .write_if_version(&key, b"v3".to_vec(), expected)
+        .await?;
+    assert_eq!(storage.read(&key).await?, b"v3"[..]);
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    common::clean(storage, path).await
+}
+
+#[tokio::test]
+async fn test_find_by_meta() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("find_by_meta");
+    let builder = || {
+        Builder::new()
+            .work_dir(&path)
+            .blob_file_name_prefix("test")
+            .max_blob_size(1_000_000)
+            .max_data_in_blob(1_000)
+            .set_filter_config(Default::default())
+            .meta_index("version")
+            .allow_duplicates()
+    };
+    let mut storage = builder().build().unwrap();
+    storage.init().await.unwrap();
+    for key in 0..10 {
+        let version = if key % 2 == 0 { "even" } else { "odd" };
+        write_one(&storage, key, b"data", Some(version)).await?;
+        if key == 4 {
+            storage.try_close_active_blob().await?;
+        }
+    }
+    let check = |storage: Storage<KeyTest>| async move {
+        let found = storage.find_by_meta("version", "even").await?;
+        let keys: Vec<_> = found.into_iter().map(|(key, _)| key).collect();
+        let expected: Vec<_> = [8, 6, 4, 2, 0].iter().map(|k| KeyTest::new(*k)).collect();
+        assert_eq!(keys, expected);
+        let (_, entry) = &storage.find_by_meta("version", "odd").await?[0];
+        assert_eq!(entry.load_data().await?, b"data"[..]);
+        assert!(storage.find_by_meta("version", "none").await?.is_empty());
+        assert!(storage.find_by_meta("other", "even").await.is_err());
+        Ok::<_, anyhow::Error>(storage)
+    };
+    let storage = check(storage).await?;
+    storage.close().await.unwrap();
+    assert!(path.join("test.0.meta_index").exists());
+
+    // meta index is regenerated, if its file is lost
+    fs::remove_file(path.join("test.0.meta_index"))?;
+    let mut storage = builder().build().unwrap();
+    storage.init().await.unwrap();
+    assert!(path.join("test.0.meta_index").exists());
+    let storage = check(storage).await?;
+    storage.close().await.unwrap();
+
+    // meta index saved with other blob index is outdated and regenerated
+    fs::copy(
+        path.join("test.1.meta_index"),
+        path.join("test.0.meta_index"),
+    )?;
+    let mut storage = builder().build().unwrap();
+    storage.init().await.unwrap();
+    let storage = check(storage).await?;
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    common::clean(storage, path).await
+}
+
+#[tokio::test]
+async fn test_entry_accessors() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("entry_accessors");
+    let storage = common::create_test_storage(&path, 1_000_000).await.unwrap();
+    write_one(&storage, 1, b"first", None).await?;
+    storage.try_close_active_blob().await?;
+    write_one(&storage, 1, b"second record", Some("2")).await?;
+    let entries = storage.read_all(&KeyTest::new(1)).await?;
+    assert_eq!(entries.len(), 2);
+    let (latest, oldest) = (&entries[0], &entries[1]);
+    assert_eq!(latest.blob_id(), 1);
+    assert_eq!(oldest.blob_id(), 0);
+    assert!(latest.timestamp() >= oldest.timestamp());
+    assert!(oldest.timestamp() > 0);
+    assert_eq!(latest.data_size(), 13);
+    assert_eq!(oldest.data_size(), 5);
+    assert!(latest.meta_size() > oldest.meta_size());
+    assert!(oldest.blob_offset() > 0);
+    assert_ne!(latest.data_checksum(), oldest.data_checksum());
+    assert_ne!(latest.header_checksum(), 0);
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    common::clean(storage, path).await
+}
+
+#[tokio::test]
+async fn test_namespaces() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("namespaces");
+    let builder = || {
+        Builder::new()
+            .blob_file_name_prefix("ns")
+            .max_blob_size(10_000)
+            .max_data_in_blob(1_000)
+            .set_filter_config(Default::default())
+            .allow_duplicates()
+    };
+    let storage = common::create_test_storage(&path, 1_000_000).await.unwrap();
+    assert!(storage
+        .create_namespace::<KeyTest>("", builder())
+        .await
+        .is_err());
+    assert!(storage
+        .create_namespace::<KeyTest>("a/b", builder())
+        .await
+        .is_err());
+    let first = storage
+        .create_namespace::<KeyTest>("first", builder())
+        .await?;
+    let second = storage
+        .create_namespace::<KeyTest>("second", builder())
+        .await?;
+    assert!(storage
+        .create_namespace::<KeyTest>("first", builder())
+        .await
+        .is_err());
+    let mut names = storage.namespaces().await;
+    names.sort();
+    assert_eq!(names, ["first", "second"]);
+
+    write_one(&storage, 1, b"root", None).await?;
+    for i in 0..20 {
+        write_one(&first, i, &[1; 1_000], None).await?;
+    }
+    write_one(&second, 1, b"second", None).await?;
+    assert_eq!(storage.read(KeyTest::new(1)).await?, b"root"[..]);
+    assert_eq!(second.read(KeyTest::new(1)).await?, b"second"[..]);
+    assert!(!storage.contains(KeyTest::new(2)).await?);
+    assert!(!second.contains(KeyTest::new(2)).await?);
+    // namespace uses its own blob size limit, blobs are rotated by the shared observer
+    let mut blobs_count = first.blobs_count().await;
+    for _ in 0..100 {
+        if blobs_count > 1 {
+            break;
+        }
+        sleep(Duration::from_millis(10)).await;
+        blobs_count = first.blobs_count().await;
+    }
+    assert!(blobs_count > 1);
+    assert_eq!(storage.blobs_count().await, 1);
+    let handle = storage.namespace::<KeyTest>("first").await.unwrap();
+    assert_eq!(handle.read(KeyTest::new(1)).await?, [1; 1_000][..]);
+    assert!(storage.namespace::<KeyTest>("third").await.is_none());
+
+    storage.drop_namespace("second").await?;
+    assert!(!path.join("namespaces/second").exists());
+    assert!(storage.drop_namespace("second").await.is_err());
+    assert_eq!(storage.namespaces().await, ["first"]);
+    storage.close().await?;
+    assert!(path.join("namespaces/first/ns.0.blob").exists());
+
+    let storage = common::default_test_storage_in(&path).await.unwrap();
+    assert!(storage.namespaces().await.is_empty());
+    let first = storage
+        .create_namespace::<KeyTest>("first", builder())
+        .await?;
+    for i in 0..20 {
+        assert_eq!(first.read(KeyTest::new(i)).await?, [1; 1_000][..]);
+    }
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    common::clean(storage, path).await
+}
+
+#[tokio::test]
+async fn test_change_feed() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("change_feed");
+    let storage = common::create_test_storage(&path, 1_000_000).await.unwrap();
+    let mut events = Box::pin(storage.subscribe());
+    write_one(&storage, 1, b"first", None).await?;
+    write_one(&storage, 2, b"second", None).await?;
+    storage.try_close_active_blob().await?;
+    write_one(&storage, 3, b"third", None).await?;
+    let mut positions = Vec::new();
+    for (key, data) in [(1, &b"first"[..]), (2, b"second"), (3, b"third")] {
+        let event = events.next().await.unwrap()?;
+        assert_eq!(event.key(), &KeyTest::new(key));
+        assert_eq!(event.entry().load_data().await?, data);
+        assert_eq!(event.position().blob_id, event.entry().blob_id());
+        positions.push(event.position());
+    }
+    assert!(positions.windows(2).all(|p| p[0] < p[1]));
+    assert_eq!(positions[2].blob_id, 1);
+
+    let replayed: Vec<_> = storage
+        .tail_from(Position::default())
+        .await?
+        .try_collect()
+        .await?;
+    let replayed: Vec<_> = replayed.iter().map(|event| event.position()).collect();
+    assert_eq!(replayed, positions);
+    let replayed: Vec<_> = storage.tail_from(positions[1]).await?.try_collect().await?;
+    let keys: Vec<_> = replayed.iter().map(|event| event.key().clone()).collect();
+    assert_eq!(keys, [KeyTest::new(2), KeyTest::new(3)]);
+    let mut wrong = positions[1];
+    wrong.offset += 1;
+    let replayed: Vec<_> = storage.tail_from(wrong).await?.collect().await;
+    assert!(replayed[0].is_err());
+    common::close_storage(storage).await?;
+    // subscription ends with the storage
+    assert!(events.next().await.is_none());
+
+    // consumer resumes from the saved position after restart
+    let storage = common::create_test_storage(&path, 1_000_000).await.unwrap();
+    let replayed: Vec<_> = storage.tail_from(positions[2]).await?.try_collect().await?;
+    assert_eq!(replayed.len(), 1);
+    assert_eq!(replayed[0].entry().load_data().await?, b"third"[..]);
+    // long tail is read by pages
+    for key in 4..2_504 {
+        write_one(&storage, key, b"data", None).await?;
+    }
+    let replayed: Vec<_> = storage.tail_from(positions[2]).await?.try_collect().await?;
+    let keys: Vec<_> = replayed.iter().map(|event| event.key().clone()).collect();
+    let expected: Vec<_> = (3..2_504).map(KeyTest::new).collect();
+    assert_eq!(keys, expected);
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    common::clean(storage, path).await
+}
+
+#[tokio::test]
+async fn test_replication() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("replication");
+    let replica_path = common::init("replication_replica");
+    let storage = common::create_test_storage(&path, 10_000).await.unwrap();
+    // replica limits are ignored, its blobs follow blobs of the primary
+    let open_replica = || async {
+        let mut replica_storage = Builder::new()
+            .work_dir(&replica_path)
+            .blob_file_name_prefix("test")
+            .max_blob_size(1_000)
+            .max_data_in_blob(10)
+            .set_filter_config(Default::default())
+            .allow_duplicates()
+            .replica()
+            .build::<KeyTest>()
+            .unwrap();
+        replica_storage.init().await.unwrap();
+        Arc::new(Replica::new(replica_storage).await.unwrap())
+    };
+    let start_shipping = |replica: Arc<Replica<KeyTest>>, from: Position| {
+        let (mut sender, mut receiver) = replication_channel(16);
+        let primary = storage.clone();
+        let shipping = tokio::spawn(async move { primary.replicate(from, &mut sender).await });
+        let applying = tokio::spawn(async move { replica.run(&mut receiver).await });
+        (shipping, applying)
+    };
+    let wait_for = |replica: Arc<Replica<KeyTest>>, key: u32| async move {
+        for _ in 0..500 {
+            if replica.storage().contains(KeyTest::new(key)).await.unwrap() {
+                return;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+        panic!("key {} is not replicated", key);
+    };
+
+    let replica = open_replica().await;
+    assert_eq!(replica.position().await.blob_id, 0);
+    let (shipping, applying) = start_shipping(replica.clone(), replica.position().await);
+    for key in 0..30 {
+        write_one(&storage, key, &[key as u8; 1_000], None).await?;
+        sleep(Duration::from_millis(10)).await;
+    }
+    wait_for(replica.clone(), 29).await;
+    assert!(storage.blobs_count().await > 1);
+    for key in 0..30 {
+        let data = replica.storage().read(KeyTest::new(key)).await?;
+        assert_eq!(data, [key as u8; 1_000][..]);
+    }
+    // replica starts blob with its first record, primary may already have the next empty one
+    let replica_blobs = replica.storage().blobs_count().await;
+    let blob_size = |dir: &std::path::Path, id| {
+        fs::metadata(dir.join(format!("test.{}.blob", id))).map(|m| m.len())
+    };
+    for id in 0..storage.blobs_count().await {
+        if id < replica_blobs {
+            assert_eq!(blob_size(&replica_path, id)?, blob_size(&path, id)?);
+        } else {
+            assert_eq!(id, replica_blobs);
+            assert!(blob_size(&path, id)? < 100);
+        }
+    }
+    assert!(replica
+        .storage()
+        .write(KeyTest::new(100), b"data".to_vec())
+        .await
+        .is_err());
+    shipping.abort();
+    applying.abort();
+    let _ = applying.await;
+    let position = replica.position().await;
+    replica.storage().clone().close().await?;
+
+    // replica resumes after restart from the position saved at the start of the last blob,
+    // records shipped once more are skipped
+    let replica = open_replica().await;
+    let resumed = replica.position().await;
+    assert!(resumed < position);
+    assert_eq!(resumed.blob_id + 1, position.blob_id);
+    let (shipping, applying) = start_shipping(replica.clone(), resumed);
+    write_one(&storage, 30, b"after restart", None).await?;
+    wait_for(replica.clone(), 30).await;
+    assert_eq!(replica.storage().records_count().await, 31);
+    assert_eq!(
+        replica.storage().read(KeyTest::new(30)).await?,
+        b"after restart"[..]
+    );
+    shipping.abort();
+    applying.abort();
+    let _ = applying.await;
+    replica.storage().clone().close().await?;
+
+    // records shipped from the very beginning are skipped too
+    let replica = open_replica().await;
+    let (shipping, applying) = start_shipping(replica.clone(), Position::default());
+    write_one(&storage, 31, b"after second restart", None).await?;
+    wait_for(replica.clone(), 31).await;
+    assert_eq!(replica.storage().records_count().await, 32);
+    shipping.abort();
+    applying.abort();
+    let _ = applying.await;
+    replica.storage().clone().close().await?;
+    fs::remove_dir_all(replica_path)?;
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    common::clean(storage, path).await
+}
+
+#[tokio::test]
+async fn test_digest() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("digest");
+    let copy_path = common::init("digest_copy");
+    let storage = common::create_test_storage(&path, 10_000).await.unwrap();
+    for key in 0..20 {
+        write_one(&storage, key, &[key as u8; 1_000], None).await?;
+        if key == 9 {
+            storage.try_close_active_blob().await?;
+        }
+    }
+    storage.close().await?;
+    fs::create_dir_all(&copy_path)?;
+    for file in fs::read_dir(&path)? {
+        let file = file?;
+        if file.file_type()?.is_file() {
+            fs::copy(file.path(), copy_path.join(file.file_name()))?;
+        }
+    }
+    let storage = common::default_test_storage_in(&path).await.unwrap();
+    let copy = common::default_test_storage_in(&copy_path).await.unwrap();
+    let digest = storage.digest().await?;
+    let copy_digest = copy.digest().await?;
+    assert_eq!(digest, copy_digest);
+    assert!(digest.blobs().len() > 1);
+    assert_eq!(
+        digest
+            .blobs()
+            .values()
+            .map(|blob| blob.records_count())
+            .sum::<usize>(),
+        20
+    );
+
+    let key = KeyTest::new(0x1234_5678);
+    write_one(&copy, 0x1234_5678, b"only in copy", None).await?;
+    let copy_digest = copy.digest().await?;
+    assert_ne!(digest.root(), copy_digest.root());
+    let diff = digest.diff(&copy_digest);
+    assert_eq!(diff.len(), 1);
+    assert!(diff[0].contains(key.as_ref()));
+    assert!(!diff[0].contains(KeyTest::new(1).as_ref()));
+    let active_id = copy.next_blob_id() - 1;
+    assert_eq!(digest.diff_blobs(&copy_digest), [active_id]);
+    common::clean(copy, copy_path).await?;
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    common::clean(storage, path).await
+}
+
+#[tokio::test]
+async fn test_digest_index_kinds() -> Result<()> {
+    let now = Instant::now();
+    for kind in [IndexKind::Simple, IndexKind::Hash, IndexKind::BPTree] {
+        let path = common::init(&format!("digest_{:?}", kind));
+        let builder = || {
+            Builder::new()
+                .work_dir(&path)
+                .blob_file_name_prefix("test")
+                .max_blob_size(10_000)
+                .max_data_in_blob(1_000)
+                .set_filter_config(Default::default())
+                .set_index_kind(kind)
+        };
+        let mut storage = builder().build().unwrap();
+        storage.init().await.unwrap();
+        for key in 0..30 {
+            write_one(&storage, key, &[key as u8; 1_000], None).await?;
+            if key % 10 == 9 {
+                storage.try_close_active_blob().await?;
+            }
+        }
+        storage.close().await?;
+        let mut storage = builder().build().unwrap();
+        storage.init().await.unwrap();
+        // headers are read from index files of closed blobs
+        let digest = storage.digest().await?;
+        assert!(digest.blobs().len() > 1);
+        let count: usize = digest.blobs().values().map(|b| b.records_count()).sum();
+        assert_eq!(count, 30);
+        common::clean(storage, path).await?;
+    }
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    Ok(())
+}
+
+#[test]
+fn test_blocking_storage() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("blocking");
+    let builder = || {
+        let builder = Builder::new()
+            .work_dir(&path)
+            .blob_file_name_prefix("test")
+            .max_blob_size(10_000)
+            .max_data_in_blob(100_000)
+            .set_filter_config(Default::default())
+            .allow_duplicates();
+        if let Ok(ioring) = rio::new() {
+            builder.enable_aio(ioring)
+        } else {
+            builder
+        }
+    };
+    let mut storage = blocking::Storage::<KeyTest>::new(builder())?;
+    storage.init()?;
+    storage.write(KeyTest::new(1), b"first".to_vec())?;
+    storage.write_with(KeyTest::new(1), b"second".to_vec(), meta_with("2"))?;
+    for key in 2..20 {
+        storage.write(KeyTest::new(key), vec![key as u8; 1_000])?;
+    }
+    assert_eq!(storage.read(KeyTest::new(1))?, b"second"[..]);
+    assert_eq!(
+        storage.read_with(KeyTest::new(1), &meta_with("2"))?,
+        b"second"[..]
+    );
+    assert_eq!(
+        storage.read_all(KeyTest::new(1))?,
+        [&b"second"[..], b"first"]
+    );
+    assert!(storage.contains(KeyTest::new(19))?);
+    assert!(!storage.contains(KeyTest::new(20))?);
+    assert_eq!(storage.records_count(), 20);
+    // blobs are rotated by the runtime workers between calls
+    let mut blobs_count = storage.blobs_count();
+    for _ in 0..100 {
+        if blobs_count > 1 {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+        blobs_count = storage.blobs_count();
+    }
+    assert!(blobs_count > 1);
+    storage.close()?;
+
+    // storage on the caller's runtime
+    let runtime = tokio::runtime::Runtime::new()?;
+    let mut storage =
+        blocking::Storage::<KeyTest>::with_handle(builder(), runtime.handle().clone())?;
+    storage.init()?;
+    assert_eq!(storage.records_count(), 20);
+    assert_eq!(storage.read(KeyTest::new(5))?, [5; 1_000][..]);
+    storage.close()?;
+    fs::remove_dir_all(path)?;
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_filter_memory_limit() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("filter_memory_limit");
+    let storage = common::create_test_storage(&path, 10_000).await.unwrap();
+    for key in 0..40 {
+        write_one(&storage, key, b"filter_memory_limit", None).await?;
+        if key % 10 == 9 {
+            storage.try_close_active_blob().await?;
+        }
+    }
+    storage.close().await?;
+    let filter_size = pearl::Bloom::new(Default::default()).memory_allocated();
+    let limit = filter_size * 7 / 2;
+    let mut storage: Storage<KeyTest> = Builder::new()
+        .work_dir(&path)
+        .blob_file_name_prefix("test")
+        .max_blob_size(10_000)
+        .max_data_in_blob(100_000)
+        .set_filter_config(Default::default())
+        .filter_memory_limit(limit)
+        .build()?;
+    storage.init().await?;
+    // closed blobs with loaded bloom filters, offloaded filters have no fill ratio,
+    // the last blob is restored as active
+    let loaded = |stats: pearl::FilterStats| -> Vec<usize> {
+        stats
+            .blobs
+            .iter()
+            .filter(|blob| blob.blob_id < 3 && blob.fill_ratio.is_some())
+            .map(|blob| blob.blob_id)
+            .collect()
+    };
+    // limit is applied on init: active blob and group filters are kept, only the newest
+    // closed blob filter fits
+    assert_eq!(storage.filter_memory_allocated().await, filter_size * 3);
+    assert_eq!(loaded(storage.filter_stats().await), [2]);
+    // observer applies the limit periodically, so lookups go on until filters are swapped
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        for key in 0..10 {
+            assert!(storage.contains(KeyTest::new(key)).await?);
+        }
+        if loaded(storage.filter_stats().await) == [0] || Instant::now() > deadline {
+            break;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+    // the oldest blob became hot, its filter replaced the filter of the newest one
+    assert!(storage.filter_memory_allocated().await <= limit);
+    assert_eq!(loaded(storage.filter_stats().await), [0]);
+    for key in 0..40 {
+        assert_eq!(storage.check_filters(KeyTest::new(key)).await, Some(true));
+    }
+    common::clean(storage, path).await?;
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_static_filters() -> Result<()> {
+    let now = Instant::now();
+    for kind in [FilterKind::BlockedBloom, FilterKind::Xor] {
+        let path = common::init(&format!("static_filters_{:?}", kind));
+        let builder = || {
+            Builder::new()
+                .work_dir(&path)
+                .blob_file_name_prefix("test")
+                .max_blob_size(100_000)
+                .max_data_in_blob(100_000)
+                .set_filter_config(BloomConfig {
+                    kind,
+                    ..Default::default()
+                })
+        };
+        let mut storage: Storage<KeyTest> = builder().build()?;
+        storage.init().await?;
+        for key in 0..300 {
+            write_one(&storage, key, b"static_filters", None).await?;
+            if key % 100 == 99 {
+                storage.try_close_active_blob().await?;
+            }
+        }
+        storage.close().await?;
+        let mut storage: Storage<KeyTest> = builder().build()?;
+        storage.init().await?;
+        write_one(&storage, 300, b"static_filters", None).await?;
+        for key in 0..301 {
+            assert_eq!(storage.check_filters(KeyTest::new(key)).await, Some(true));
+            assert!(storage.contains(KeyTest::new(key)).await?);
+        }
+        let mut false_positives = 0;
+        for key in 1_000..11_000 {
+            if storage.check_filters(KeyTest::new(key)).await == Some(true) {
+                false_positives += 1;
+            }
+        }
+        assert!(false_positives < 200, "{:?}: {}", kind, false_positives);
+        // closed blobs keep only small static filters, the active one has bloom filter
+        let bloom_size = pearl::Bloom::new(Default::default()).memory_allocated();
+        assert!(storage.filter_memory_allocated().await < bloom_size * 2);
+        common::clean(storage, path).await?;
+    }
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_saved_group_filters() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("saved_group_filters");
+    let builder = || {
+        Builder::new()
+            .work_dir(&path)
+            .blob_file_name_prefix("test")
+            .max_blob_size(10_000)
+            .max_data_in_blob(100_000)
+            .set_filter_config(Default::default())
+            .set_bloom_filter_group_size(2)
+    };
+    let mut storage: Storage<KeyTest> = builder().build()?;
+    storage.init().await?;
+    for key in 0..50 {
+        write_one(&storage, key, b"saved_group_filters", None).await?;
+        if key % 10 == 9 {
+            storage.try_close_active_blob().await?;
+        }
+    }
+    sleep(Duration::from_millis(300)).await;
+    storage.close().await?;
+    // blobs 0 to 4 are closed, so there are two full groups
+    let group_filter = |id| path.join(format!("test.{}.group_filter", id));
+    assert!(group_filter(0).exists());
+    assert!(group_filter(2).exists());
+    assert!(!group_filter(4).exists());
+
+    let filter_size = pearl::Bloom::new(Default::default()).memory_allocated();
+    let mut storage: Storage<KeyTest> = builder().build()?;
+    storage.init().await?;
+    // blob 4 becomes active, filters of blobs from saved groups aren't loaded,
+    // so only active blob filter and filters of two groups and root are in memory
+    assert_eq!(storage.filter_memory_allocated().await, filter_size * 4);
+    for key in 0..50 {
+        assert_eq!(storage.check_filters(KeyTest::new(key)).await, Some(true));
+        assert!(storage.contains(KeyTest::new(key)).await?);
+    }
+    storage.close().await?;
+
+    fs::write(group_filter(2), b"corrupted")?;
+    let mut storage: Storage<KeyTest> = builder().build()?;
+    storage.init().await?;
+    // filter of the second group is rebuilt from filters of blobs 2 and 3 and saved again
+    assert_eq!(storage.filter_memory_allocated().await, filter_size * 6);
+    assert!(fs::metadata(group_filter(2))?.len() > filter_size as u64);
+    for key in 0..50 {
+        assert_eq!(storage.check_filters(KeyTest::new(key)).await, Some(true));
+        assert!(storage.contains(KeyTest::new(key)).await?);
+    }
+    storage.close().await?;
+
+    // file of other format version is rebuilt, version follows 8 bytes of magic
+    let version = |buf: &[u8]| u32::from_le_bytes([buf[8], buf[9], buf[10], buf[11]]);
+    let mut buf = fs::read(group_filter(0))?;
+    buf[8..12].copy_from_slice(&0_u32.to_le_bytes());
+    fs::write(group_filter(0), &buf)?;
+    let mut storage: Storage<KeyTest> = builder().build()?;
+    storage.init().await?;
+    assert_eq!(storage.filter_memory_allocated().await, filter_size * 6);
+    assert_eq!(version(&fs::read(group_filter(0))?), 1);
+    storage.close().await?;
+
+    fs::remove_file(group_filter(0))?;
+    fs::remove_file(group_filter(2))?;
+    let mut storage: Storage<KeyTest> = builder().save_group_filters(false).build()?;
+    storage.init().await?;
+    for key in 0..50 {
+        assert!(storage.contains(KeyTest::new(key)).await?);
+    }
+    assert!(!group_filter(0).exists());
+    assert!(!group_filter(2).exists());
+    common::clean(storage, path).await?;
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_filter_stats() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("filter_stats");
+    let mut storage: Storage<KeyTest> = Builder::new()
+        .work_dir(&path)
+        .blob_file_name_prefix("test")
+        .max_blob_size(10_000)
+        .max_data_in_blob(100_000)
+        .set_filter_config(Default::default())
+        .set_bloom_filter_group_size(2)
+        .build()?;
+    storage.init().await?;
+    for key in 0..40 {
+        write_one(&storage, key, b"filter_stats", None).await?;
+        if key % 10 == 9 {
+            storage.try_close_active_blob().await?;
+        }
+    }
+    for key in 0..40 {
+        assert!(storage.contains(KeyTest::new(key)).await?);
+    }
+    for key in 1_000..3_000 {
+        assert!(!storage.contains(KeyTest::new(key)).await?);
+    }
+    let stats = storage.filter_stats().await;
+    assert_eq!(stats.blobs.len(), 4);
+    let passed: u64 = stats.blobs.iter().map(|blob| blob.checks.passed).sum();
+    let false_positives: u64 = stats
+        .blobs
+        .iter()
+        .map(|blob| blob.checks.false_positives)
+        .sum();
+    // every passed key is either found or counted as false positive
+    assert_eq!(passed - false_positives, 40);
+    for blob in &stats.blobs {
+        let fill_ratio = blob.fill_ratio.expect("filter is in memory");
+        assert!(fill_ratio > 0.0 && fill_ratio < 0.01, "{}", fill_ratio);
+        assert!(blob.estimated_false_positive_rate.unwrap() < fill_ratio);
+        let rate = blob
+            .checks
+            .observed_false_positive_rate()
+            .unwrap_or_default();
+        assert!(rate < 0.05, "{:?}", blob);
+    }
+    // root and two group nodes, absent keys are mostly rejected by group filters
+    assert_eq!(stats.levels.len(), 2);
+    assert_eq!(stats.levels[0].nodes, 1);
+    assert_eq!(stats.levels[1].nodes, 2);
+    assert!(stats.levels[1].lookups >= 2_000);
+    assert!(stats.levels[1].rejected > 3_000, "{:?}", stats.levels[1]);
+    common::clean(storage, path).await?;
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_multi_range_filters() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("multi_range_filters");
+    let builder = || {
+        Builder::new()
+            .work_dir(&path)
+            .blob_file_name_prefix("test")
+            .max_blob_size(10_000)
+            .max_data_in_blob(100_000)
+            .set_filter_config(Default::default())
+            .set_bloom_filter_group_size(2)
+    };
+    let mut storage: Storage<KeyTest> = builder().build()?;
+    storage.init().await?;
+    // every blob holds two separate key ranges, so min and max of blobs cover absent keys
+    for blob in 0..5 {
+        for key in (0..10).chain(10_000..10_010) {
+            write_one(&storage, blob * 10 + key, b"multi_range_filters", None).await?;
+        }
+        storage.try_close_active_blob().await?;
+    }
+    sleep(Duration::from_millis(300)).await;
+    storage.close().await?;
+
+    let mut storage: Storage<KeyTest> = builder().build()?;
+    storage.init().await?;
+    for key in 1_000..3_000 {
+        assert!(!storage.contains(KeyTest::new(key)).await?);
+    }
+    let stats = storage.filter_stats().await;
+    // absent keys are rejected by range filters of both groups and of the active blob,
+    // filters of closed blobs aren't checked
+    assert_eq!(stats.levels[1].rejected, 4_000);
+    let (closed, active) = stats.blobs.split_at(4);
+    assert!(closed
+        .iter()
+        .all(|blob| blob.checks == FilterCheckStats::default()));
+    assert_eq!(active[0].checks.range_rejected, 2_000);
+    assert_eq!(active[0].checks.bloom_rejected, 0);
+    for key in (0..50).chain(10_000..10_050) {
+        assert!(storage.contains(KeyTest::new(key)).await?);
+    }
+    common::clean(storage, path).await?;
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_scan_prefix() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("scan_prefix");
+    let builder = || {
+        Builder::new()
+            .work_dir(&path)
+            .blob_file_name_prefix("test")
+            .max_blob_size(100_000)
+            .max_data_in_blob(100_000)
+            .set_filter_config(Default::default())
+            .set_bloom_filter_group_size(2)
+            .key_prefix_len(2)
+    };
+    // first two bytes of the key are tenant id, every blob holds keys of tenants 4 * b
+    // and 4 * b + 2
+    let key = |tenant: u32, id: u32| (tenant << 16) + id;
+    let mut storage: Storage<KeyTest> = builder().build()?;
+    storage.init().await?;
+    for blob in 0..5 {
+        for tenant in [4 * blob, 4 * blob + 2] {
+            for id in (0..20).rev() {
+                write_one(&storage, key(tenant, id), b"scan_prefix", None).await?;
+            }
+        }
+        storage.try_close_active_blob().await?;
+    }
+    let tenant_keys = |tenant: u32| (0..20).map(move |id| KeyTest::new(key(tenant, id)));
+    let prefix = |tenant: u32| (tenant as u16).to_be_bytes();
+    let expected: Vec<_> = tenant_keys(6).collect();
+    assert_eq!(storage.scan_prefix(prefix(6)).await?, expected);
+    sleep(Duration::from_millis(300)).await;
+    storage.close().await?;
+
+    let mut storage: Storage<KeyTest> = builder().build()?;
+    storage.init().await?;
+    for blob in 0..5 {
+        for tenant in [4 * blob, 4 * blob + 2] {
+            let expected: Vec<_> = tenant_keys(tenant).collect();
+            assert_eq!(storage.scan_prefix(prefix(tenant)).await?, expected);
+        }
+        assert!(storage.scan_prefix(prefix(4 * blob + 1)).await?.is_empty());
+    }
+    // short prefix selects keys of all tenants with the same first byte
+    assert_eq!(storage.scan_prefix([0]).await?.len(), 200);
+    // groups without keys with the prefix are skipped
+    let stats = storage.filter_stats().await;
+    assert!(stats.levels[1].rejected >= 10, "{:?}", stats.levels[1]);
+    write_one(&storage, key(5, 0), b"scan_prefix", None).await?;
+    assert_eq!(
+        storage.scan_prefix(prefix(5)).await?,
+        vec![KeyTest::new(key(5, 0))]
+    );
+    common::clean(storage, path).await?;
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_auto_sized_filters() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("auto_sized_filters");
+    let builder = || {
+        Builder::new()
+            .work_dir(&path)
+            .blob_file_name_prefix("test")
+            .max_blob_size(1_000_000)
+            .max_data_in_blob(100_000)
+            .set_filter_config(BloomConfig {
+                auto_size: true,
+                ..Default::default()
+            })
+    };
+    let filter_size = pearl::Bloom::new(Default::default()).memory_allocated();
+    let mut storage: Storage<KeyTest> = builder().build()?;
+    storage.init().await?;
+    // blobs of 900, 1000 and 1100 keys
+    for key in 0..3_000 {
+        write_one(&storage, key, b"auto_sized_filters", None).await?;
+        if [899, 1_899, 2_999].contains(&key) {
+            storage.try_close_active_blob().await?;
+        }
+    }
+    // all blobs are closed, filters are sized for their keys
+    let small = storage.filter_memory_allocated().await;
+    assert!(small < filter_size / 10);
+    storage.close().await?;
+
+    let mut storage: Storage<KeyTest> = builder().build()?;
+    storage.init().await?;
+    // blobs with close keys counts have filters of the same size, which are merged
+    assert!(storage.filter_stats().await.levels[0].fill_ratio.is_some());
+    // last blob is active again and gets 1500 keys
+    for key in 3_000..3_400 {
+        write_one(&storage, key, b"auto_sized_filters", None).await?;
+    }
+    storage.try_close_active_blob().await?;
+    sleep(Duration::from_millis(300)).await;
+    // filters of closed blobs are rebuilt for their keys on dump and merged into group filter
+    assert!(storage.filter_memory_allocated().await < filter_size / 10);
+    assert!(storage.filter_stats().await.levels[0].fill_ratio.is_some());
+    for key in 0..3_400 {
+        assert_eq!(storage.check_filters(KeyTest::new(key)).await, Some(true));
+    }
+    let mut false_positives = 0;
+    for key in 10_000..60_000 {
+        if storage.check_filters(KeyTest::new(key)).await == Some(true) {
+            false_positives += 1;
+        }
+    }
+    // 3 blobs with the preferred rate 0.001
+    assert!(false_positives < 500, "{}", false_positives);
+    common::clean(storage, path).await?;
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_legacy_hash_algorithm_filters() -> Result<()> {
+    use pearl::HashAlgorithm;
+    let now = Instant::now();
+    for kind in [FilterKind::Bloom, FilterKind::BlockedBloom] {
+        let path = common::init("legacy_hash_algorithm_filters");
+        let builder = |hash_algorithm| {
+            Builder::new()
+                .work_dir(&path)
+                .blob_file_name_prefix("test")
+                .max_blob_size(1_000_000)
+                .max_data_in_blob(100_000)
+                .set_bloom_filter_group_size(2)
+                .set_filter_config(BloomConfig {
+                    kind,
+                    hash_algorithm,
+                    ..Default::default()
+                })
+        };
+        // bloom filters saved with ahash have the format of filters saved before algorithm
+        // was recorded
+        let mut storage: Storage<KeyTest> = builder(HashAlgorithm::AHash).build()?;
+        storage.init().await?;
+        for key in 0..300 {
+            write_one(&storage, key, b"legacy_hash_algorithm", None).await?;
+            if key % 100 == 99 {
+                storage.try_close_active_blob().await?;
+            }
+        }
+        storage.close().await?;
+
+        let mut storage: Storage<KeyTest> = builder(HashAlgorithm::Xxh3).build()?;
+        storage.init().await?;
+        for key in 300..600 {
+            write_one(&storage, key, b"legacy_hash_algorithm", None).await?;
+            if key % 100 == 99 {
+                storage.try_close_active_blob().await?;
+            }
+        }
+        sleep(Duration::from_millis(300)).await;
+        storage.close().await?;
+
+        let mut storage: Storage<KeyTest> = builder(HashAlgorithm::Xxh3).build()?;
+        storage.init().await?;
+        // old blobs keep their algorithm, new blobs use the configured one; the last old blob
+        // was restored as active, its bloom filter is extended, but static filter is rebuilt
+        let algorithms: Vec<_> = storage
+            .filter_stats()
+            .await
+            .blobs
+            .iter()
+            .map(|blob| (blob.blob_id, blob.hash_algorithm))
+            .collect();
+        assert_eq!(algorithms.len(), 5);
+        for (blob_id, hash_algorithm) in algorithms {
+            let expected = if blob_id < 2 || (blob_id == 2 && kind == FilterKind::Bloom) {
+                HashAlgorithm::AHash
+            } else {
+                HashAlgorithm::Xxh3
+            };
+            assert_eq!(hash_algorithm, Some(expected), "blob {}", blob_id);
+        }
+        // group of blobs 2 and 3 with different algorithms has no bloom filter, its keys are
+        // checked by filters of the blobs
+        for key in 0..600 {
+            assert_eq!(storage.check_filters(KeyTest::new(key)).await, Some(true));
+            assert!(storage.contains(KeyTest::new(key)).await?);
+        }
+        let mut false_positives = 0;
+        for key in 10_000..20_000 {
+            if storage.check_filters(KeyTest::new(key)).await == Some(true) {
+                false_positives += 1;
+            }
+        }
+        assert!(false_positives < 100, "{}", false_positives);
+        common::clean(storage, path).await?;
+    }
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_read_all_load_all() {
     let now = Instant::now();
@@ -582,6 +1881,57 @@ async fn test_check_bloom_filter_multiple_offloaded() {
     warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
 }
 
+#[tokio::test]
+async fn test_load_offloaded_filters() -> Result<()> {
+    let now = Instant::now();
+    let path = common::init("load_offloaded_filters");
+    let mut storage: Storage<KeyTest> = Builder::new()
+        .work_dir(&path)
+        .blob_file_name_prefix("test")
+        .max_blob_size(10_000)
+        .max_data_in_blob(100_000)
+        .set_filter_config(Default::default())
+        .set_bloom_filter_group_size(2)
+        .build()?;
+    storage.init().await?;
+    for key in 0..40 {
+        write_one(&storage, key, b"load_offloaded_filters", None).await?;
+        if key % 10 == 9 {
+            storage.try_close_active_blob().await?;
+        }
+    }
+    sleep(Duration::from_millis(300)).await;
+    // keys are looked up through filters of two groups
+    for key in 1_000..1_100 {
+        assert!(!storage.contains(KeyTest::new(key)).await?);
+    }
+    let lookups = |stats: &pearl::FilterStats| -> Vec<_> {
+        stats.levels.iter().map(|level| level.lookups).collect()
+    };
+    let before = lookups(&storage.filter_stats().await);
+    assert!(before.iter().any(|&count| count > 0), "{:?}", before);
+    let loaded = storage.filter_memory_allocated().await;
+    let freed = storage.offload_buffer(usize::MAX, 100).await;
+    assert!(freed > 0);
+    assert_eq!(storage.filter_memory_allocated().await, loaded - freed);
+    assert_eq!(storage.load_filters().await?, loaded);
+    assert_eq!(storage.filter_memory_allocated().await, loaded);
+    // filters of blobs and of the group are in memory again, group keeps its stats
+    let stats = storage.filter_stats().await;
+    assert!(stats.blobs.iter().all(|blob| blob.fill_ratio.is_some()));
+    assert!(stats.levels.iter().all(|level| level.fill_ratio.is_some()));
+    assert_eq!(lookups(&stats), before);
+    for key in 0..40 {
+        assert_eq!(storage.check_filters(KeyTest::new(key)).await, Some(true));
+    }
+    for key in 1_000..2_000 {
+        assert_eq!(storage.check_filters(KeyTest::new(key)).await, Some(false));
+    }
+    common::clean(storage, path).await?;
+    warn!("elapsed: {:.3}", now.elapsed().as_secs_f64());
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_check_bloom_filter_init_from_existing() {
     let now = Instant::now();